@@ -1,5 +1,103 @@
-// generated by `sqlx migrate build-script`
+use std::{
+    env,
+    fmt::Write as _,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use sha2::{Digest, Sha256};
+
 fn main() {
+    // generated by `sqlx migrate build-script`
     // trigger recompilation when a new migration is added
     println!("cargo:rerun-if-changed=migrations");
+
+    embed_static_assets();
+}
+
+/// Content hashes and precompresses everything under `static/` and writes an `ASSETS`
+/// table for `http::assets` to include, so the files are served from the binary.
+fn embed_static_assets() {
+    println!("cargo:rerun-if-changed=static");
+
+    let static_dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("static");
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    let mut files = Vec::new();
+    collect_files(&static_dir, &mut files);
+    files.sort();
+
+    let mut table = String::from("pub static ASSETS: &[Asset] = &[\n");
+    for file in files {
+        let path = file
+            .strip_prefix(&static_dir)
+            .unwrap()
+            .to_str()
+            .expect("static asset paths should be valid utf-8")
+            .replace('\\', "/");
+        let body = fs::read(&file).unwrap();
+        let hash = hex::encode(&Sha256::digest(&body)[..8]);
+        let hashed_path = match path.rsplit_once('.') {
+            Some((stem, extension)) => format!("{stem}.{hash}.{extension}"),
+            None => format!("{path}.{hash}"),
+        };
+
+        let compressed_name = hashed_path.replace('/', "_");
+        let gzip_file = out_dir.join(format!("{compressed_name}.gz"));
+        fs::write(&gzip_file, gzip(&body)).unwrap();
+        let brotli_file = out_dir.join(format!("{compressed_name}.br"));
+        fs::write(&brotli_file, brotli(&body)).unwrap();
+
+        writeln!(
+            table,
+            "    Asset {{ path: {path:?}, hashed_path: {hashed_path:?}, content_type: {:?}, \
+            body: include_bytes!({file:?}), gzip: include_bytes!({gzip_file:?}), \
+            brotli: include_bytes!({brotli_file:?}) }},",
+            content_type(&path),
+        )
+        .unwrap();
+    }
+    table.push_str("];\n");
+
+    fs::write(out_dir.join("assets.rs"), table).unwrap();
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+fn gzip(body: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(body).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn brotli(body: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    {
+        let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+        writer.write_all(body).unwrap();
+    }
+    compressed
 }
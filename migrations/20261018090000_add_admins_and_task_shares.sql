@@ -0,0 +1,9 @@
+alter table users
+add column is_admin boolean not null default false;
+
+create table task_share(
+    task_id         uuid            not null references task(task_id) on delete cascade,
+    user_id         uuid            not null references users(user_id) on delete cascade,
+    created_at      timestamptz     not null default now(),
+    primary key (task_id, user_id)
+);
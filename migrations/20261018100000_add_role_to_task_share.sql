@@ -0,0 +1,7 @@
+-- shares made before roles existed could edit the task
+alter table task_share
+add column role text not null default 'editor'
+check (role in ('viewer', 'editor'));
+
+alter table task_share
+alter column role drop default;
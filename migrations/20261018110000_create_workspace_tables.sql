@@ -0,0 +1,22 @@
+create table workspace(
+    workspace_id    uuid            primary key default uuid_generate_v1mc(),
+    name            text            not null,
+    created_at      timestamptz     not null default now(),
+    updated_at      timestamptz     not null default now()
+);
+
+select trigger_updated_at('workspace');
+
+create table workspace_member(
+    workspace_id    uuid            not null references workspace(workspace_id) on delete cascade,
+    user_id         uuid            not null references users(user_id) on delete cascade,
+    role            text            not null check (role in ('owner', 'admin', 'member', 'guest')),
+    created_at      timestamptz     not null default now(),
+    primary key (workspace_id, user_id)
+);
+
+-- tasks without a workspace are the creator's personal tasks
+alter table task
+add column workspace_id uuid references workspace(workspace_id) on delete cascade;
+
+create index task_workspace_id_idx on task(workspace_id);
@@ -0,0 +1,15 @@
+alter table task
+add column assignee_id uuid references users(user_id) on delete set null;
+
+create index task_assignee_id_idx on task(assignee_id);
+
+-- every change of assignee, a null assignee_id means the task was unassigned
+create table task_assignment(
+    assignment_id   uuid            primary key default uuid_generate_v1mc(),
+    task_id         uuid            not null references task(task_id) on delete cascade,
+    assignee_id     uuid            references users(user_id) on delete set null,
+    changed_by      uuid            references users(user_id) on delete set null,
+    changed_at      timestamptz     not null default now()
+);
+
+create index task_assignment_task_id_idx on task_assignment(task_id);
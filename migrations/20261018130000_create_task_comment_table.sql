@@ -0,0 +1,16 @@
+create table task_comment(
+    comment_id      uuid            primary key default uuid_generate_v1mc(),
+    task_id         uuid            not null references task(task_id) on delete cascade,
+    -- replies point at the comment they answer, top level comments have no parent
+    parent_id       uuid            references task_comment(comment_id) on delete cascade,
+    author_id       uuid            references users(user_id) on delete set null,
+    body            text            not null,
+    -- deleted comments keep their place in the thread so replies stay readable
+    deleted_at      timestamptz,
+    created_at      timestamptz     not null default now(),
+    updated_at      timestamptz     not null default now()
+);
+
+select trigger_updated_at('task_comment');
+
+create index task_comment_task_id_idx on task_comment(task_id);
@@ -0,0 +1,16 @@
+create table task_attachment(
+    attachment_id   uuid            primary key default uuid_generate_v1mc(),
+    task_id         uuid            not null references task(task_id) on delete cascade,
+    -- counts towards the uploader's quota
+    uploaded_by     uuid            not null references users(user_id) on delete cascade,
+    file_name       text            not null,
+    -- sniffed from the contents, not taken from the upload
+    content_type    text            not null,
+    size_bytes      bigint          not null check (size_bytes >= 0),
+    -- where the blob lives in the configured storage backend
+    storage_key     text            not null unique,
+    created_at      timestamptz     not null default now()
+);
+
+create index task_attachment_task_id_idx on task_attachment(task_id);
+create index task_attachment_uploaded_by_idx on task_attachment(uploaded_by);
@@ -0,0 +1,35 @@
+-- append-only history of what happened to each task, task_id is deliberately not a
+-- foreign key so the history outlives the task and deletions can be traced
+create table task_event(
+    event_id        uuid            primary key default uuid_generate_v1mc(),
+    task_id         uuid            not null,
+    actor_id        uuid            references users(user_id) on delete set null,
+    kind            text            not null
+        check (kind in ('created', 'changed', 'completed', 'reopened', 'deleted')),
+    -- for changed events, which field changed and its values before and after
+    field           text,
+    old_value       text,
+    new_value       text,
+    created_at      timestamptz     not null default now()
+);
+
+create index task_event_task_id_idx on task_event(task_id, created_at);
+
+create or replace function reject_task_event_change()
+    returns trigger as
+$$
+begin
+    raise exception 'task_event is append-only';
+end;
+$$ language plpgsql;
+
+-- actor_id may still be cleared when the actor's account is deleted
+create trigger task_event_append_only
+    before update of task_id, kind, field, old_value, new_value, created_at or delete
+    on task_event
+    for each statement
+execute procedure reject_task_event_change();
+
+-- tasks created before the history existed start with their creation
+insert into task_event (task_id, actor_id, kind, created_at)
+select task_id, user_id, 'created', created_at from task;
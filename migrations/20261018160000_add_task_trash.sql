@@ -0,0 +1,11 @@
+-- deleted tasks stay in the trash until they're restored or purged
+alter table task
+    add column deleted_at timestamptz,
+    add column deleted_by uuid references users(user_id) on delete set null;
+
+create index task_deleted_at_idx on task(deleted_at) where deleted_at is not null;
+
+-- purged is a permanent delete from the trash, expired is the retention period running out
+alter table task_event drop constraint task_event_kind_check;
+alter table task_event add constraint task_event_kind_check
+    check (kind in ('created', 'changed', 'completed', 'reopened', 'deleted', 'restored', 'purged', 'expired'));
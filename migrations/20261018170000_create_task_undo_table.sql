@@ -0,0 +1,12 @@
+-- how to reverse a recent task action, looked up by the signed token in the undo
+-- button of its flash message and applied at most once before it expires
+create table task_undo(
+    undo_id         uuid            primary key default uuid_generate_v1mc(),
+    user_id         uuid            not null references users(user_id) on delete cascade,
+    inverse         jsonb           not null,
+    expires_at      timestamptz     not null,
+    undone_at       timestamptz,
+    created_at      timestamptz     not null default now()
+);
+
+create index task_undo_user_id_idx on task_undo(user_id);
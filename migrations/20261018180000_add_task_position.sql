@@ -0,0 +1,20 @@
+-- tasks are listed in an order users arrange themselves, positions are spread out so a
+-- task can be moved between two others without renumbering the rest
+create sequence task_position_seq;
+
+alter table task add column position bigint;
+
+update task t
+set position = ordered.n * 65536
+from (select task_id, row_number() over (order by created_at, task_id) as n from task) ordered
+where ordered.task_id = t.task_id;
+
+select setval('task_position_seq', (select count(*) from task) + 1, false);
+
+-- new tasks go to the end
+alter table task
+    alter column position set default nextval('task_position_seq') * 65536,
+    alter column position set not null;
+alter sequence task_position_seq owned by task.position;
+
+create index task_position_idx on task(position);
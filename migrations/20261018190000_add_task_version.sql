@@ -0,0 +1,3 @@
+-- bumped by every change to a task's contents, so edits made from a stale copy of the
+-- task can be told apart and rejected
+alter table task add column version integer not null default 1;
@@ -0,0 +1,29 @@
+-- when a task was completed, kept in step with `completed` by the trigger below so
+-- every way of completing or reopening a task sets it
+alter table task add column completed_at timestamptz;
+
+update task t
+set completed_at = coalesce(
+    (select max(e.created_at) from task_event e where e.task_id = t.task_id and e.kind = 'completed'),
+    t.updated_at
+)
+where t.completed;
+
+create or replace function set_completed_at()
+    returns trigger as
+$$
+begin
+    if TG_OP = 'INSERT' or NEW.completed is distinct from OLD.completed then
+        NEW.completed_at = case when NEW.completed then now() end;
+    end if;
+    return NEW;
+end;
+$$ language plpgsql;
+
+create trigger set_completed_at
+    before insert or update of completed
+    on task
+    for each row
+execute function set_completed_at();
+
+create index task_completed_at_idx on task(completed_at) where completed_at is not null;
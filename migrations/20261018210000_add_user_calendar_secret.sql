@@ -0,0 +1,3 @@
+-- part of the signed address of each user's calendar feed, replacing it revokes every
+-- address given out before
+alter table users add column calendar_secret uuid not null default gen_random_uuid();
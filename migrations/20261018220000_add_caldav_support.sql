@@ -0,0 +1,19 @@
+-- tasks created over CalDAV keep the resource name and UID their client gave them,
+-- other tasks are served as {task_id}.ics with their id as the UID
+alter table task
+    add column caldav_name text,
+    add column ical_uid text;
+
+create unique index task_caldav_name_idx on task(user_id, caldav_name);
+
+-- passwords for CalDAV clients, so the account password doesn't need to be stored in them
+create table app_password (
+    app_password_id uuid primary key default uuid_generate_v1mc(),
+    user_id         uuid not null references users(user_id) on delete cascade,
+    name            text not null,
+    password_hash   text not null,
+    last_used_at    timestamptz,
+    created_at      timestamptz not null default now()
+);
+
+create index app_password_user_id_idx on app_password(user_id);
@@ -0,0 +1,22 @@
+-- changes of assignee are recorded in task_event, the ones made before the history
+-- existed are moved there and task_assignment goes. Changes made since were recorded
+-- in both, in the same transaction and so with the same timestamp.
+insert into task_event (task_id, actor_id, kind, field, old_value, new_value, created_at)
+select a.task_id, a.changed_by, 'changed', 'assignee', previous.username, assignee.username, a.changed_at
+from (
+    select
+        task_id,
+        assignee_id,
+        changed_by,
+        changed_at,
+        lag(assignee_id) over (partition by task_id order by changed_at) as previous_id
+    from task_assignment
+) a
+left join users assignee on assignee.user_id = a.assignee_id
+left join users previous on previous.user_id = a.previous_id
+where not exists (
+    select 1 from task_event e
+    where e.task_id = a.task_id and e.field = 'assignee' and e.created_at = a.changed_at
+);
+
+drop table task_assignment;
@@ -0,0 +1,6 @@
+-- resource names only have to be unique within a user's CalDAV collection, so a task in
+-- the trash or moved into a workspace doesn't keep its name from being reused
+drop index task_caldav_name_idx;
+
+create unique index task_caldav_name_idx on task(user_id, caldav_name)
+    where deleted_at is null and workspace_id is null;
@@ -0,0 +1,12 @@
+-- app passwords start with a lookup id, so checking one takes a single password hash.
+-- those made before can't be looked up and have to be made again
+delete from app_password;
+
+alter table app_password add column lookup_id text not null;
+
+create unique index app_password_lookup_id_idx on app_password(lookup_id);
+
+-- failed HTTP Basic logins, to slow down guessing a user's passwords
+alter table users
+    add column basic_auth_failures integer not null default 0,
+    add column basic_auth_failed_at timestamptz;
@@ -0,0 +1,4 @@
+-- when a task should be done by, open tasks past it are overdue
+alter table task add column due_at timestamptz;
+
+create index task_due_at_idx on task(due_at) where due_at is not null and not completed;
@@ -37,7 +37,8 @@ wait_for_postgres() {
   echo "PostgreSQL is up and running!"
 }
 
-# Function to run SQLx database setup
+# Function to run SQLx database setup. The app can't apply its embedded migrations
+# here, as building it needs a migrated database to check its queries against
 setup_database() {
   echo "Setting up the database with SQLx..."
   sqlx db setup
@@ -0,0 +1,196 @@
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use secrecy::SecretString;
+use sqlx::PgPool;
+use tokio::net::TcpListener;
+use tower_sessions::ExpiredDeletion;
+use uuid::Uuid;
+
+use crate::{
+    config::{try_get_config, Settings},
+    http::{self, attachments::Attachments, tasks, users},
+    migrations::run_migrations,
+};
+
+/// Todo web app server and management commands
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serve the web app (the default when no command is given)
+    Serve,
+    /// Apply the migrations embedded in the binary to the database
+    Migrate,
+    /// Register a new user
+    CreateUser {
+        #[arg(long)]
+        email: String,
+        #[arg(long)]
+        username: String,
+        #[arg(long, env = "TODO_USER_PASSWORD", hide_env_values = true)]
+        password: SecretString,
+    },
+    /// Set a new password for the user with the given email
+    ResetPassword {
+        #[arg(long)]
+        email: String,
+        #[arg(long, env = "TODO_USER_PASSWORD", hide_env_values = true)]
+        password: SecretString,
+    },
+    /// Make the user with the given email an admin, who can access every task
+    SetAdmin {
+        #[arg(long)]
+        email: String,
+        /// Take the admin role away instead
+        #[arg(long)]
+        revoke: bool,
+    },
+    /// List all registered users
+    ListUsers,
+    /// Print the history of a task, also of tasks that were deleted
+    TaskHistory {
+        #[arg(long)]
+        task_id: Uuid,
+    },
+    /// Delete expired sessions from the session store
+    PurgeExpiredSessions,
+    /// Permanently delete the tasks that have been in the trash for longer than the
+    /// retention period, which the server also does periodically
+    PurgeTrash,
+    /// Load the configuration for the current APP_ENV and check the database is reachable
+    CheckConfig,
+}
+
+impl Cli {
+    /// Runs the command with the configuration for the current `APP_ENV`. A
+    /// configuration that can't be loaded is returned as the error, so it's reported
+    /// with a non-zero exit code.
+    pub async fn run(self) -> anyhow::Result<()> {
+        let config = try_get_config()?;
+        self.run_with_config(config).await
+    }
+
+    pub async fn run_with_config(self, config: Settings) -> anyhow::Result<()> {
+        match self.command.unwrap_or(Command::Serve) {
+            Command::Serve => {
+                let pool = connect(&config).await?;
+                if config.application.migrate_on_startup {
+                    run_migrations(&pool).await?;
+                }
+                let listener = TcpListener::bind(config.application.ip_addr())
+                    .await
+                    .context("Error binding to the application address")?;
+                http::serve_app(config, pool, listener).await
+            }
+            Command::Migrate => {
+                let pool = connect(&config).await?;
+                run_migrations(&pool).await?;
+                println!("database is up to date");
+                Ok(())
+            }
+            Command::CreateUser {
+                email,
+                username,
+                password,
+            } => {
+                let pool = connect(&config).await?;
+                let password_hash = users::hash_password(&password).await?;
+                users::db::insert_user(&pool, &email, &username, &password_hash)
+                    .await
+                    .with_context(|| format!("Error creating user {username} <{email}>"))?;
+                println!("created user {username} <{email}>");
+                Ok(())
+            }
+            Command::ResetPassword { email, password } => {
+                let pool = connect(&config).await?;
+                let password_hash = users::hash_password(&password).await?;
+                users::db::update_password_hash(&pool, &email, &password_hash)
+                    .await
+                    .with_context(|| format!("Error resetting the password of {email}"))?;
+                println!("reset password of {email}");
+                Ok(())
+            }
+            Command::SetAdmin { email, revoke } => {
+                let pool = connect(&config).await?;
+                users::db::set_admin(&pool, &email, !revoke)
+                    .await
+                    .with_context(|| format!("Error changing the admin role of {email}"))?;
+                if revoke {
+                    println!("{email} is no longer an admin");
+                } else {
+                    println!("{email} is now an admin");
+                }
+                Ok(())
+            }
+            Command::ListUsers => {
+                let pool = connect(&config).await?;
+                for user in users::db::get_all_users(&pool).await? {
+                    println!(
+                        "{}\t{}\t{}\t{}{}",
+                        user.user_id,
+                        user.username,
+                        user.email,
+                        user.created_at,
+                        if user.is_admin { "\tadmin" } else { "" }
+                    );
+                }
+                Ok(())
+            }
+            Command::TaskHistory { task_id } => {
+                let pool = connect(&config).await?;
+                for event in tasks::history::get_history(&pool, task_id).await? {
+                    println!(
+                        "{}\t{}\t{}",
+                        event.created_at,
+                        match (&event.actor_name, event.automatic()) {
+                            (_, true) => "the task",
+                            (Some(actor), false) => actor,
+                            (None, false) => "deleted user",
+                        },
+                        event.describe()
+                    );
+                }
+                Ok(())
+            }
+            Command::PurgeExpiredSessions => {
+                let pool = connect(&config).await?;
+                http::session_store(pool)
+                    .await?
+                    .delete_expired()
+                    .await
+                    .context("Error deleting expired sessions")?;
+                println!("purged expired sessions");
+                Ok(())
+            }
+            Command::PurgeTrash => {
+                let pool = connect(&config).await?;
+                let attachments = Attachments::from_config(&config.attachments)?;
+                let purged =
+                    tasks::trash::purge_expired(&pool, &attachments, config.trash.retention_days)
+                        .await
+                        .context("Error purging the trash")?;
+                println!("purged {purged} tasks from the trash");
+                Ok(())
+            }
+            Command::CheckConfig => {
+                println!("{config:#?}");
+                connect(&config).await?;
+                println!("configuration is valid and the database is reachable");
+                Ok(())
+            }
+        }
+    }
+}
+
+async fn connect(config: &Settings) -> anyhow::Result<PgPool> {
+    config
+        .postgres
+        .get_pool()
+        .await
+        .context("Error getting database connection pool")
+}
@@ -1,6 +1,6 @@
-use std::{env::current_dir, time::Duration};
+use std::{env::current_dir, path::PathBuf, time::Duration};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use config::{Config, File};
 use secrecy::{ExposeSecret, SecretString};
 use sqlx::{
@@ -9,31 +9,40 @@ use sqlx::{
 };
 
 pub fn get_config() -> Settings {
+    try_get_config().expect("Configuration should be valid")
+}
+
+/// Loads the configuration for the current `APP_ENV`, with what is wrong with it when
+/// it can't be loaded.
+pub fn try_get_config() -> anyhow::Result<Settings> {
     let config_dir = current_dir()
-        .expect("Couldn't get current directory")
+        .context("Couldn't get current directory")?
         .join("config");
 
     let app_env: AppEnv = std::env::var("APP_ENV")
         .unwrap_or_else(|_| "dev".into())
         .as_str()
-        .try_into()
-        .expect("The APP_ENV environment variable should be a valid running mode");
+        .try_into()?;
 
     let config = Config::builder()
         .add_source(File::from(config_dir.join("base.yaml")))
         .add_source(File::from(config_dir.join(app_env.config_file())))
         .build()
-        .expect("Source collection of configuration data should pass");
+        .context("Error reading the configuration files")?;
 
     config
         .try_deserialize()
-        .expect("Configuration data should be deserializable into Settings")
+        .context("Error deserializing the configuration into Settings")
 }
 
 #[derive(Debug, serde::Deserialize)]
 pub struct Settings {
     pub application: Application,
     pub postgres: Postgres,
+    #[serde(default)]
+    pub attachments: Attachments,
+    #[serde(default)]
+    pub trash: Trash,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -41,6 +50,13 @@ pub struct Application {
     pub port: u16,
     pub host: String,
     pub hmac_key: SecretString,
+    /// Apply the embedded migrations before serving
+    #[serde(default)]
+    pub migrate_on_startup: bool,
+    /// Serve over HTTPS instead of plain HTTP when present
+    pub tls: Option<Tls>,
+    #[serde(default)]
+    pub security_headers: SecurityHeaders,
 }
 
 impl Application {
@@ -49,6 +65,115 @@ impl Application {
     }
 }
 
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Tls {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// Seconds between checks of the certificate files for changes
+    #[serde(default = "Tls::default_reload_interval")]
+    pub reload_interval: u64,
+    /// Port of an optional plain HTTP listener redirecting every request to HTTPS
+    pub http_redirect_port: Option<u16>,
+}
+
+impl Tls {
+    fn default_reload_interval() -> u64 {
+        60
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct SecurityHeaders {
+    /// Send a nonce based Content-Security-Policy, only allowing inline scripts and
+    /// styles that carry the request's nonce
+    pub content_security_policy: bool,
+    /// max-age of the Strict-Transport-Security header, only sent when serving over HTTPS
+    pub hsts_max_age: u64,
+    pub frame_options: String,
+    pub referrer_policy: String,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        Self {
+            content_security_policy: true,
+            hsts_max_age: 63_072_000,
+            frame_options: "DENY".into(),
+            referrer_policy: "strict-origin-when-cross-origin".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct Attachments {
+    pub storage: Storage,
+    /// Largest file that can be uploaded, in bytes
+    pub max_file_size: u64,
+    /// Total size of the attachments a user can upload, in bytes
+    pub user_quota: u64,
+}
+
+impl Default for Attachments {
+    fn default() -> Self {
+        Self {
+            storage: Storage::Local {
+                path: "attachments".into(),
+            },
+            max_file_size: 10 * 1024 * 1024,
+            user_quota: 100 * 1024 * 1024,
+        }
+    }
+}
+
+/// Where uploaded attachments are kept.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Storage {
+    /// A directory on the local filesystem, created if it doesn't exist
+    Local { path: PathBuf },
+    /// A bucket of an S3 compatible object store
+    S3(S3),
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct S3 {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: SecretString,
+    /// Address the bucket as `endpoint/bucket` instead of `bucket.endpoint`, which
+    /// most self hosted object stores need
+    #[serde(default = "S3::default_path_style")]
+    pub path_style: bool,
+}
+
+impl S3 {
+    fn default_path_style() -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct Trash {
+    /// Days a deleted task stays in the trash before it's purged for good
+    pub retention_days: u32,
+    /// Seconds between runs of the background purge
+    pub purge_interval: u64,
+}
+
+impl Default for Trash {
+    fn default() -> Self {
+        Self {
+            retention_days: 30,
+            purge_interval: 3600,
+        }
+    }
+}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct Postgres {
     pub user: String,
@@ -0,0 +1,122 @@
+use axum::{
+    extract::Path,
+    http::{
+        header::{
+            ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_NONE_MATCH,
+            VARY,
+        },
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use tracing::instrument;
+
+use super::{error::Error, utilities::Result};
+
+/// A file from `static/`, embedded along with its precompressed variants by `build.rs`.
+pub struct Asset {
+    pub path: &'static str,
+    pub hashed_path: &'static str,
+    pub content_type: &'static str,
+    pub body: &'static [u8],
+    pub gzip: &'static [u8],
+    pub brotli: &'static [u8],
+}
+
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+impl Asset {
+    fn etag(&self) -> String {
+        format!("\"{}\"", self.hashed_path)
+    }
+}
+
+/// URL of the content hashed version of an asset, e.g. `/static/css/todos.<hash>.css`.
+pub fn asset_url(path: &str) -> Option<String> {
+    ASSETS
+        .iter()
+        .find(|asset| asset.path == path)
+        .map(|asset| format!("/static/{}", asset.hashed_path))
+}
+
+/// Serves an embedded asset. Hashed paths never change content so they are cached for
+/// a year, the plain path always has to be revalidated.
+#[instrument(skip(headers))]
+pub async fn serve_asset(Path(path): Path<String>, headers: HeaderMap) -> Result<Response> {
+    let (asset, immutable) = ASSETS
+        .iter()
+        .find_map(|asset| {
+            if asset.hashed_path == path {
+                Some((asset, true))
+            } else if asset.path == path {
+                Some((asset, false))
+            } else {
+                None
+            }
+        })
+        .ok_or(Error::NotFound)?;
+
+    let cache_control = if immutable {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+    let etag = asset.etag();
+
+    let not_modified = headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+    if not_modified {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(CACHE_CONTROL, cache_control), (ETAG, etag.as_str())],
+        )
+            .into_response());
+    }
+
+    let (encoding, body) = if accepts_encoding(&headers, "br") {
+        (Some("br"), asset.brotli)
+    } else if accepts_encoding(&headers, "gzip") {
+        (Some("gzip"), asset.gzip)
+    } else {
+        (None, asset.body)
+    };
+
+    let mut response = (
+        [
+            (CONTENT_TYPE, asset.content_type),
+            (CACHE_CONTROL, cache_control),
+            (ETAG, etag.as_str()),
+            (VARY, "accept-encoding"),
+        ],
+        body,
+    )
+        .into_response();
+    if let Some(encoding) = encoding {
+        response
+            .headers_mut()
+            .insert(CONTENT_ENCODING, encoding.parse().unwrap());
+    }
+
+    Ok(response)
+}
+
+fn accepts_encoding(headers: &HeaderMap, encoding: &str) -> bool {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|candidate| {
+            let mut params = candidate.split(';').map(str::trim);
+            let name = params.next().unwrap_or_default();
+            let rejected = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            name.eq_ignore_ascii_case(encoding) && !rejected
+        })
+}
@@ -0,0 +1,197 @@
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::super::{
+    error::Error,
+    tasks::history::{record_event, FieldChange, TaskEventKind, ATTACHMENT_FIELD},
+    utilities::Result,
+};
+
+#[derive(Debug)]
+pub struct Attachment {
+    pub attachment_id: Uuid,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub storage_key: String,
+    pub uploader_name: String,
+    pub created_at: OffsetDateTime,
+}
+
+impl Attachment {
+    /// Images are shown in the browser, everything else is downloaded. SVGs can carry
+    /// scripts so they're downloaded too.
+    pub fn is_image(&self) -> bool {
+        self.content_type.starts_with("image/") && self.content_type != "image/svg+xml"
+    }
+}
+
+#[instrument(skip_all, fields(%task_id))]
+pub async fn get_attachments(pool: &PgPool, task_id: Uuid) -> Result<Vec<Attachment>> {
+    sqlx::query_as!(
+        Attachment,
+        r#"
+        select
+            a.attachment_id,
+            a.file_name,
+            a.content_type,
+            a.size_bytes,
+            a.storage_key,
+            u.username as uploader_name,
+            a.created_at
+        from task_attachment a
+        join users u on u.user_id = a.uploaded_by
+        where a.task_id = $1
+        order by a.created_at
+        "#,
+        task_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+#[instrument(skip_all, fields(%task_id, %attachment_id))]
+pub async fn get_attachment(
+    pool: &PgPool,
+    task_id: Uuid,
+    attachment_id: Uuid,
+) -> Result<Option<Attachment>> {
+    sqlx::query_as!(
+        Attachment,
+        r#"
+        select
+            a.attachment_id,
+            a.file_name,
+            a.content_type,
+            a.size_bytes,
+            a.storage_key,
+            u.username as uploader_name,
+            a.created_at
+        from task_attachment a
+        join users u on u.user_id = a.uploaded_by
+        where a.task_id = $1 and a.attachment_id = $2
+        "#,
+        task_id,
+        attachment_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// Bytes of attachments the user has uploaded, counted against their quota.
+#[instrument(skip(pool))]
+pub async fn used_quota(pool: &PgPool, user_id: Uuid) -> Result<i64> {
+    sqlx::query_scalar!(
+        r#"
+        select coalesce(sum(size_bytes), 0)::bigint as "used!"
+        from task_attachment
+        where uploaded_by = $1
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+pub struct NewAttachment<'a> {
+    pub task_id: Uuid,
+    pub uploaded_by: Uuid,
+    pub file_name: &'a str,
+    pub content_type: &'a str,
+    pub size_bytes: i64,
+    pub storage_key: &'a str,
+}
+
+/// Records an uploaded attachment and adds it to the task's history, unless it would
+/// take the uploader over `quota` bytes. Returns whether it was recorded. The
+/// uploader's row is locked before the check, so concurrent uploads by the same user
+/// are checked one after the other and can't both squeeze under the quota.
+#[instrument(skip_all, fields(task_id = %attachment.task_id, uploaded_by = %attachment.uploaded_by))]
+pub async fn create_attachment(
+    pool: &PgPool,
+    attachment: NewAttachment<'_>,
+    quota: i64,
+) -> Result<bool> {
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        "select 1 as locked from users where user_id = $1 for update",
+        attachment.uploaded_by
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    let query_result = sqlx::query!(
+        r#"
+        insert into task_attachment
+            (task_id, uploaded_by, file_name, content_type, size_bytes, storage_key)
+        select $1, $2, $3, $4, $5::bigint, $6
+        where (
+            select coalesce(sum(size_bytes), 0) from task_attachment where uploaded_by = $2
+        ) + $5::bigint <= $7::bigint
+        "#,
+        attachment.task_id,
+        attachment.uploaded_by,
+        attachment.file_name,
+        attachment.content_type,
+        attachment.size_bytes,
+        attachment.storage_key,
+        quota
+    )
+    .execute(&mut *transaction)
+    .await?;
+    if query_result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    let change = FieldChange::new(ATTACHMENT_FIELD, None, Some(attachment.file_name));
+    record_event(
+        &mut transaction,
+        attachment.task_id,
+        attachment.uploaded_by,
+        TaskEventKind::Changed,
+        change,
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(true)
+}
+
+/// Deletes the attachment and adds its removal to the task's history.
+#[instrument(skip(pool))]
+pub async fn delete_attachment(
+    pool: &PgPool,
+    task_id: Uuid,
+    attachment_id: Uuid,
+    deleted_by: Uuid,
+) -> Result<()> {
+    let mut transaction = pool.begin().await?;
+    let file_name = sqlx::query_scalar!(
+        r#"
+        delete from task_attachment
+        where task_id = $1 and attachment_id = $2
+        returning file_name
+        "#,
+        task_id,
+        attachment_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let change = FieldChange::new(ATTACHMENT_FIELD, Some(&file_name), None);
+    record_event(
+        &mut transaction,
+        task_id,
+        deleted_by,
+        TaskEventKind::Changed,
+        change,
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
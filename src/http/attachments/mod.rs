@@ -0,0 +1,50 @@
+use tracing::error;
+
+use crate::{
+    config,
+    storage::{AnyStorage, Storage},
+};
+
+pub(crate) mod db;
+mod routes;
+
+pub use db::Attachment;
+pub use routes::router;
+
+/// Where attachments are stored and how much can be uploaded.
+#[derive(Debug, Clone)]
+pub struct Attachments {
+    pub storage: AnyStorage,
+    pub max_file_size: u64,
+    pub user_quota: u64,
+}
+
+impl Attachments {
+    pub fn from_config(config: &config::Attachments) -> anyhow::Result<Self> {
+        Ok(Self {
+            storage: AnyStorage::from_config(&config.storage)?,
+            max_file_size: config.max_file_size,
+            user_quota: config.user_quota,
+        })
+    }
+
+    /// Removes the blobs of attachments whose rows are already gone, e.g. with their
+    /// task. Failures are only logged, the attachments can't be reached anymore.
+    pub async fn delete_blobs(&self, storage_keys: &[String]) {
+        for key in storage_keys {
+            if let Err(error) = self.storage.delete(key).await {
+                error!(?error, %key, "couldn't delete attachment blob");
+            }
+        }
+    }
+}
+
+/// The content type of an upload, judged by its contents rather than by what the
+/// browser claims. Text that isn't a known format is served as plain text.
+pub fn sniff_content_type(data: &[u8]) -> &'static str {
+    match infer::get(data) {
+        Some(kind) => kind.mime_type(),
+        None if std::str::from_utf8(data).is_ok() => "text/plain; charset=utf-8",
+        None => "application/octet-stream",
+    }
+}
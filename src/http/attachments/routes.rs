@@ -0,0 +1,244 @@
+use axum::{
+    extract::{multipart::Field, DefaultBodyLimit, Multipart, Path, State},
+    http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE},
+    middleware::from_fn,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Extension, Router,
+};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use crate::{
+    http::users::{auth_middleware, UserSessionData},
+    storage::Storage,
+};
+
+use super::super::{
+    authorization::{authorize_task, TaskAction},
+    error::Error,
+    filters::format_size,
+    utilities::{ApiState, FlashMessageLevel, FlashMessages, Result},
+    validation::Validator,
+};
+
+use super::{
+    db::{self, NewAttachment},
+    sniff_content_type, Attachments,
+};
+
+/// Routes under `/todo/{task_id}/attachments`, attachments are listed on the task
+/// page. Uploads may be a little bigger than `max_file_size` to fit the multipart
+/// framing, the file itself is checked against it.
+pub fn router(max_file_size: u64) -> Router<ApiState> {
+    let body_limit = usize::try_from(max_file_size)
+        .unwrap_or(usize::MAX)
+        .saturating_add(MULTIPART_OVERHEAD);
+    Router::new()
+        .route(
+            "/{task_id}/attachments",
+            post(upload_attachment).layer(DefaultBodyLimit::max(body_limit)),
+        )
+        .route(
+            "/{task_id}/attachments/{attachment_id}",
+            get(download_attachment),
+        )
+        .route(
+            "/{task_id}/attachments/{attachment_id}/delete",
+            post(delete_attachment),
+        )
+        .route_layer(from_fn(auth_middleware))
+}
+
+const MULTIPART_OVERHEAD: usize = 64 * 1024;
+const MAX_FILE_NAME_CHARS: usize = 255;
+
+fn task_page(task_id: Uuid) -> Redirect {
+    Redirect::to(&format!("/todo/{task_id}#attachments"))
+}
+
+/// Browsers may send a full path as the file name, only the last part is kept.
+fn clean_file_name(name: &str) -> String {
+    let name: String = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_FILE_NAME_CHARS)
+        .collect();
+    match name.trim() {
+        "" => "attachment".into(),
+        name => name.into(),
+    }
+}
+
+fn multipart_error(error: impl ToString) -> Error {
+    Error::unprocessable_entity([("file", error.to_string())])
+}
+
+/// Reads the uploaded file, stopping as soon as it's over `max_size` bytes.
+async fn read_file(mut field: Field<'_>, max_size: u64) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+        data.extend_from_slice(&chunk);
+        if data.len() as u64 > max_size {
+            return Err(Error::unprocessable_entity([(
+                "file",
+                format!(
+                    "files can be at most {}",
+                    format_size(max_size.try_into().unwrap_or(i64::MAX))
+                ),
+            )]));
+        }
+    }
+    Ok(data)
+}
+
+struct Upload {
+    file_name: String,
+    data: Vec<u8>,
+}
+
+async fn read_upload(multipart: &mut Multipart, max_size: u64) -> Result<Upload> {
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let file_name = clean_file_name(field.file_name().unwrap_or_default());
+        let data = read_file(field, max_size).await?;
+        Validator::new()
+            .check("file", !data.is_empty(), "choose a file to upload")
+            .finish()?;
+        return Ok(Upload { file_name, data });
+    }
+    Err(multipart_error("choose a file to upload"))
+}
+
+#[instrument(skip_all, fields(action = "uploading an attachment", %task_id, %user_session))]
+async fn upload_attachment(
+    State(pool): State<PgPool>,
+    State(attachments): State<Attachments>,
+    Path(task_id): Path<Uuid>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+    mut multipart: Multipart,
+) -> Result<Redirect> {
+    authorize_task(&pool, &user_session, task_id, TaskAction::Edit).await?;
+    let upload = match read_upload(&mut multipart, attachments.max_file_size).await {
+        Ok(upload) => upload,
+        Err(error) => {
+            flash_msgs.set_invalid(error).await?;
+            return Ok(task_page(task_id));
+        }
+    };
+
+    let quota = i64::try_from(attachments.user_quota).unwrap_or(i64::MAX);
+    let size_bytes = upload.data.len() as i64;
+    let quota_exceeded = format!(
+        "Uploading {} would take you over your {} attachment quota",
+        upload.file_name,
+        format_size(quota)
+    );
+    if db::used_quota(&pool, user_session.user_id()).await? + size_bytes > quota {
+        flash_msgs
+            .set_msg(FlashMessageLevel::Error, &quota_exceeded)
+            .await?;
+        return Ok(task_page(task_id));
+    }
+
+    let content_type = sniff_content_type(&upload.data);
+    let storage_key = format!("{task_id}/{}", Uuid::new_v4());
+    attachments
+        .storage
+        .put(&storage_key, upload.data.into(), content_type)
+        .await?;
+
+    // the blob goes first so a recorded attachment can always be downloaded, it's
+    // removed again when the attachment can't be recorded
+    let recorded = db::create_attachment(
+        &pool,
+        NewAttachment {
+            task_id,
+            uploaded_by: user_session.user_id(),
+            file_name: &upload.file_name,
+            content_type,
+            size_bytes,
+            storage_key: &storage_key,
+        },
+        quota,
+    )
+    .await;
+    match recorded {
+        Ok(true) => {}
+        Ok(false) => {
+            attachments.delete_blobs(&[storage_key]).await;
+            flash_msgs
+                .set_msg(FlashMessageLevel::Error, &quota_exceeded)
+                .await?;
+            return Ok(task_page(task_id));
+        }
+        Err(error) => {
+            attachments.delete_blobs(&[storage_key]).await;
+            return Err(error);
+        }
+    }
+
+    info!(%content_type, size_bytes, "attachment uploaded");
+    Ok(task_page(task_id))
+}
+
+/// Serves an attachment to anyone who can see its task. Images are shown inline,
+/// anything else is sent as a download so it's never rendered as part of the site.
+#[instrument(skip_all, fields(action = "downloading an attachment", %task_id, %attachment_id, %user_session))]
+async fn download_attachment(
+    State(pool): State<PgPool>,
+    State(attachments): State<Attachments>,
+    Path((task_id, attachment_id)): Path<(Uuid, Uuid)>,
+    Extension(user_session): Extension<UserSessionData>,
+) -> Result<Response> {
+    authorize_task(&pool, &user_session, task_id, TaskAction::View).await?;
+    let attachment = db::get_attachment(&pool, task_id, attachment_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let data = attachments.storage.get(&attachment.storage_key).await?;
+
+    let disposition = format!(
+        "{}; filename*=UTF-8''{}",
+        if attachment.is_image() {
+            "inline"
+        } else {
+            "attachment"
+        },
+        urlencoding::encode(&attachment.file_name)
+    );
+    Ok((
+        [
+            (CONTENT_TYPE, attachment.content_type),
+            (CONTENT_DISPOSITION, disposition),
+            (CONTENT_LENGTH, data.len().to_string()),
+            (CACHE_CONTROL, "private, no-cache".into()),
+        ],
+        data,
+    )
+        .into_response())
+}
+
+#[instrument(skip_all, fields(action = "deleting an attachment", %task_id, %attachment_id, %user_session))]
+async fn delete_attachment(
+    State(pool): State<PgPool>,
+    State(attachments): State<Attachments>,
+    Path((task_id, attachment_id)): Path<(Uuid, Uuid)>,
+    Extension(user_session): Extension<UserSessionData>,
+) -> Result<Redirect> {
+    authorize_task(&pool, &user_session, task_id, TaskAction::Edit).await?;
+    let attachment = db::get_attachment(&pool, task_id, attachment_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    db::delete_attachment(&pool, task_id, attachment_id, user_session.user_id()).await?;
+    attachments.delete_blobs(&[attachment.storage_key]).await;
+
+    Ok(task_page(task_id))
+}
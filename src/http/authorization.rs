@@ -0,0 +1,305 @@
+use std::fmt::Display;
+
+use serde::Deserialize;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+use super::{error::Error, users::UserSessionData, utilities::Result};
+
+/// Something a user wants to do with an existing task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskAction {
+    /// Seeing the task and its comments, anyone with access to it can.
+    View,
+    Comment,
+    Edit,
+    Delete,
+    /// Changing who the task is shared with.
+    Share,
+}
+
+/// What a user a task was shared with may do with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum ShareRole {
+    Viewer,
+    Editor,
+}
+
+impl Display for ShareRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Viewer => write!(f, "viewer"),
+            Self::Editor => write!(f, "editor"),
+        }
+    }
+}
+
+/// A user's role in a workspace, from most to least privileged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum WorkspaceRole {
+    Owner,
+    Admin,
+    Member,
+    Guest,
+}
+
+impl WorkspaceRole {
+    pub fn allows(self, action: WorkspaceAction) -> bool {
+        match action {
+            WorkspaceAction::View => true,
+            WorkspaceAction::CreateTasks => self != Self::Guest,
+            WorkspaceAction::ManageMembers => matches!(self, Self::Owner | Self::Admin),
+        }
+    }
+}
+
+impl Display for WorkspaceRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Owner => write!(f, "owner"),
+            Self::Admin => write!(f, "admin"),
+            Self::Member => write!(f, "member"),
+            Self::Guest => write!(f, "guest"),
+        }
+    }
+}
+
+/// Something a user wants to do in a workspace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkspaceAction {
+    /// Seeing the workspace, its members and its tasks.
+    View,
+    CreateTasks,
+    /// Inviting and removing members and changing their roles.
+    ManageMembers,
+}
+
+/// A policy that lets a user act on a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grant {
+    /// The user created the task, owners can do anything with it.
+    Owner,
+    /// The task was shared with the user, editors can edit it, nobody it was shared
+    /// with can delete or reshare it.
+    SharedWith(ShareRole),
+    /// The task is in a workspace the user is a member of. Workspace owners and admins
+    /// can do anything with it, members can edit it and guests can only see and
+    /// comment on it.
+    Workspace(WorkspaceRole),
+    /// Admins can do anything with any task. The flag is read from the database on
+    /// every check, so revoking it takes effect without logging the admin out.
+    Admin,
+}
+
+impl Grant {
+    pub fn allows(self, action: TaskAction) -> bool {
+        match (self, action) {
+            (_, TaskAction::View | TaskAction::Comment) => true,
+            (Self::Owner | Self::Admin, _) => true,
+            (Self::SharedWith(role), TaskAction::Edit) => role == ShareRole::Editor,
+            (Self::Workspace(WorkspaceRole::Owner | WorkspaceRole::Admin), _) => true,
+            (Self::Workspace(WorkspaceRole::Member), TaskAction::Edit) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Everything that gives a user access to a task. A user can hold several grants,
+/// e.g. a workspace guest the task was also shared with as an editor, and may do
+/// whatever any of them allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grants(Vec<Grant>);
+
+impl Grants {
+    pub fn allows(&self, action: TaskAction) -> bool {
+        self.0.iter().any(|grant| grant.allows(action))
+    }
+
+    /// Whether only being an admin allows `action`, which is audited.
+    fn only_admin_allows(&self, action: TaskAction) -> bool {
+        self.0.contains(&Grant::Admin)
+            && !self
+                .0
+                .iter()
+                .any(|&grant| grant != Grant::Admin && grant.allows(action))
+    }
+}
+
+/// The grants a user holds on a task, most specific first.
+pub fn task_grants(
+    owned: bool,
+    is_admin: bool,
+    workspace_role: Option<WorkspaceRole>,
+    share_role: Option<ShareRole>,
+) -> Grants {
+    Grants(
+        [
+            owned.then_some(Grant::Owner),
+            workspace_role.map(Grant::Workspace),
+            share_role.map(Grant::SharedWith),
+            is_admin.then_some(Grant::Admin),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+    )
+}
+
+/// Checks the task policies for `user` doing `action` on a task. Missing tasks and
+/// tasks in the trash are [`Error::NotFound`], tasks the user has no access to are
+/// [`Error::Forbidden`] and every denial is written to the `audit` log target. Returns
+/// all of the user's grants, to check other actions against.
+#[instrument(skip(pool, user), fields(%user))]
+pub async fn authorize_task(
+    pool: &PgPool,
+    user: &UserSessionData,
+    task_id: Uuid,
+    action: TaskAction,
+) -> Result<Grants> {
+    authorize(pool, user, task_id, action, false).await
+}
+
+/// Checks `user` may restore or purge a task in the trash, which takes the same
+/// grant as deleting it. Tasks that aren't in the trash are [`Error::NotFound`].
+#[instrument(skip(pool, user), fields(%user))]
+pub async fn authorize_trashed_task(
+    pool: &PgPool,
+    user: &UserSessionData,
+    task_id: Uuid,
+) -> Result<Grants> {
+    authorize(pool, user, task_id, TaskAction::Delete, true).await
+}
+
+/// Like [`authorize_task`], but in the caller's transaction with the task locked until
+/// it ends, so the task can't change hands between the check and the caller's changes.
+#[instrument(skip(conn, user), fields(%user))]
+pub async fn authorize_task_for_update(
+    conn: &mut PgConnection,
+    user: &UserSessionData,
+    task_id: Uuid,
+    action: TaskAction,
+) -> Result<Grants> {
+    sqlx::query!(
+        "select 1 as locked from task where task_id = $1 for update",
+        task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    authorize(&mut *conn, user, task_id, action, false).await
+}
+
+async fn authorize(
+    executor: impl PgExecutor<'_>,
+    user: &UserSessionData,
+    task_id: Uuid,
+    action: TaskAction,
+    trashed: bool,
+) -> Result<Grants> {
+    let task = sqlx::query!(
+        r#"
+        select
+            t.user_id as owner_id,
+            (
+                select s.role from task_share s
+                where s.task_id = t.task_id and s.user_id = $2
+            ) as "share_role: ShareRole",
+            (
+                select m.role from workspace_member m
+                where m.workspace_id = t.workspace_id and m.user_id = $2
+            ) as "workspace_role: WorkspaceRole",
+            coalesce((select u.is_admin from users u where u.user_id = $2), false) as "is_admin!"
+        from task t
+        where t.task_id = $1 and (t.deleted_at is not null) = $3
+        "#,
+        task_id,
+        user.user_id(),
+        trashed
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let grants = task_grants(
+        task.owner_id == user.user_id(),
+        task.is_admin,
+        task.workspace_role,
+        task.share_role,
+    );
+
+    if !grants.allows(action) {
+        warn!(
+            target: "audit",
+            user_id = %user.user_id(),
+            %task_id,
+            owner_id = %task.owner_id,
+            ?action,
+            trashed,
+            ?grants,
+            "denied access to task"
+        );
+        return Err(Error::Forbidden);
+    }
+    if grants.only_admin_allows(action) {
+        info!(
+            target: "audit",
+            user_id = %user.user_id(),
+            %task_id,
+            owner_id = %task.owner_id,
+            ?action,
+            "admin acting on a task of another user"
+        );
+    }
+    Ok(grants)
+}
+
+/// Checks `user`'s role in a workspace allows `action`. Workspaces the user isn't a
+/// member of are [`Error::NotFound`] so their existence isn't leaked, denials of
+/// members are [`Error::Forbidden`] and written to the `audit` log target.
+#[instrument(skip(pool, user), fields(%user))]
+pub async fn authorize_workspace(
+    pool: &PgPool,
+    user: &UserSessionData,
+    workspace_id: Uuid,
+    action: WorkspaceAction,
+) -> Result<WorkspaceRole> {
+    let role = sqlx::query_scalar!(
+        r#"
+        select role as "role: WorkspaceRole" from workspace_member
+        where workspace_id = $1 and user_id = $2
+        "#,
+        workspace_id,
+        user.user_id()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    match role {
+        Some(role) if role.allows(action) => Ok(role),
+        Some(role) => {
+            warn!(
+                target: "audit",
+                user_id = %user.user_id(),
+                %workspace_id,
+                %role,
+                ?action,
+                "denied access to workspace"
+            );
+            Err(Error::Forbidden)
+        }
+        None => {
+            warn!(
+                target: "audit",
+                user_id = %user.user_id(),
+                %workspace_id,
+                ?action,
+                "non member tried to access workspace"
+            );
+            Err(Error::NotFound)
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use sqlx::{PgConnection, PgPool};
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::super::{error::Error, tasks::db as tasks_db, utilities::Result};
+
+/// A task in a user's CalDAV collection: one of their own that isn't in a workspace.
+#[derive(Debug)]
+pub struct CalDavTask {
+    pub task_id: Uuid,
+    /// The resource name the task is served under.
+    pub name: String,
+    pub uid: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+    pub completed_at: Option<OffsetDateTime>,
+    pub assignee_id: Option<Uuid>,
+    pub due_at: Option<OffsetDateTime>,
+    pub version: i32,
+}
+
+#[instrument(skip(pool))]
+pub async fn get_collection(pool: &PgPool, user_id: Uuid) -> Result<Vec<CalDavTask>> {
+    sqlx::query_as!(
+        CalDavTask,
+        r#"
+        select
+            task_id,
+            coalesce(caldav_name, task_id::text || '.ics') as "name!",
+            coalesce(ical_uid, task_id::text) as "uid!",
+            title,
+            description,
+            created_at,
+            updated_at,
+            completed_at,
+            assignee_id,
+            due_at,
+            version
+        from task
+        where user_id = $1 and workspace_id is null and deleted_at is null
+        order by position, created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// The tasks in the user's collection with these resource names, missing ones left out.
+#[instrument(skip(pool))]
+pub async fn get_tasks_by_name(
+    pool: &PgPool,
+    user_id: Uuid,
+    names: &[String],
+) -> Result<Vec<CalDavTask>> {
+    sqlx::query_as!(
+        CalDavTask,
+        r#"
+        select
+            task_id,
+            coalesce(caldav_name, task_id::text || '.ics') as "name!",
+            coalesce(ical_uid, task_id::text) as "uid!",
+            title,
+            description,
+            created_at,
+            updated_at,
+            completed_at,
+            assignee_id,
+            due_at,
+            version
+        from task
+        where user_id = $1 and workspace_id is null and deleted_at is null
+            and coalesce(caldav_name, task_id::text || '.ics') = any($2)
+        "#,
+        user_id,
+        names
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// The task served under `name`, locked until the caller's transaction ends so a `PUT`
+/// can check its version before writing.
+#[instrument(skip(conn))]
+pub async fn get_task_by_name(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    name: &str,
+) -> Result<Option<CalDavTask>> {
+    sqlx::query_as!(
+        CalDavTask,
+        r#"
+        select
+            task_id,
+            coalesce(caldav_name, task_id::text || '.ics') as "name!",
+            coalesce(ical_uid, task_id::text) as "uid!",
+            title,
+            description,
+            created_at,
+            updated_at,
+            completed_at,
+            assignee_id,
+            due_at,
+            version
+        from task
+        where user_id = $1 and workspace_id is null and deleted_at is null
+            and coalesce(caldav_name, task_id::text || '.ics') = $2
+        for update
+        "#,
+        user_id,
+        name
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// Changes whenever a task in the collection is added, changed or removed, so clients
+/// can skip syncing an unchanged collection.
+#[instrument(skip(pool))]
+pub async fn get_ctag(pool: &PgPool, user_id: Uuid) -> Result<String> {
+    sqlx::query_scalar!(
+        r#"
+        select md5(coalesce(string_agg(task_id::text || ':' || version, ',' order by task_id), ''))
+            as "ctag!"
+        from task
+        where user_id = $1 and workspace_id is null and deleted_at is null
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// Creates a task sent by a client under the resource name and UID it chose, in the
+/// caller's transaction.
+#[allow(clippy::too_many_arguments)]
+#[instrument(skip(conn))]
+pub async fn create_task(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    name: &str,
+    uid: Option<&str>,
+    title: &str,
+    description: &str,
+    completed: bool,
+    due_at: Option<OffsetDateTime>,
+) -> Result<i32> {
+    let task_id = tasks_db::insert_task(conn, title, description, due_at, user_id, None).await?;
+    sqlx::query!(
+        "update task set caldav_name = $2, ical_uid = $3 where task_id = $1",
+        task_id,
+        name,
+        uid
+    )
+    .execute(&mut *conn)
+    .await?;
+    if completed {
+        tasks_db::set_completed(conn, task_id, true, user_id).await?;
+    }
+
+    sqlx::query_scalar!("select version from task where task_id = $1", task_id)
+        .fetch_one(conn)
+        .await
+        .map_err(Error::SQLx)
+}
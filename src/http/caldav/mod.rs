@@ -0,0 +1,9 @@
+//! A CalDAV server (RFC 4791) for two-way sync with task apps. It serves the subset
+//! they need: one `VTODO` collection with the user's own tasks, found with `PROPFIND`
+//! and `REPORT` and written with `PUT` and `DELETE`, with task versions as ETags.
+
+mod db;
+mod routes;
+mod xml;
+
+pub use routes::router;
@@ -0,0 +1,416 @@
+use axum::{
+    extract::{Path, State},
+    http::{
+        header::{ALLOW, CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH},
+        HeaderMap, HeaderName, HeaderValue, Method, StatusCode,
+    },
+    response::{IntoResponse, Redirect, Response},
+    routing::any,
+    Router,
+};
+use sqlx::PgPool;
+use tracing::{debug, instrument};
+
+use crate::http::users::{BasicAuth, UserSessionData};
+
+use super::super::{
+    calendar::ical::{calendar, parse_todo, VTodo},
+    error::Error,
+    tasks::{db as tasks_db, validate_task},
+    utilities::{etag_listed, ApiState, Result},
+};
+use super::{
+    db::{self, CalDavTask},
+    xml::{
+        parse_request, DavRequest, Multistatus, Name, Prop, PropRequest, CALDAV, CALENDARSERVER,
+        DAV,
+    },
+};
+
+/// The principal and calendar home of whoever is logged in.
+const ROOT: &str = "/caldav/";
+/// The one calendar collection, holding the user's own tasks outside workspaces.
+const COLLECTION: &str = "/caldav/tasks/";
+const ALLOWED_METHODS: &str = "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT";
+const TODO_CONTENT_TYPE: &str = "text/calendar; charset=utf-8; component=VTODO";
+const MAX_NAME_LEN: usize = 255;
+
+/// Every path answers OPTIONS and PROPFIND, which axum has no method filter for, so
+/// methods are told apart in the handlers rather than by the router.
+pub fn router() -> Router<ApiState> {
+    Router::new()
+        .route("/.well-known/caldav", any(well_known))
+        .route("/caldav", any(root))
+        .route(ROOT, any(root))
+        .route("/caldav/tasks", any(collection))
+        .route(COLLECTION, any(collection))
+        .route("/caldav/tasks/{name}", any(resource))
+}
+
+/// Where clients set up with just the site's address find the server, RFC 6764.
+async fn well_known() -> Redirect {
+    Redirect::permanent(ROOT)
+}
+
+#[instrument(skip_all, fields(action = "serving caldav principal", %user_session, %method))]
+async fn root(
+    method: Method,
+    headers: HeaderMap,
+    State(pool): State<PgPool>,
+    BasicAuth(user_session): BasicAuth,
+    body: String,
+) -> Result<Response> {
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => {
+            let Some(request) = read_request(&body) else {
+                return Ok(StatusCode::BAD_REQUEST.into_response());
+            };
+            let mut multistatus = Multistatus::new();
+            multistatus.response(ROOT, root_props(&user_session), &request.props);
+            if depth(&headers) > 0 {
+                let ctag = db::get_ctag(&pool, user_session.user_id()).await?;
+                multistatus.response(COLLECTION, collection_props(&ctag), &request.props);
+            }
+            Ok(multistatus.into_response())
+        }
+        _ => Ok(method_not_allowed()),
+    }
+}
+
+#[instrument(skip_all, fields(action = "serving caldav collection", %user_session, %method))]
+async fn collection(
+    method: Method,
+    headers: HeaderMap,
+    State(pool): State<PgPool>,
+    BasicAuth(user_session): BasicAuth,
+    body: String,
+) -> Result<Response> {
+    let user_id = user_session.user_id();
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => {
+            let Some(request) = read_request(&body) else {
+                return Ok(StatusCode::BAD_REQUEST.into_response());
+            };
+            let mut multistatus = Multistatus::new();
+            let ctag = db::get_ctag(&pool, user_id).await?;
+            multistatus.response(COLLECTION, collection_props(&ctag), &request.props);
+            if depth(&headers) > 0 {
+                for task in db::get_collection(&pool, user_id).await? {
+                    multistatus.response(
+                        &href(&task),
+                        task_props(&task, &request.props),
+                        &request.props,
+                    );
+                }
+            }
+            Ok(multistatus.into_response())
+        }
+        "REPORT" => {
+            let Some(request) = read_request(&body) else {
+                return Ok(StatusCode::BAD_REQUEST.into_response());
+            };
+            report(&pool, user_id, request).await
+        }
+        _ => Ok(method_not_allowed()),
+    }
+}
+
+/// `calendar-query` returns every task unless it's filtered to components other than
+/// todos, time ranges and property filters aren't applied. `calendar-multiget` returns
+/// the tasks asked for.
+async fn report(pool: &PgPool, user_id: uuid::Uuid, request: DavRequest) -> Result<Response> {
+    let mut multistatus = Multistatus::new();
+    match request.root.as_ref().map(|root| root.local.as_str()) {
+        Some("calendar-query") => {
+            let todos_wanted = request
+                .comp_filters
+                .iter()
+                .all(|component| component == "VCALENDAR" || component == "VTODO");
+            if todos_wanted {
+                for task in db::get_collection(pool, user_id).await? {
+                    multistatus.response(
+                        &href(&task),
+                        task_props(&task, &request.props),
+                        &request.props,
+                    );
+                }
+            }
+        }
+        Some("calendar-multiget") => {
+            let names: Vec<String> = request
+                .hrefs
+                .iter()
+                .filter_map(|href| name_from_href(href))
+                .collect();
+            let tasks = db::get_tasks_by_name(pool, user_id, &names).await?;
+            for requested in &request.hrefs {
+                let task = name_from_href(requested)
+                    .and_then(|name| tasks.iter().find(|task| task.name == name));
+                match task {
+                    Some(task) => multistatus.response(
+                        requested,
+                        task_props(task, &request.props),
+                        &request.props,
+                    ),
+                    None => multistatus.not_found(requested),
+                }
+            }
+        }
+        report => {
+            debug!(?report, "unsupported report");
+            return Ok(StatusCode::FORBIDDEN.into_response());
+        }
+    }
+    Ok(multistatus.into_response())
+}
+
+#[instrument(skip_all, fields(action = "serving caldav resource", %user_session, %method, %name))]
+async fn resource(
+    method: Method,
+    headers: HeaderMap,
+    State(pool): State<PgPool>,
+    BasicAuth(user_session): BasicAuth,
+    Path(name): Path<String>,
+    body: String,
+) -> Result<Response> {
+    let user_id = user_session.user_id();
+    if method.as_str() == "OPTIONS" {
+        return Ok(options());
+    }
+    if !valid_name(&name) {
+        return Err(match method.as_str() {
+            "PUT" => Error::Forbidden,
+            _ => Error::NotFound,
+        });
+    }
+
+    match method.as_str() {
+        "GET" | "HEAD" | "PROPFIND" => {
+            let task = db::get_tasks_by_name(&pool, user_id, &[name])
+                .await?
+                .pop()
+                .ok_or(Error::NotFound)?;
+            if method.as_str() == "PROPFIND" {
+                let Some(request) = read_request(&body) else {
+                    return Ok(StatusCode::BAD_REQUEST.into_response());
+                };
+                let mut multistatus = Multistatus::new();
+                multistatus.response(
+                    &href(&task),
+                    task_props(&task, &request.props),
+                    &request.props,
+                );
+                return Ok(multistatus.into_response());
+            }
+            Ok((
+                [
+                    (CONTENT_TYPE, HeaderValue::from_static(TODO_CONTENT_TYPE)),
+                    (ETAG, etag(task.version)),
+                ],
+                calendar_data(&task),
+            )
+                .into_response())
+        }
+        "PUT" => put(&pool, user_id, &name, &headers, &body).await,
+        "DELETE" => {
+            let mut transaction = pool.begin().await?;
+            let task = db::get_task_by_name(&mut transaction, user_id, &name)
+                .await?
+                .ok_or(Error::NotFound)?;
+            if !preconditions_hold(&headers, Some(task.version)) {
+                return Err(Error::PreconditionFailed);
+            }
+            tasks_db::delete_task(&mut transaction, task.task_id, user_id).await?;
+            transaction.commit().await?;
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        _ => Ok(method_not_allowed()),
+    }
+}
+
+/// Creates or replaces the task under `name`. Only the summary, description and
+/// completion are kept, so no ETag is sent back and clients fetch what was stored, as
+/// RFC 4791 section 5.3.4 asks.
+async fn put(
+    pool: &PgPool,
+    user_id: uuid::Uuid,
+    name: &str,
+    headers: &HeaderMap,
+    body: &str,
+) -> Result<Response> {
+    let todo = parse_todo(body).ok_or_else(|| {
+        Error::unprocessable_entity([("calendar-data", "should hold a VTODO component")])
+    })?;
+    let description = todo.description.as_deref().unwrap_or_default();
+    validate_task(&todo.summary, description).finish()?;
+
+    let mut transaction = pool.begin().await?;
+    let existing = db::get_task_by_name(&mut transaction, user_id, name).await?;
+    if !preconditions_hold(headers, existing.as_ref().map(|task| task.version)) {
+        return Err(Error::PreconditionFailed);
+    }
+    let status = match existing {
+        Some(task) => {
+            tasks_db::update_task(
+                &mut transaction,
+                task.task_id,
+                &todo.summary,
+                description,
+                todo.completed,
+                task.assignee_id,
+                todo.due,
+                user_id,
+                Some(task.version),
+            )
+            .await?;
+            StatusCode::NO_CONTENT
+        }
+        None => {
+            db::create_task(
+                &mut transaction,
+                user_id,
+                name,
+                todo.uid.as_deref(),
+                &todo.summary,
+                description,
+                todo.completed,
+                todo.due,
+            )
+            .await?;
+            StatusCode::CREATED
+        }
+    };
+    transaction.commit().await?;
+    Ok(status.into_response())
+}
+
+fn options() -> Response {
+    (
+        StatusCode::OK,
+        [
+            (HeaderName::from_static("dav"), "1, 3, calendar-access"),
+            (ALLOW, ALLOWED_METHODS),
+        ],
+    )
+        .into_response()
+}
+
+fn method_not_allowed() -> Response {
+    (StatusCode::METHOD_NOT_ALLOWED, [(ALLOW, ALLOWED_METHODS)]).into_response()
+}
+
+fn read_request(body: &str) -> Option<DavRequest> {
+    parse_request(body)
+        .inspect_err(|error| debug!(?error, "malformed request body"))
+        .ok()
+}
+
+/// How deep a `PROPFIND` goes, `infinity` is treated like 1 as there's nothing deeper.
+fn depth(headers: &HeaderMap) -> u8 {
+    match headers.get("depth").and_then(|value| value.to_str().ok()) {
+        Some("0") => 0,
+        _ => 1,
+    }
+}
+
+/// Resource names are kept to characters that need no escaping in an href.
+fn valid_name(name: &str) -> bool {
+    name.len() <= MAX_NAME_LEN
+        && name
+            .strip_suffix(".ics")
+            .is_some_and(|stem| !stem.is_empty())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'))
+}
+
+/// The resource name in an href, which clients may send as a full URL.
+fn name_from_href(href: &str) -> Option<String> {
+    let href = urlencoding::decode(href).ok()?;
+    let (_, name) = href.split_once(COLLECTION)?;
+    valid_name(name).then(|| name.to_string())
+}
+
+fn href(task: &CalDavTask) -> String {
+    format!("{COLLECTION}{}", task.name)
+}
+
+fn etag(version: i32) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{version}\"")).expect("a quoted number is a valid header")
+}
+
+/// Whether `If-Match` and `If-None-Match` allow a write to a resource at `version`,
+/// `None` when it doesn't exist yet.
+fn preconditions_hold(headers: &HeaderMap, version: Option<i32>) -> bool {
+    let current = version.map(etag);
+    let matches = |value: &HeaderValue| etag_listed(value, current.as_ref());
+
+    headers.get(IF_MATCH).is_none_or(matches) && !headers.get(IF_NONE_MATCH).is_some_and(matches)
+}
+
+fn root_props(user_session: &UserSessionData) -> Vec<Prop> {
+    vec![
+        Prop::new(DAV, "resourcetype", "<d:collection/><d:principal/>"),
+        Prop::text(DAV, "displayname", user_session.username()),
+        Prop::href(DAV, "current-user-principal", ROOT),
+        Prop::href(DAV, "principal-URL", ROOT),
+        Prop::href(CALDAV, "calendar-home-set", ROOT),
+    ]
+}
+
+fn collection_props(ctag: &str) -> Vec<Prop> {
+    vec![
+        Prop::new(DAV, "resourcetype", "<d:collection/><c:calendar/>"),
+        Prop::text(DAV, "displayname", "Todos"),
+        Prop::href(DAV, "current-user-principal", ROOT),
+        Prop::href(DAV, "owner", ROOT),
+        Prop::new(
+            DAV,
+            "current-user-privilege-set",
+            "<d:privilege><d:read/></d:privilege><d:privilege><d:write/></d:privilege>",
+        ),
+        Prop::new(
+            CALDAV,
+            "supported-calendar-component-set",
+            r#"<c:comp name="VTODO"/>"#,
+        ),
+        Prop::text(CALENDARSERVER, "getctag", ctag),
+    ]
+}
+
+/// The calendar data is only sent when it's asked for by name, as `allprop` leaves it
+/// out.
+fn task_props(task: &CalDavTask, requested: &PropRequest) -> Vec<Prop> {
+    let mut props = vec![
+        Prop::new(DAV, "resourcetype", ""),
+        Prop::text(
+            DAV,
+            "getetag",
+            etag(task.version).to_str().unwrap_or_default(),
+        ),
+        Prop::text(DAV, "getcontenttype", TODO_CONTENT_TYPE),
+    ];
+    let data = Name::new(CALDAV, "calendar-data");
+    if matches!(requested, PropRequest::Named(names) if names.contains(&data)) {
+        props.push(Prop::text(CALDAV, "calendar-data", &calendar_data(task)));
+    }
+    props
+}
+
+fn calendar_data(task: &CalDavTask) -> String {
+    calendar(
+        "Todos",
+        [VTodo {
+            uid: &task.uid,
+            summary: &task.title,
+            description: task.description.as_deref(),
+            created: task.created_at,
+            last_modified: task.updated_at,
+            completed: task.completed_at,
+            due: task.due_at,
+            sequence: task.version,
+        }],
+    )
+}
@@ -0,0 +1,269 @@
+//! Reading WebDAV request bodies and writing `207 Multi-Status` responses.
+
+use axum::{
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+};
+use quick_xml::{
+    escape::escape,
+    events::{BytesStart, Event},
+    name::ResolveResult,
+    NsReader,
+};
+
+pub const DAV: &str = "DAV:";
+pub const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+/// Apple's namespace, for the `getctag` clients check before syncing.
+pub const CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+
+/// A namespaced element name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Name {
+    pub namespace: String,
+    pub local: String,
+}
+
+impl Name {
+    pub fn new(namespace: &str, local: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            local: local.to_string(),
+        }
+    }
+
+    fn is(&self, namespace: &str, local: &str) -> bool {
+        self.namespace == namespace && self.local == local
+    }
+
+    /// An empty element with this name, prefixed for the namespaces declared on
+    /// `multistatus` and with its own declaration otherwise.
+    fn empty_element(&self) -> String {
+        match prefix(&self.namespace) {
+            Some(prefix) => format!("<{prefix}:{}/>", self.local),
+            None => format!(
+                r#"<x:{} xmlns:x="{}"/>"#,
+                self.local,
+                escape(self.namespace.as_str())
+            ),
+        }
+    }
+
+    fn element(&self, content: &str) -> String {
+        match prefix(&self.namespace) {
+            Some(prefix) => format!("<{prefix}:{0}>{content}</{prefix}:{0}>", self.local),
+            None => format!(
+                r#"<x:{0} xmlns:x="{1}">{content}</x:{0}>"#,
+                self.local,
+                escape(self.namespace.as_str())
+            ),
+        }
+    }
+}
+
+fn prefix(namespace: &str) -> Option<&'static str> {
+    match namespace {
+        DAV => Some("d"),
+        CALDAV => Some("c"),
+        CALENDARSERVER => Some("cs"),
+        _ => None,
+    }
+}
+
+/// Which properties a `PROPFIND` or `REPORT` asks for.
+#[derive(Debug, Default)]
+pub enum PropRequest {
+    /// `allprop`, `propname` or no body at all.
+    #[default]
+    All,
+    Named(Vec<Name>),
+}
+
+/// The parts of a `PROPFIND` or `REPORT` body that are acted on.
+#[derive(Debug, Default)]
+pub struct DavRequest {
+    /// The root element, telling which report is asked for.
+    pub root: Option<Name>,
+    pub props: PropRequest,
+    /// The resources a `calendar-multiget` asks for.
+    pub hrefs: Vec<String>,
+    /// The components a `calendar-query` is filtered to.
+    pub comp_filters: Vec<String>,
+}
+
+/// Parses a request body, an empty one is a `PROPFIND` for all properties.
+pub fn parse_request(body: &str) -> Result<DavRequest, quick_xml::Error> {
+    let mut request = DavRequest::default();
+    let mut reader = NsReader::from_str(body);
+    reader.config_mut().trim_text(true);
+    let mut stack: Vec<Name> = Vec::new();
+
+    loop {
+        let (resolved, event) = reader.read_resolved_event()?;
+        match event {
+            Event::Start(start) => {
+                let name = resolve(&resolved, &start);
+                on_element(&mut request, &stack, &name, &start)?;
+                stack.push(name);
+            }
+            Event::Empty(start) => {
+                let name = resolve(&resolved, &start);
+                on_element(&mut request, &stack, &name, &start)?;
+            }
+            Event::End(_) => {
+                stack.pop();
+            }
+            Event::Text(text) if stack.last().is_some_and(|name| name.is(DAV, "href")) => {
+                request.hrefs.push(text.unescape()?.into_owned());
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(request)
+}
+
+fn resolve(resolved: &ResolveResult, start: &BytesStart) -> Name {
+    let namespace = match resolved {
+        ResolveResult::Bound(namespace) => String::from_utf8_lossy(namespace.as_ref()).into_owned(),
+        _ => String::new(),
+    };
+    Name {
+        namespace,
+        local: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+    }
+}
+
+fn on_element(
+    request: &mut DavRequest,
+    stack: &[Name],
+    name: &Name,
+    start: &BytesStart,
+) -> Result<(), quick_xml::Error> {
+    let parent = stack.last();
+    if stack.is_empty() {
+        request.root = Some(name.clone());
+    } else if parent.is_some_and(|parent| parent.is(DAV, "prop")) && stack.len() == 2 {
+        //only the properties asked for, not those in filters
+        match &mut request.props {
+            PropRequest::Named(names) => names.push(name.clone()),
+            PropRequest::All => request.props = PropRequest::Named(vec![name.clone()]),
+        }
+    } else if name.is(CALDAV, "comp-filter") {
+        if let Some(component) = start.try_get_attribute("name")? {
+            request
+                .comp_filters
+                .push(component.unescape_value()?.to_ascii_uppercase());
+        }
+    }
+    Ok(())
+}
+
+/// A property of a resource, with its content already written as XML.
+pub struct Prop {
+    pub name: Name,
+    pub content: String,
+}
+
+impl Prop {
+    pub fn new(namespace: &str, local: &str, content: impl Into<String>) -> Self {
+        Self {
+            name: Name::new(namespace, local),
+            content: content.into(),
+        }
+    }
+
+    /// A property holding text, escaped here.
+    pub fn text(namespace: &str, local: &str, text: &str) -> Self {
+        Self::new(namespace, local, escape(text))
+    }
+
+    /// A property holding a link to another resource.
+    pub fn href(namespace: &str, local: &str, href: &str) -> Self {
+        Self::new(
+            namespace,
+            local,
+            format!("<d:href>{}</d:href>", escape(href)),
+        )
+    }
+}
+
+/// A `207 Multi-Status` response being written.
+pub struct Multistatus {
+    body: String,
+}
+
+impl Multistatus {
+    pub fn new() -> Self {
+        Self {
+            body: format!(
+                r#"<?xml version="1.0" encoding="utf-8"?><d:multistatus xmlns:d="{DAV}" xmlns:c="{CALDAV}" xmlns:cs="{CALENDARSERVER}">"#
+            ),
+        }
+    }
+
+    /// Adds a resource with the properties that were asked for, the ones it doesn't
+    /// have are listed as not found.
+    pub fn response(&mut self, href: &str, props: Vec<Prop>, requested: &PropRequest) {
+        let (found, missing): (Vec<Prop>, Vec<Name>) = match requested {
+            PropRequest::All => (props, Vec::new()),
+            PropRequest::Named(names) => {
+                let mut props = props;
+                let mut found = Vec::new();
+                let mut missing = Vec::new();
+                for name in names {
+                    match props.iter().position(|prop| &prop.name == name) {
+                        Some(i) => found.push(props.swap_remove(i)),
+                        None => missing.push(name.clone()),
+                    }
+                }
+                (found, missing)
+            }
+        };
+
+        self.body.push_str("<d:response>");
+        self.body
+            .push_str(&format!("<d:href>{}</d:href>", escape(href)));
+        if !found.is_empty() {
+            let props: String = found
+                .iter()
+                .map(|prop| match prop.content.is_empty() {
+                    true => prop.name.empty_element(),
+                    false => prop.name.element(&prop.content),
+                })
+                .collect();
+            self.propstat(&props, "200 OK");
+        }
+        if !missing.is_empty() {
+            let props: String = missing.iter().map(Name::empty_element).collect();
+            self.propstat(&props, "404 Not Found");
+        }
+        self.body.push_str("</d:response>");
+    }
+
+    /// Adds a resource that was asked for but doesn't exist.
+    pub fn not_found(&mut self, href: &str) {
+        self.body.push_str(&format!(
+            "<d:response><d:href>{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>",
+            escape(href)
+        ));
+    }
+
+    fn propstat(&mut self, props: &str, status: &str) {
+        self.body.push_str(&format!(
+            "<d:propstat><d:prop>{props}</d:prop><d:status>HTTP/1.1 {status}</d:status></d:propstat>"
+        ));
+    }
+}
+
+impl IntoResponse for Multistatus {
+    fn into_response(mut self) -> Response {
+        self.body.push_str("</d:multistatus>");
+        (
+            StatusCode::MULTI_STATUS,
+            [(CONTENT_TYPE, "application/xml; charset=utf-8")],
+            self.body,
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,93 @@
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::super::{error::Error, utilities::Result};
+
+/// A task as published in calendar feeds.
+#[derive(Debug)]
+pub struct FeedTask {
+    /// The UID a CalDAV client gave the task, or else its id.
+    pub uid: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+    pub completed_at: Option<OffsetDateTime>,
+    pub due_at: Option<OffsetDateTime>,
+    pub version: i32,
+}
+
+#[instrument(skip(pool))]
+pub async fn get_calendar_secret(pool: &PgPool, user_id: Uuid) -> Result<Uuid> {
+    sqlx::query_scalar!(
+        "select calendar_secret from users where user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(Error::NotFound)
+}
+
+/// Replaces the user's calendar secret, so feed addresses made with the old one stop working.
+#[instrument(skip(pool))]
+pub async fn regenerate_calendar_secret(pool: &PgPool, user_id: Uuid) -> Result<Uuid> {
+    sqlx::query_scalar!(
+        r#"
+        update users
+        set calendar_secret = gen_random_uuid()
+        where user_id = $1
+        returning calendar_secret
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(Error::NotFound)
+}
+
+/// The username of the user whose current calendar secret is `secret`.
+#[instrument(skip(pool, secret))]
+pub async fn get_feed_owner(pool: &PgPool, user_id: Uuid, secret: Uuid) -> Result<String> {
+    sqlx::query_scalar!(
+        "select username from users where user_id = $1 and calendar_secret = $2",
+        user_id,
+        secret
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(Error::NotFound)
+}
+
+/// The user's own tasks along with the ones assigned to or shared with them, trashed
+/// tasks left out. Users who lose access to a task are unassigned from it.
+#[instrument(skip(pool))]
+pub async fn get_feed_tasks(pool: &PgPool, user_id: Uuid) -> Result<Vec<FeedTask>> {
+    sqlx::query_as!(
+        FeedTask,
+        r#"
+        select
+            coalesce(t.ical_uid, t.task_id::text) as "uid!",
+            t.title,
+            t.description,
+            t.created_at,
+            t.updated_at,
+            t.completed_at,
+            t.due_at,
+            t.version
+        from task t
+        where t.deleted_at is null
+        and (
+            t.user_id = $1
+            or t.assignee_id = $1
+            or exists (select 1 from task_share s where s.task_id = t.task_id and s.user_id = $1)
+        )
+        order by t.position, t.created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
@@ -0,0 +1,251 @@
+//! Just enough of iCalendar (RFC 5545) to publish tasks as `VTODO` components, with
+//! `VEVENT`s at their due dates in feeds, and read back the ones CalDAV clients send.
+
+use time::{
+    format_description::BorrowedFormatItem, macros::format_description, Date, OffsetDateTime,
+    PrimitiveDateTime, UtcOffset,
+};
+
+/// Content lines longer than this many octets are folded onto the next line.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// A task as a `VTODO` component.
+#[derive(Debug)]
+pub struct VTodo<'a> {
+    pub uid: &'a str,
+    pub summary: &'a str,
+    pub description: Option<&'a str>,
+    pub created: OffsetDateTime,
+    pub last_modified: OffsetDateTime,
+    pub completed: Option<OffsetDateTime>,
+    pub due: Option<OffsetDateTime>,
+    /// Bumped by every change, see RFC 5545 section 3.8.7.4.
+    pub sequence: i32,
+}
+
+/// The parts of a `VTODO` sent by a client that a task is made from.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ParsedTodo {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    pub due: Option<OffsetDateTime>,
+}
+
+/// Reads the first `VTODO` in a calendar object, `None` if there isn't one. Properties
+/// of components nested in it, like alarms, are skipped.
+pub fn parse_todo(calendar: &str) -> Option<ParsedTodo> {
+    let unfolded = calendar
+        .replace("\r\n ", "")
+        .replace("\r\n\t", "")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+    let mut lines = unfolded.lines().map(|line| line.trim_end_matches('\r'));
+    lines.find(|line| line.eq_ignore_ascii_case("BEGIN:VTODO"))?;
+
+    let mut todo = ParsedTodo::default();
+    let mut nested = 0;
+    for line in lines {
+        let Some((name, value)) = split_property(line) else {
+            continue;
+        };
+        match name.to_ascii_uppercase().as_str() {
+            "BEGIN" => nested += 1,
+            "END" if nested == 0 => return Some(todo),
+            "END" => nested -= 1,
+            _ if nested > 0 => {}
+            "UID" => todo.uid = Some(unescape(value)),
+            "SUMMARY" => todo.summary = unescape(value),
+            "DESCRIPTION" => todo.description = Some(unescape(value)),
+            "STATUS" => todo.completed |= value.eq_ignore_ascii_case("COMPLETED"),
+            "COMPLETED" => todo.completed = true,
+            "PERCENT-COMPLETE" => todo.completed |= value.trim() == "100",
+            "DUE" => todo.due = parse_date_time(value.trim()),
+            _ => {}
+        }
+    }
+    //the VTODO was never ended
+    None
+}
+
+/// Splits a content line into its name and value, leaving out any parameters. Colons
+/// in quoted parameter values don't end the name.
+fn split_property(line: &str) -> Option<(&str, &str)> {
+    let mut quoted = false;
+    let mut name_end = None;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted && name_end.is_none() => name_end = Some(i),
+            ':' if !quoted => return Some((&line[..name_end.unwrap_or(i)], &line[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Reads a `DATE-TIME` or `DATE` value, a date being due at its start. Times in a
+/// `TZID` or without one are taken as UTC, there's no time zone database to look
+/// them up in.
+fn parse_date_time(value: &str) -> Option<OffsetDateTime> {
+    const DATE: &[BorrowedFormatItem] = format_description!("[year][month][day]");
+    const DATE_TIME: &[BorrowedFormatItem] =
+        format_description!("[year][month][day]T[hour][minute][second]");
+
+    let value = value.strip_suffix(['Z', 'z']).unwrap_or(value);
+    match PrimitiveDateTime::parse(value, DATE_TIME) {
+        Ok(date_time) => Some(date_time.assume_utc()),
+        Err(_) => Date::parse(value, DATE)
+            .ok()
+            .map(|date| date.midnight().assume_utc()),
+    }
+}
+
+/// Reverses [`escape`].
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// A `VCALENDAR` object holding `todos`, named `name` for the clients that show it.
+pub fn calendar<'a>(name: &str, todos: impl IntoIterator<Item = VTodo<'a>>) -> String {
+    write_calendar(name, todos, false)
+}
+
+/// Like [`calendar`], with an event at the due date of every todo that has one as well,
+/// for calendar apps that don't show todos. CalDAV resources can't hold these, all the
+/// components in one need the same UID.
+pub fn feed_calendar<'a>(name: &str, todos: impl IntoIterator<Item = VTodo<'a>>) -> String {
+    write_calendar(name, todos, true)
+}
+
+fn write_calendar<'a>(
+    name: &str,
+    todos: impl IntoIterator<Item = VTodo<'a>>,
+    with_events: bool,
+) -> String {
+    let mut out = String::new();
+    line(&mut out, "BEGIN:VCALENDAR");
+    line(&mut out, "VERSION:2.0");
+    line(&mut out, "PRODID:-//todo-web-app//tasks//EN");
+    line(&mut out, "CALSCALE:GREGORIAN");
+    line(&mut out, &format!("X-WR-CALNAME:{}", escape(name)));
+    for todo in todos {
+        write_todo(&mut out, &todo);
+        if let Some(due) = todo.due.filter(|_| with_events) {
+            write_due_event(&mut out, &todo, due);
+        }
+    }
+    line(&mut out, "END:VCALENDAR");
+    out
+}
+
+fn write_todo(out: &mut String, todo: &VTodo) {
+    line(out, "BEGIN:VTODO");
+    line(out, &format!("UID:{}", escape(todo.uid)));
+    line(out, &format!("DTSTAMP:{}", date_time(todo.last_modified)));
+    line(out, &format!("CREATED:{}", date_time(todo.created)));
+    line(
+        out,
+        &format!("LAST-MODIFIED:{}", date_time(todo.last_modified)),
+    );
+    line(out, &format!("SEQUENCE:{}", todo.sequence));
+    line(out, &format!("SUMMARY:{}", escape(todo.summary)));
+    write_description(out, todo);
+    if let Some(due) = todo.due {
+        line(out, &format!("DUE:{}", date_time(due)));
+    }
+    match todo.completed {
+        Some(completed) => {
+            line(out, "STATUS:COMPLETED");
+            line(out, &format!("COMPLETED:{}", date_time(completed)));
+            line(out, "PERCENT-COMPLETE:100");
+        }
+        None => line(out, "STATUS:NEEDS-ACTION"),
+    }
+    line(out, "END:VTODO");
+}
+
+/// An event without a duration at the time `todo` is due, transparent so it doesn't
+/// show the time as busy. Its UID is the todo's with `-due` added.
+fn write_due_event(out: &mut String, todo: &VTodo, due: OffsetDateTime) {
+    line(out, "BEGIN:VEVENT");
+    line(
+        out,
+        &format!("UID:{}", escape(&format!("{}-due", todo.uid))),
+    );
+    line(out, &format!("DTSTAMP:{}", date_time(todo.last_modified)));
+    line(out, &format!("DTSTART:{}", date_time(due)));
+    line(out, &format!("SEQUENCE:{}", todo.sequence));
+    line(
+        out,
+        &format!("SUMMARY:{}", escape(&format!("Due: {}", todo.summary))),
+    );
+    write_description(out, todo);
+    line(out, "TRANSP:TRANSPARENT");
+    line(out, "END:VEVENT");
+}
+
+fn write_description(out: &mut String, todo: &VTodo) {
+    if let Some(description) = todo
+        .description
+        .filter(|description| !description.is_empty())
+    {
+        line(out, &format!("DESCRIPTION:{}", escape(description)));
+    }
+}
+
+/// A UTC `DATE-TIME` value, e.g. `20261018T143000Z`.
+fn date_time(value: OffsetDateTime) -> String {
+    value
+        .to_offset(UtcOffset::UTC)
+        .format(format_description!(
+            "[year][month][day]T[hour][minute][second]Z"
+        ))
+        .expect("a UTC date time can always be formatted")
+}
+
+/// Escapes a `TEXT` value, line breaks become `\n`.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes a content line ended by CRLF, folded so no line is longer than
+/// [`MAX_LINE_OCTETS`] without splitting a character.
+fn line(out: &mut String, content: &str) {
+    let mut octets = 0;
+    for c in content.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            //the space starting the continuation counts towards its length
+            octets = 1;
+        }
+        out.push(c);
+        octets += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
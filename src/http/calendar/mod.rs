@@ -0,0 +1,21 @@
+use uuid::Uuid;
+
+use super::utilities::HmacKey;
+
+mod db;
+pub(crate) mod ical;
+mod routes;
+mod templates;
+
+pub use routes::router;
+
+/// The signed token in a user's feed address, good for as long as `secret` is theirs.
+fn feed_token(hmac_key: &HmacKey, user_id: Uuid, secret: Uuid) -> String {
+    hmac_key.sign(&format!("{user_id}:{secret}"))
+}
+
+/// The user and secret a feed token was made for, `None` if it wasn't signed by us.
+fn verify_feed_token(hmac_key: &HmacKey, token: &str) -> Option<(Uuid, Uuid)> {
+    let (user_id, secret) = hmac_key.verify(token)?.split_once(':')?;
+    Some((user_id.parse().ok()?, secret.parse().ok()?))
+}
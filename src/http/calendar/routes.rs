@@ -0,0 +1,192 @@
+use axum::{
+    extract::{Path, State},
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    middleware::from_fn,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Extension, Form, Router,
+};
+use secrecy::SecretString;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::http::users::{
+    auth_middleware, db as users_db, generate_app_password, hash_password, UserSessionData,
+};
+
+use super::super::{
+    error::Error,
+    layout::{LazyPageContext, PageContext},
+    utilities::{render_template, ApiState, FlashMessageLevel, FlashMessages, HmacKey, Result},
+    validation::Validator,
+};
+use super::{
+    db, feed_token,
+    ical::{feed_calendar, VTodo},
+    templates::CalendarTemplate,
+    verify_feed_token,
+};
+
+const MAX_APP_PASSWORD_NAME_CHARS: usize = 100;
+
+/// The feed is fetched by calendar apps without a session, its signed token stands in
+/// for logging in.
+pub fn router() -> Router<ApiState> {
+    Router::new()
+        .route("/", get(calendar_page))
+        .route("/regenerate", post(regenerate_feed))
+        .route("/app-passwords", post(create_app_password))
+        .route(
+            "/app-passwords/{app_password_id}/revoke",
+            post(revoke_app_password),
+        )
+        .route_layer(from_fn(auth_middleware))
+        .route("/feed/{token}/tasks.ics", get(feed))
+}
+
+fn feed_path(token: &str) -> String {
+    format!("/calendar/feed/{token}/tasks.ics")
+}
+
+#[instrument(skip_all, fields(action = "displaying calendar page", %user_session))]
+pub async fn calendar_page(
+    State(pool): State<PgPool>,
+    State(hmac_key): State<HmacKey>,
+    Extension(user_session): Extension<UserSessionData>,
+    ctx: PageContext,
+) -> Result<Html<String>> {
+    render_calendar_page(&pool, &hmac_key, &user_session, ctx, None).await
+}
+
+async fn render_calendar_page(
+    pool: &PgPool,
+    hmac_key: &HmacKey,
+    user_session: &UserSessionData,
+    ctx: PageContext,
+    new_app_password: Option<String>,
+) -> Result<Html<String>> {
+    let user_id = user_session.user_id();
+    let secret = db::get_calendar_secret(pool, user_id).await?;
+
+    render_template(CalendarTemplate {
+        ctx,
+        feed_path: feed_path(&feed_token(hmac_key, user_id, secret)),
+        username: user_session.username().to_string(),
+        app_passwords: users_db::get_app_passwords(pool, user_id).await?,
+        new_app_password,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewAppPassword {
+    name: String,
+}
+
+/// Makes a password for a sync app, shown on the page this once as only its hash is kept.
+#[instrument(skip_all, fields(action = "creating app password", %user_session))]
+pub async fn create_app_password(
+    State(pool): State<PgPool>,
+    State(hmac_key): State<HmacKey>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+    ctx: LazyPageContext,
+    Form(form): Form<NewAppPassword>,
+) -> Result<Response> {
+    let name = form.name.trim();
+    let validation = Validator::new()
+        .required("name", name)
+        .max_chars("name", name, MAX_APP_PASSWORD_NAME_CHARS)
+        .finish();
+    if let Err(error) = validation {
+        flash_msgs.set_invalid(error).await?;
+        return Ok(Redirect::to("/calendar").into_response());
+    }
+
+    let (lookup_id, password) = generate_app_password();
+    let password_hash = hash_password(&SecretString::from(password.as_str())).await?;
+    users_db::insert_app_password(
+        &pool,
+        user_session.user_id(),
+        name,
+        &lookup_id,
+        &password_hash,
+    )
+    .await?;
+
+    let ctx = ctx.build().await?;
+    Ok(
+        render_calendar_page(&pool, &hmac_key, &user_session, ctx, Some(password))
+            .await?
+            .into_response(),
+    )
+}
+
+#[instrument(skip_all, fields(action = "revoking app password", %user_session, %app_password_id))]
+pub async fn revoke_app_password(
+    State(pool): State<PgPool>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+    Path(app_password_id): Path<Uuid>,
+) -> Result<Redirect> {
+    users_db::delete_app_password(&pool, user_session.user_id(), app_password_id).await?;
+
+    flash_msgs
+        .set_msg(
+            FlashMessageLevel::Success,
+            "The app password was revoked, apps using it can no longer sync",
+        )
+        .await?;
+    Ok(Redirect::to("/calendar"))
+}
+
+#[instrument(skip_all, fields(action = "regenerating calendar feed", %user_session))]
+pub async fn regenerate_feed(
+    State(pool): State<PgPool>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+) -> Result<Redirect> {
+    db::regenerate_calendar_secret(&pool, user_session.user_id()).await?;
+
+    flash_msgs
+        .set_msg(
+            FlashMessageLevel::Success,
+            "Your calendar feed has a new address, the old one no longer works",
+        )
+        .await?;
+    Ok(Redirect::to("/calendar"))
+}
+
+#[instrument(skip_all, fields(action = "serving calendar feed"))]
+pub async fn feed(
+    State(pool): State<PgPool>,
+    State(hmac_key): State<HmacKey>,
+    Path(token): Path<String>,
+) -> Result<Response> {
+    let (user_id, secret) = verify_feed_token(&hmac_key, &token).ok_or(Error::NotFound)?;
+    let username = db::get_feed_owner(&pool, user_id, secret).await?;
+    let tasks = db::get_feed_tasks(&pool, user_id).await?;
+
+    let body = feed_calendar(
+        &format!("{username}'s todos"),
+        tasks.iter().map(|task| VTodo {
+            uid: &task.uid,
+            summary: &task.title,
+            description: task.description.as_deref(),
+            created: task.created_at,
+            last_modified: task.updated_at,
+            completed: task.completed_at,
+            due: task.due_at,
+            sequence: task.version,
+        }),
+    );
+    Ok((
+        [
+            (CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (CACHE_CONTROL, "private, no-cache"),
+        ],
+        body,
+    )
+        .into_response())
+}
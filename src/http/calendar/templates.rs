@@ -0,0 +1,15 @@
+use askama::Template;
+
+use super::super::{filters, layout::PageContext, users::db::AppPassword};
+
+#[derive(Template)]
+#[template(path = "calendar.html")]
+pub struct CalendarTemplate {
+    pub ctx: PageContext,
+    /// Path of the user's feed, relative to the site.
+    pub feed_path: String,
+    pub username: String,
+    pub app_passwords: Vec<AppPassword>,
+    /// A password that was just made, shown this once.
+    pub new_app_password: Option<String>,
+}
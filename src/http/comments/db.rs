@@ -0,0 +1,140 @@
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::super::{error::Error, utilities::Result};
+
+#[derive(Debug)]
+pub struct Comment {
+    pub comment_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub author_id: Option<Uuid>,
+    /// `None` when the author's account is gone.
+    pub author_name: Option<String>,
+    pub body: String,
+    pub deleted: bool,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+impl Comment {
+    pub fn is_by(&self, user_id: &Uuid) -> bool {
+        self.author_id.as_ref() == Some(user_id)
+    }
+
+    pub fn edited(&self) -> bool {
+        self.updated_at > self.created_at
+    }
+}
+
+#[instrument(skip_all, fields(%task_id))]
+pub async fn get_comments(pool: &PgPool, task_id: Uuid) -> Result<Vec<Comment>> {
+    sqlx::query_as!(
+        Comment,
+        r#"
+        select
+            c.comment_id,
+            c.parent_id,
+            c.author_id,
+            u.username as "author_name?",
+            c.body,
+            c.deleted_at is not null as "deleted!",
+            c.created_at,
+            c.updated_at
+        from task_comment c
+        left join users u on u.user_id = c.author_id
+        where c.task_id = $1
+        order by c.created_at
+        "#,
+        task_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// Adds a comment, replies have to be to a comment on the same task.
+#[instrument(skip_all, fields(%task_id, %author_id, ?parent_id))]
+pub async fn create_comment(
+    pool: &PgPool,
+    task_id: Uuid,
+    author_id: Uuid,
+    parent_id: Option<Uuid>,
+    body: &str,
+) -> Result<()> {
+    let query_result = sqlx::query!(
+        r#"
+        insert into task_comment (task_id, author_id, parent_id, body)
+        select $1, $2, $3, $4
+        where $3::uuid is null
+            or exists(select 1 from task_comment where comment_id = $3 and task_id = $1)
+        "#,
+        task_id,
+        author_id,
+        parent_id,
+        body
+    )
+    .execute(pool)
+    .await?;
+
+    if query_result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+
+    Ok(())
+}
+
+/// The author of a comment on the task, `Some(None)` when the author's account is gone.
+#[instrument(skip_all, fields(%task_id, %comment_id))]
+pub async fn get_comment_author(
+    pool: &PgPool,
+    task_id: Uuid,
+    comment_id: Uuid,
+) -> Result<Option<Option<Uuid>>> {
+    sqlx::query_scalar!(
+        r#"
+        select author_id from task_comment
+        where comment_id = $1 and task_id = $2 and deleted_at is null
+        "#,
+        comment_id,
+        task_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+#[instrument(skip_all, fields(%comment_id))]
+pub async fn update_comment(pool: &PgPool, comment_id: Uuid, body: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+        update task_comment
+        set body = $1
+        where comment_id = $2
+        "#,
+        body,
+        comment_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Blanks out the comment but keeps it in place, so its replies still have a parent.
+#[instrument(skip_all, fields(%comment_id))]
+pub async fn delete_comment(pool: &PgPool, comment_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        update task_comment
+        set body = '', deleted_at = now()
+        where comment_id = $1
+        "#,
+        comment_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
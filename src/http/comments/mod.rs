@@ -0,0 +1,54 @@
+pub(crate) mod db;
+mod routes;
+
+pub use db::Comment;
+pub use routes::router;
+
+/// A comment along with how deep in its thread it is, top level comments are at 0.
+#[derive(Debug)]
+pub struct ThreadedComment {
+    pub comment: Comment,
+    pub depth: usize,
+}
+
+impl ThreadedComment {
+    /// Replies are indented up to this depth, deeper ones line up with it.
+    const MAX_INDENT: usize = 4;
+
+    pub fn comment(&self) -> &Comment {
+        &self.comment
+    }
+
+    pub fn indent(&self) -> usize {
+        self.depth.min(Self::MAX_INDENT)
+    }
+}
+
+/// Orders comments as threads, each followed by its replies, oldest first.
+pub fn thread(comments: Vec<Comment>) -> Vec<ThreadedComment> {
+    let mut threaded = Vec::with_capacity(comments.len());
+    let (mut top_level, mut replies): (Vec<_>, Vec<_>) = comments
+        .into_iter()
+        .partition(|comment| comment.parent_id.is_none());
+    top_level.sort_by_key(|comment| comment.created_at);
+    replies.sort_by_key(|comment| comment.created_at);
+
+    let mut stack: Vec<ThreadedComment> = top_level
+        .into_iter()
+        .rev()
+        .map(|comment| ThreadedComment { comment, depth: 0 })
+        .collect();
+    while let Some(current) = stack.pop() {
+        let (children, rest): (Vec<_>, Vec<_>) = replies
+            .into_iter()
+            .partition(|reply| reply.parent_id == Some(current.comment.comment_id));
+        replies = rest;
+        stack.extend(children.into_iter().rev().map(|comment| ThreadedComment {
+            comment,
+            depth: current.depth + 1,
+        }));
+        threaded.push(current);
+    }
+
+    threaded
+}
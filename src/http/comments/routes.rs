@@ -0,0 +1,140 @@
+use axum::{
+    extract::{Path, State},
+    middleware::from_fn,
+    response::Redirect,
+    routing::post,
+    Extension, Form, Router,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::{instrument, warn};
+use uuid::Uuid;
+
+use crate::http::users::{auth_middleware, UserSessionData};
+
+use super::super::{
+    authorization::{authorize_task, TaskAction},
+    error::Error,
+    utilities::{empty_string_as_none, ApiState, FlashMessages, Result},
+    validation::{Validate, Validator},
+};
+
+use super::db;
+
+/// Routes under `/todo/{task_id}/comments`, comments are shown on the task page.
+pub fn router() -> Router<ApiState> {
+    Router::new()
+        .route("/{task_id}/comments", post(create_comment))
+        .route("/{task_id}/comments/{comment_id}", post(update_comment))
+        .route(
+            "/{task_id}/comments/{comment_id}/delete",
+            post(delete_comment),
+        )
+        .route_layer(from_fn(auth_middleware))
+}
+
+const MAX_BODY_CHARS: usize = 5_000;
+
+#[derive(Debug, Deserialize)]
+struct CommentForm {
+    body: String,
+    /// The comment this one replies to.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    parent_id: Option<Uuid>,
+}
+
+impl Validate for CommentForm {
+    fn validate(&self) -> Result<()> {
+        Validator::new()
+            .required("body", &self.body)
+            .max_chars("body", &self.body, MAX_BODY_CHARS)
+            .finish()
+    }
+}
+
+/// Validation errors are shown as flash messages on the task page, which has a
+/// comment form for every reply.
+fn task_page(task_id: Uuid) -> Redirect {
+    Redirect::to(&format!("/todo/{task_id}#comments"))
+}
+
+#[instrument(skip_all, fields(action = "commenting on a task", %task_id, ?comment.parent_id, %user_session))]
+async fn create_comment(
+    State(pool): State<PgPool>,
+    Path(task_id): Path<Uuid>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+    Form(comment): Form<CommentForm>,
+) -> Result<Redirect> {
+    authorize_task(&pool, &user_session, task_id, TaskAction::Comment).await?;
+    if let Err(error) = comment.validate() {
+        flash_msgs.set_invalid(error).await?;
+        return Ok(task_page(task_id));
+    }
+
+    db::create_comment(
+        &pool,
+        task_id,
+        user_session.user_id(),
+        comment.parent_id,
+        comment.body.trim(),
+    )
+    .await?;
+
+    Ok(task_page(task_id))
+}
+
+/// Only the author of a comment can change it, as long as they can still see the task.
+async fn authorize_author(
+    pool: &PgPool,
+    user_session: &UserSessionData,
+    task_id: Uuid,
+    comment_id: Uuid,
+) -> Result<()> {
+    authorize_task(pool, user_session, task_id, TaskAction::View).await?;
+    let author_id = db::get_comment_author(pool, task_id, comment_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    if author_id != Some(user_session.user_id()) {
+        warn!(
+            target: "audit",
+            user_id = %user_session.user_id(),
+            %task_id,
+            %comment_id,
+            "denied changing a comment of another user"
+        );
+        return Err(Error::Forbidden);
+    }
+    Ok(())
+}
+
+#[instrument(skip_all, fields(action = "editing a comment", %task_id, %comment_id, %user_session))]
+async fn update_comment(
+    State(pool): State<PgPool>,
+    Path((task_id, comment_id)): Path<(Uuid, Uuid)>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+    Form(comment): Form<CommentForm>,
+) -> Result<Redirect> {
+    authorize_author(&pool, &user_session, task_id, comment_id).await?;
+    if let Err(error) = comment.validate() {
+        flash_msgs.set_invalid(error).await?;
+        return Ok(task_page(task_id));
+    }
+
+    db::update_comment(&pool, comment_id, comment.body.trim()).await?;
+    Ok(task_page(task_id))
+}
+
+#[instrument(skip_all, fields(action = "deleting a comment", %task_id, %comment_id, %user_session))]
+async fn delete_comment(
+    State(pool): State<PgPool>,
+    Path((task_id, comment_id)): Path<(Uuid, Uuid)>,
+    Extension(user_session): Extension<UserSessionData>,
+) -> Result<Redirect> {
+    authorize_author(&pool, &user_session, task_id, comment_id).await?;
+    db::delete_comment(&pool, comment_id).await?;
+
+    Ok(task_page(task_id))
+}
@@ -20,6 +20,13 @@ pub enum Error {
     },
     #[error("error in authentication")]
     Unauthorized,
+    #[error("you don't have permission to do that")]
+    Forbidden,
+    #[error("it was changed by someone else in the meantime")]
+    Conflict,
+    /// An `If-Match` header that doesn't list the current `ETag`.
+    #[error("it was changed since the ETag in If-Match was given out")]
+    PreconditionFailed,
     #[error("error in displaying page")]
     Template(#[from] askama::Error),
     #[error("an internal server error occurred")]
@@ -31,6 +38,10 @@ impl Error {
         match self {
             Self::NotFound => StatusCode::NOT_FOUND,
             Self::UnprocessableEntity { errors: _ } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::Conflict => StatusCode::CONFLICT,
+            Self::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -50,23 +61,47 @@ impl Error {
     }
 }
 
+/// Attached to the extensions of every error response, so the `error_pages` middleware
+/// can turn the plain text fallback into an HTML page or a problem+json document.
+#[derive(Debug, Clone)]
+pub struct ErrorDetails {
+    pub status: StatusCode,
+    pub detail: String,
+    pub errors: HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>,
+}
+
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
+        let details = ErrorDetails {
+            status: self.status_code(),
+            detail: self.to_string(),
+            errors: match &self {
+                Self::UnprocessableEntity { errors } => errors.clone(),
+                _ => HashMap::new(),
+            },
+        };
+
         match &self {
             Self::SQLx(error) => tracing::error!("SQLx error: {:?}", error),
             Self::UnprocessableEntity { errors } => {
                 tracing::trace!("Errors in the reguest: {:?}", errors)
             }
             Self::Other(error) => tracing::error!("Generic error: {:?}", error),
-            Self::Unauthorized => {
-                tracing::trace!("Authentication failed");
-                return Redirect::to("/users/login").into_response();
-            }
+            Self::Unauthorized => tracing::trace!("Authentication failed"),
+            Self::Forbidden => tracing::trace!("Access forbidden"),
+            Self::Conflict => tracing::trace!("Conflicting change"),
+            Self::PreconditionFailed => tracing::trace!("If-Match precondition failed"),
             Self::Template(error) => tracing::error!("Template rendering error: {:?}", error),
             Self::Session(error) => tracing::error!("Error in session middleware: {:?}", error),
             _ => {}
         };
-        (self.status_code(), self.to_string()).into_response()
+
+        let mut response = match self {
+            Self::Unauthorized => Redirect::to("/users/login").into_response(),
+            _ => (self.status_code(), self.to_string()).into_response(),
+        };
+        response.extensions_mut().insert(details);
+        response
     }
 }
 
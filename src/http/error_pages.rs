@@ -0,0 +1,152 @@
+use askama::Template;
+use axum::{
+    extract::Request,
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderMap, StatusCode,
+    },
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use tower_sessions::Session;
+use tracing::warn;
+
+use super::{
+    error::{Error, ErrorDetails},
+    filters,
+    layout::PageContext,
+    security_headers::CspNonce,
+    users::SessionExt,
+    REQUEST_ID_HEADER,
+};
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct ErrorTemplate {
+    ctx: PageContext,
+    status: u16,
+    title: &'static str,
+    detail: String,
+    errors: Vec<(String, Vec<String>)>,
+    request_id: String,
+}
+
+/// Whether the client asked for JSON rather than a page, browsers always list text/html.
+pub(crate) fn wants_json(headers: &HeaderMap) -> bool {
+    let media_types: Vec<&str> = headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|media_type| media_type.split(';').next())
+        .map(str::trim)
+        .collect();
+
+    !media_types.contains(&"text/html")
+        && media_types
+            .iter()
+            .any(|media_type| *media_type == "application/json" || media_type.ends_with("+json"))
+}
+
+/// Turns error responses into HTML error pages for browsers and RFC 7807
+/// `application/problem+json` documents for API clients, both carrying the request id.
+/// Browsers keep getting redirected to the login page on a 401.
+pub async fn error_pages(req: Request, next: Next) -> Response {
+    let json = wants_json(req.headers());
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let instance = req.uri().path().to_string();
+    let nonce = req.extensions().get::<CspNonce>().cloned();
+    let session = req.extensions().get::<Session>().cloned();
+
+    let response = next.run(req).await;
+    let Some(details) = response.extensions().get::<ErrorDetails>().cloned() else {
+        return response;
+    };
+
+    if json {
+        return problem_json(details, instance, request_id);
+    }
+    if details.status == StatusCode::UNAUTHORIZED {
+        return response;
+    }
+
+    let Some(nonce) = nonce else {
+        return response;
+    };
+    let (user, workspace) = match session {
+        Some(session) => (
+            session.user_session().await.ok().flatten(),
+            session.current_workspace().await.ok().flatten(),
+        ),
+        None => (None, None),
+    };
+
+    let mut errors: Vec<(String, Vec<String>)> = details
+        .errors
+        .into_iter()
+        .map(|(field, msgs)| {
+            (
+                field.into_owned(),
+                msgs.into_iter().map(|msg| msg.into_owned()).collect(),
+            )
+        })
+        .collect();
+    errors.sort();
+
+    let template = ErrorTemplate {
+        ctx: PageContext {
+            user,
+            workspace,
+            flash_msgs: Vec::new(),
+            nonce,
+        },
+        status: details.status.as_u16(),
+        title: details.status.canonical_reason().unwrap_or("Error"),
+        detail: details.detail,
+        errors,
+        request_id,
+    };
+    match template.render() {
+        Ok(page) => (details.status, Html(page)).into_response(),
+        Err(error) => {
+            warn!(
+                ?error,
+                "failed to render error page, falling back to plain text"
+            );
+            response
+        }
+    }
+}
+
+fn problem_json(details: ErrorDetails, instance: String, request_id: String) -> Response {
+    let mut problem = json!({
+        "type": "about:blank",
+        "title": details.status.canonical_reason().unwrap_or("Error"),
+        "status": details.status.as_u16(),
+        "detail": details.detail,
+        "instance": instance,
+        "request_id": request_id,
+    });
+    if !details.errors.is_empty() {
+        problem["errors"] = json!(details.errors);
+    }
+
+    (
+        details.status,
+        [(CONTENT_TYPE, "application/problem+json")],
+        Json(problem),
+    )
+        .into_response()
+}
+
+/// Fallback for routes that don't exist, so they get the same error pages.
+pub async fn not_found() -> Error {
+    Error::NotFound
+}
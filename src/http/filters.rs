@@ -0,0 +1,56 @@
+//! Askama filters, bring them into scope with `use super::super::filters;` next to a
+//! template and use them as `{{ "css/todos.css"|asset }}`.
+
+use std::fmt::Display;
+
+use time::{macros::format_description, OffsetDateTime};
+
+use super::assets::asset_url;
+
+pub fn asset<T: Display>(path: T) -> askama::Result<String> {
+    let path = path.to_string();
+    asset_url(&path)
+        .ok_or_else(|| askama::Error::Custom(format!("no static asset at {path}").into()))
+}
+
+/// Formats a timestamp as `2025-03-01 14:05 UTC`.
+pub fn datetime(value: &OffsetDateTime) -> askama::Result<String> {
+    Ok(format_datetime(*value))
+}
+
+/// Formats a timestamp like the [`datetime`] filter, for text made outside templates
+/// such as task history.
+pub fn format_datetime(value: OffsetDateTime) -> String {
+    value
+        .to_offset(time::UtcOffset::UTC)
+        .format(format_description!(
+            "[year]-[month]-[day] [hour]:[minute] UTC"
+        ))
+        .expect("a UTC date time can always be formatted")
+}
+
+/// Shows an optional value, or nothing when there's none, e.g. as the value of an
+/// optional form field.
+pub fn or_empty<T: Display>(value: &Option<T>) -> askama::Result<String> {
+    Ok(value.as_ref().map(ToString::to_string).unwrap_or_default())
+}
+
+/// Formats a size in bytes as `512 B`, `1.5 KB` or `10.0 MB`.
+pub fn filesize(bytes: &i64) -> askama::Result<String> {
+    Ok(format_size(*bytes))
+}
+
+/// Sizes below a kilobyte are shown in bytes, bigger ones with one decimal.
+pub fn format_size(bytes: i64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
@@ -0,0 +1,79 @@
+use serde::Serialize;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::super::{error::Error, tasks::db as tasks_db, utilities::Result};
+use super::formats::ImportedTask;
+
+/// One of the user's personal tasks as it's exported.
+#[derive(Debug, Serialize)]
+pub struct ExportedTask {
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub completed_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub due_at: Option<OffsetDateTime>,
+}
+
+/// The user's personal tasks, trashed ones left out. Imports go to the personal tasks
+/// too, so these are what they're checked against for duplicates.
+#[instrument(skip(pool))]
+pub async fn get_export_tasks(pool: &PgPool, user_id: Uuid) -> Result<Vec<ExportedTask>> {
+    sqlx::query_as!(
+        ExportedTask,
+        r#"
+        select title, description, completed, created_at, completed_at, due_at
+        from task
+        where user_id = $1 and workspace_id is null and deleted_at is null
+        order by position, created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// Creates the tasks as the user's personal ones in a single transaction, so an import
+/// is never left half done. The dates in the file are kept, so completing old tasks
+/// doesn't count as completing them today.
+#[instrument(skip(pool, tasks), fields(tasks = tasks.len()))]
+pub async fn import_tasks(pool: &PgPool, user_id: Uuid, tasks: &[ImportedTask]) -> Result<()> {
+    let mut transaction = pool.begin().await?;
+    for task in tasks {
+        let task_id = tasks_db::insert_task(
+            &mut transaction,
+            &task.title,
+            task.description.as_deref().unwrap_or_default(),
+            task.due_at,
+            user_id,
+            None,
+        )
+        .await?;
+        if task.completed {
+            tasks_db::set_completed(&mut transaction, task_id, true, user_id).await?;
+        }
+        //after completing it, which stamps completed_at with the current time
+        sqlx::query!(
+            r#"
+            update task
+            set created_at = coalesce($2, created_at),
+                completed_at = case when completed then coalesce($3, completed_at) end
+            where task_id = $1
+            "#,
+            task_id,
+            task.created_at,
+            task.completed_at
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+    Ok(())
+}
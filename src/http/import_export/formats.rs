@@ -0,0 +1,397 @@
+//! Writing tasks out as JSON, CSV or todo.txt and reading them back in.
+
+use std::fmt::{self, Display};
+
+use serde::Deserialize;
+use time::{
+    format_description::{well_known::Rfc3339, BorrowedFormatItem},
+    macros::format_description,
+    Date, OffsetDateTime,
+};
+
+use super::db::ExportedTask;
+
+/// The most tasks read from one file.
+pub const MAX_IMPORT_ROWS: usize = 1000;
+
+/// How todo.txt writes the creation and completion dates.
+const TODO_TXT_DATE: &[BorrowedFormatItem<'_>] = format_description!("[year]-[month]-[day]");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Json,
+    Csv,
+    TodoTxt,
+}
+
+impl Format {
+    /// The format a file is in going by its name.
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let (_, extension) = file_name.rsplit_once('.')?;
+        match extension.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            "txt" => Some(Self::TodoTxt),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::TodoTxt => "text/plain; charset=utf-8",
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Json => "todos.json",
+            Self::Csv => "todos.csv",
+            Self::TodoTxt => "todo.txt",
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+            Self::TodoTxt => "todotxt",
+        })
+    }
+}
+
+/// What a task is made from when it's imported. Dates the file doesn't have are the
+/// time of the import.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ImportedTask {
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub completed: bool,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_at: Option<OffsetDateTime>,
+    /// Only kept for completed tasks.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub completed_at: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub due_at: Option<OffsetDateTime>,
+}
+
+/// A task read from a file, or why it couldn't be.
+#[derive(Debug)]
+pub struct ParsedRow {
+    /// The position of the task in the file: the index in a JSON array counting from
+    /// 1, or the line it starts on otherwise.
+    pub row: usize,
+    pub task: Result<ImportedTask, String>,
+}
+
+pub fn export(format: Format, tasks: &[ExportedTask]) -> anyhow::Result<String> {
+    match format {
+        Format::Json => Ok(serde_json::to_string_pretty(tasks)?),
+        Format::Csv => Ok(export_csv(tasks)),
+        Format::TodoTxt => Ok(export_todo_txt(tasks)),
+    }
+}
+
+/// Reads every task in `input`, an error is a problem with the file as a whole.
+pub fn parse(format: Format, input: &str) -> Result<Vec<ParsedRow>, String> {
+    let rows = match format {
+        Format::Json => parse_json(input)?,
+        Format::Csv => parse_csv(input)?,
+        Format::TodoTxt => parse_todo_txt(input),
+    };
+    if rows.len() > MAX_IMPORT_ROWS {
+        return Err(format!(
+            "at most {MAX_IMPORT_ROWS} tasks can be imported at once"
+        ));
+    }
+    Ok(rows)
+}
+
+fn parse_json(input: &str) -> Result<Vec<ParsedRow>, String> {
+    let values: Vec<serde_json::Value> = serde_json::from_str(input)
+        .map_err(|error| format!("the file should be a JSON array of tasks: {error}"))?;
+    Ok(values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| ParsedRow {
+            row: i + 1,
+            task: serde_json::from_value(value).map_err(|error| error.to_string()),
+        })
+        .collect())
+}
+
+const CSV_COLUMNS: [&str; 6] = [
+    "title",
+    "description",
+    "completed",
+    "created_at",
+    "completed_at",
+    "due_at",
+];
+
+fn export_csv(tasks: &[ExportedTask]) -> String {
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push_str("\r\n");
+    for task in tasks {
+        let fields = [
+            escape_formula(&task.title),
+            escape_formula(task.description.as_deref().unwrap_or_default()),
+            task.completed.to_string(),
+            rfc3339(task.created_at),
+            task.completed_at.map(rfc3339).unwrap_or_default(),
+            task.due_at.map(rfc3339).unwrap_or_default(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+fn rfc3339(datetime: OffsetDateTime) -> String {
+    datetime.format(&Rfc3339).unwrap_or_default()
+}
+
+/// Spreadsheets run fields starting with one of these as formulas.
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Prefixes text that a spreadsheet would run as a formula with `'`, which it shows
+/// the text after. Text that already starts with `'` gets another one, so
+/// [`unescape_formula`] gives it back as it was.
+fn escape_formula(text: &str) -> String {
+    if text.trim_start_matches('\'').starts_with(FORMULA_PREFIXES) {
+        format!("'{text}")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Undoes [`escape_formula`].
+fn unescape_formula(text: &str) -> &str {
+    match text.strip_prefix('\'') {
+        Some(rest) if rest.trim_start_matches('\'').starts_with(FORMULA_PREFIXES) => rest,
+        _ => text,
+    }
+}
+
+/// Quotes a field when it holds a separator, a quote or a line break, RFC 4180.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Splits CSV into records of fields, along with the line each record starts on.
+/// Blank lines are skipped.
+fn csv_records(input: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = input
+        .strip_prefix('\u{feff}')
+        .unwrap_or(input)
+        .chars()
+        .peekable();
+
+    while let Some(c) = chars.next() {
+        match (in_quotes, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => in_quotes = false,
+            (true, c) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+            (false, '"') if field.is_empty() => in_quotes = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|field| !field.is_empty()) {
+                    records.push((record_line, std::mem::take(&mut record)));
+                }
+                record.clear();
+                line += 1;
+                record_line = line;
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!(
+            "the quoted field starting on line {record_line} is never closed"
+        ));
+    }
+    record.push(field);
+    if record.iter().any(|field| !field.is_empty()) {
+        records.push((record_line, record));
+    }
+    Ok(records)
+}
+
+/// The first record names the columns, `title` is needed and `description`,
+/// `completed`, `created_at`, `completed_at` and `due_at` are read when they're there.
+/// Other columns are ignored. Titles and descriptions escaped on export so spreadsheets
+/// don't run them are unescaped.
+fn parse_csv(input: &str) -> Result<Vec<ParsedRow>, String> {
+    let mut records = csv_records(input)?.into_iter();
+    let Some((_, header)) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(name))
+    };
+    let title =
+        column("title").ok_or("the first line should name the columns, with a title column")?;
+    let description = column("description");
+    let completed = column("completed");
+    let created_at = column("created_at");
+    let completed_at = column("completed_at");
+    let due_at = column("due_at");
+
+    Ok(records
+        .map(|(row, fields)| {
+            let field = |index: Option<usize>| {
+                index
+                    .and_then(|index| fields.get(index))
+                    .map(String::as_str)
+                    .unwrap_or_default()
+            };
+            let task = || {
+                Ok(ImportedTask {
+                    title: unescape_formula(field(Some(title))).to_string(),
+                    description: Some(unescape_formula(field(description)).to_string())
+                        .filter(|d| !d.is_empty()),
+                    completed: parse_completed(field(completed))?,
+                    created_at: parse_datetime("created_at", field(created_at))?,
+                    completed_at: parse_datetime("completed_at", field(completed_at))?,
+                    due_at: parse_datetime("due_at", field(due_at))?,
+                })
+            };
+            ParsedRow { row, task: task() }
+        })
+        .collect())
+}
+
+fn parse_completed(value: &str) -> Result<bool, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" | "x" => Ok(true),
+        "false" | "no" | "0" | "" => Ok(false),
+        _ => Err(format!("completed should be true or false, not {value:?}")),
+    }
+}
+
+/// An RFC 3339 date and time, like the ones exported, or nothing for an empty field.
+fn parse_datetime(column: &str, value: &str) -> Result<Option<OffsetDateTime>, String> {
+    match value.trim() {
+        "" => Ok(None),
+        value => OffsetDateTime::parse(value, &Rfc3339)
+            .map(Some)
+            .map_err(|_| {
+                format!(
+                    "{column} should be a date and time like 2026-10-01T09:30:00Z, not {value:?}"
+                )
+            }),
+    }
+}
+
+/// One task per line, `x` and the completion date first for completed ones, which is
+/// the creation date for completed tasks without a completion date. Every
+/// task has its creation date, so titles starting with a date keep it on import.
+/// todo.txt has no descriptions or due dates, so they're left out.
+fn export_todo_txt(tasks: &[ExportedTask]) -> String {
+    let date = |datetime: OffsetDateTime| datetime.format(TODO_TXT_DATE).unwrap_or_default();
+    let mut todo_txt = String::new();
+    for task in tasks {
+        if task.completed {
+            todo_txt.push_str("x ");
+            todo_txt.push_str(&date(task.completed_at.unwrap_or(task.created_at)));
+            todo_txt.push(' ');
+        }
+        todo_txt.push_str(&date(task.created_at));
+        todo_txt.push(' ');
+        todo_txt.push_str(&task.title.replace(['\r', '\n'], " "));
+        todo_txt.push('\n');
+    }
+    todo_txt
+}
+
+/// The completion mark and the dates are read, at midnight UTC, while priorities are
+/// dropped from the title. Only completed tasks have a completion date before the
+/// creation date, so a date after those is part of the title. Projects and contexts
+/// stay part of it.
+fn parse_todo_txt(input: &str) -> Vec<ParsedRow> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let mut rest = line.trim();
+            let completed = match rest.strip_prefix("x ") {
+                Some(after) => {
+                    rest = after.trim_start();
+                    true
+                }
+                None => false,
+            };
+            if let Some(after) = strip_priority(rest) {
+                rest = after;
+            }
+            let completed_at = if completed {
+                take_date(&mut rest)
+            } else {
+                None
+            };
+            let created_at = take_date(&mut rest);
+            ParsedRow {
+                row: i + 1,
+                task: Ok(ImportedTask {
+                    title: rest.to_string(),
+                    description: None,
+                    completed,
+                    created_at,
+                    completed_at,
+                    due_at: None,
+                }),
+            }
+        })
+        .collect()
+}
+
+fn strip_priority(text: &str) -> Option<&str> {
+    match text.as_bytes() {
+        [b'(', priority, b')', b' ', ..] if priority.is_ascii_uppercase() => {
+            Some(text[4..].trim_start())
+        }
+        _ => None,
+    }
+}
+
+/// Takes the date `text` starts with off it.
+fn take_date(text: &mut &str) -> Option<OffsetDateTime> {
+    let (date, rest) = text.split_at_checked(10)?;
+    let date = Date::parse(date, TODO_TXT_DATE).ok()?;
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    *text = rest.trim_start();
+    Some(date.midnight().assume_utc())
+}
@@ -0,0 +1,169 @@
+//! Moving a user's tasks out of the app and into it as JSON, CSV or todo.txt.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use super::{error::Error, tasks::validate_task};
+use db::ExportedTask;
+use formats::{ImportedTask, ParsedRow};
+
+mod db;
+mod formats;
+mod routes;
+mod templates;
+
+pub use routes::router;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RowStatus {
+    New,
+    /// The user already has the task, or it came earlier in the file.
+    Duplicate,
+    Invalid,
+}
+
+impl RowStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::New => "New",
+            Self::Duplicate => "Already there, skipped",
+            Self::Invalid => "Invalid",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportRow {
+    pub row: usize,
+    pub title: String,
+    pub status: RowStatus,
+    pub errors: Vec<String>,
+    #[serde(skip)]
+    task: Option<ImportedTask>,
+}
+
+/// What an import did, or would do on a dry run.
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// Whether the new tasks were created, which needs every row to be valid.
+    pub imported: bool,
+    pub new: usize,
+    pub duplicates: usize,
+    pub invalid: usize,
+    pub rows: Vec<ReportRow>,
+}
+
+impl ImportReport {
+    /// Checks every row and marks the ones already among the user's personal tasks, which
+    /// imports go to, as duplicates, going by their title, ignoring case, and description.
+    /// Rows without a description, like every todo.txt row, go by their title alone.
+    fn new(rows: Vec<ParsedRow>, existing: &[ExportedTask], dry_run: bool) -> Self {
+        let mut seen = SeenTasks::default();
+        for task in existing {
+            seen.insert(&task.title, task.description.as_deref());
+        }
+
+        let rows: Vec<ReportRow> = rows
+            .into_iter()
+            .map(|ParsedRow { row, task }| {
+                let mut task = match task {
+                    Ok(task) => task,
+                    Err(error) => {
+                        return ReportRow {
+                            row,
+                            title: String::new(),
+                            status: RowStatus::Invalid,
+                            errors: vec![error],
+                            task: None,
+                        }
+                    }
+                };
+                task.title = task.title.trim().to_string();
+                let errors = row_errors(&task);
+                let status = if !errors.is_empty() {
+                    RowStatus::Invalid
+                } else if seen.insert(&task.title, task.description.as_deref()) {
+                    RowStatus::New
+                } else {
+                    RowStatus::Duplicate
+                };
+                ReportRow {
+                    row,
+                    title: task.title.clone(),
+                    status,
+                    errors,
+                    task: Some(task),
+                }
+            })
+            .collect();
+
+        let count = |status| rows.iter().filter(|row| row.status == status).count();
+        Self {
+            dry_run,
+            imported: false,
+            new: count(RowStatus::New),
+            duplicates: count(RowStatus::Duplicate),
+            invalid: count(RowStatus::Invalid),
+            rows,
+        }
+    }
+
+    /// Whether the new tasks can be imported as they are.
+    pub fn can_import(&self) -> bool {
+        self.invalid == 0 && self.new > 0
+    }
+
+    fn new_tasks(&self) -> Vec<ImportedTask> {
+        self.rows
+            .iter()
+            .filter(|row| row.status == RowStatus::New)
+            .filter_map(|row| row.task.clone())
+            .collect()
+    }
+}
+
+/// The tasks an import has come across, to tell duplicates apart.
+#[derive(Default)]
+struct SeenTasks {
+    titles: HashSet<String>,
+    described: HashSet<(String, String)>,
+}
+
+impl SeenTasks {
+    /// Returns whether the task wasn't seen yet, any task with the same title counts
+    /// when it has no description.
+    fn insert(&mut self, title: &str, description: Option<&str>) -> bool {
+        let title = title.trim().to_lowercase();
+        let description = description.unwrap_or_default().trim();
+        let new = match description {
+            "" => !self.titles.contains(&title),
+            description => !self
+                .described
+                .contains(&(title.clone(), description.to_string())),
+        };
+        self.described
+            .insert((title.clone(), description.to_string()));
+        self.titles.insert(title);
+        new
+    }
+}
+
+/// The same checks as a task made on the site.
+fn row_errors(task: &ImportedTask) -> Vec<String> {
+    let description = task.description.as_deref().unwrap_or_default();
+    match validate_task(&task.title, description).finish() {
+        Err(Error::UnprocessableEntity { errors }) => {
+            let mut errors: Vec<String> = errors
+                .into_values()
+                .flatten()
+                .map(|error| error.into_owned())
+                .collect();
+            errors.sort();
+            errors
+        }
+        _ => Vec::new(),
+    }
+}
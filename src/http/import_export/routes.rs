@@ -0,0 +1,204 @@
+use axum::{
+    extract::{Multipart, Path, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE},
+        HeaderMap, StatusCode,
+    },
+    middleware::from_fn,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+
+use crate::http::users::{auth_middleware, UserSessionData};
+
+use super::super::{
+    error::Error,
+    error_pages::wants_json,
+    layout::{LazyPageContext, PageContext},
+    tasks::count_tasks,
+    utilities::{
+        render_invalid_form, render_template, ApiState, FlashMessageLevel, FlashMessages, Result,
+    },
+};
+use super::{
+    db,
+    formats::{self, Format},
+    templates::ImportExportTemplate,
+    ImportReport,
+};
+
+pub fn router() -> Router<ApiState> {
+    Router::new()
+        .route("/", get(import_export_page))
+        .route("/export/{format}", get(export))
+        .route("/import", post(import))
+        .route_layer(from_fn(auth_middleware))
+}
+
+#[instrument(skip_all, fields(action = "displaying import and export page", %user_session))]
+pub async fn import_export_page(
+    Extension(user_session): Extension<UserSessionData>,
+    ctx: PageContext,
+) -> Result<Html<String>> {
+    render_template(ImportExportTemplate {
+        ctx,
+        report: None,
+        format: None,
+        content: String::new(),
+    })
+}
+
+#[instrument(skip_all, fields(action = "exporting tasks", %user_session, ?format))]
+pub async fn export(
+    State(pool): State<PgPool>,
+    Extension(user_session): Extension<UserSessionData>,
+    Path(format): Path<Format>,
+) -> Result<Response> {
+    let tasks = db::get_export_tasks(&pool, user_session.user_id()).await?;
+    let body = formats::export(format, &tasks)?;
+
+    Ok((
+        [
+            (CONTENT_TYPE, format.content_type().to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!(r#"attachment; filename="{}""#, format.file_name()),
+            ),
+            (CACHE_CONTROL, "private, no-cache".to_string()),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// An import as it was submitted: an uploaded file, or the content of one sent back
+/// from the preview.
+struct ImportForm {
+    format: Option<Format>,
+    content: String,
+    dry_run: bool,
+}
+
+fn import_error(error: impl ToString) -> Error {
+    Error::unprocessable_entity([("file", error.to_string())])
+}
+
+async fn read_import_form(multipart: &mut Multipart) -> Result<ImportForm> {
+    let mut format = None;
+    let mut file_format = None;
+    let mut file = None;
+    let mut content = None;
+    let mut dry_run = false;
+
+    while let Some(field) = multipart.next_field().await.map_err(import_error)? {
+        match field.name().unwrap_or_default() {
+            "format" => {
+                format = match field.text().await.map_err(import_error)?.as_str() {
+                    "json" => Some(Format::Json),
+                    "csv" => Some(Format::Csv),
+                    "todotxt" => Some(Format::TodoTxt),
+                    _ => None,
+                }
+            }
+            "file" => {
+                file_format = field.file_name().and_then(Format::from_file_name);
+                let data = field.bytes().await.map_err(import_error)?;
+                if !data.is_empty() {
+                    let text = String::from_utf8(data.to_vec())
+                        .map_err(|_| import_error("the file should be UTF-8 text"))?;
+                    file = Some(text);
+                }
+            }
+            "content" => content = Some(field.text().await.map_err(import_error)?),
+            "dry_run" => dry_run = field.text().await.map_err(import_error)? == "true",
+            _ => {}
+        }
+    }
+
+    let content = file
+        .or(content)
+        .filter(|content| !content.trim().is_empty())
+        .ok_or_else(|| import_error("choose a file to import"))?;
+    Ok(ImportForm {
+        format: format.or(file_format),
+        content,
+        dry_run,
+    })
+}
+
+/// Reads the tasks in a file and reports on every row. Unless it's a dry run the new
+/// tasks are created, all together or, when any row is invalid, none of them.
+/// Duplicates are skipped. API clients get the report as JSON.
+#[instrument(skip_all, fields(action = "importing tasks", %user_session))]
+pub async fn import(
+    State(pool): State<PgPool>,
+    Extension(user_session): Extension<UserSessionData>,
+    headers: HeaderMap,
+    mut flash_msgs: FlashMessages,
+    ctx: LazyPageContext,
+    mut multipart: Multipart,
+) -> Result<Response> {
+    let json = wants_json(&headers);
+    let parsed = read_import_form(&mut multipart).await.and_then(|form| {
+        let format = form
+            .format
+            .ok_or_else(|| import_error("choose the format of the file"))?;
+        let rows = formats::parse(format, &form.content).map_err(import_error)?;
+        Ok((form, format, rows))
+    });
+    let (form, format, rows) = match parsed {
+        Ok(parsed) => parsed,
+        Err(error) if json => return Err(error),
+        Err(error) => {
+            flash_msgs.set_invalid(error).await?;
+            return Ok(Redirect::to("/data").into_response());
+        }
+    };
+
+    let user_id = user_session.user_id();
+    let existing = db::get_export_tasks(&pool, user_id).await?;
+    let mut report = ImportReport::new(rows, &existing, form.dry_run);
+    if !report.dry_run && report.invalid == 0 {
+        db::import_tasks(&pool, user_id, &report.new_tasks()).await?;
+        report.imported = true;
+        info!(
+            new = report.new,
+            duplicates = report.duplicates,
+            "tasks imported"
+        );
+    }
+
+    let status = match report.dry_run || report.invalid == 0 {
+        true => StatusCode::OK,
+        false => StatusCode::UNPROCESSABLE_ENTITY,
+    };
+    if json {
+        return Ok((status, Json(report)).into_response());
+    }
+
+    if report.imported {
+        let mut msg = format!("Imported {}", count_tasks(report.new));
+        if report.duplicates > 0 {
+            msg.push_str(&format!(
+                ", skipped {} you already had",
+                count_tasks(report.duplicates)
+            ));
+        }
+        flash_msgs.set_msg(FlashMessageLevel::Success, &msg).await?;
+        return Ok(Redirect::to("/todo").into_response());
+    }
+
+    let template = ImportExportTemplate {
+        ctx: ctx.build().await?,
+        report: Some(report),
+        format: Some(format),
+        content: form.content,
+    };
+    match status {
+        StatusCode::OK => Ok(render_template(template)?.into_response()),
+        _ => render_invalid_form(template),
+    }
+}
@@ -0,0 +1,15 @@
+use askama::Template;
+
+use super::super::{filters, layout::PageContext};
+use super::{formats::Format, ImportReport};
+
+#[derive(Template)]
+#[template(path = "import_export.html")]
+pub struct ImportExportTemplate {
+    pub ctx: PageContext,
+    /// The outcome of a preview, or of an import that had invalid rows.
+    pub report: Option<ImportReport>,
+    pub format: Option<Format>,
+    /// The file that was read, sent back to import it after a preview.
+    pub content: String,
+}
@@ -0,0 +1,112 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use anyhow::anyhow;
+use axum::{extract::FromRequestParts, http::request::Parts};
+use tower_sessions::Session;
+
+use super::{
+    error::Error,
+    security_headers::CspNonce,
+    users::{CurrentWorkspace, SessionExt, UserSessionData},
+    utilities::{FlashMessage, FlashMessages, Result},
+};
+
+/// Context shared by every page extending `base.html`, it feeds the nav, the flash
+/// message banner and the nonce on the page's stylesheets and scripts.
+pub struct PageContext {
+    pub user: Option<UserSessionData>,
+    /// Shown by the workspace switcher in the nav.
+    pub workspace: Option<CurrentWorkspace>,
+    pub flash_msgs: Vec<FlashMessage>,
+    pub nonce: CspNonce,
+}
+
+impl<S> FromRequestParts<S> for PageContext
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        LazyPageContext::from_request_parts(parts, state)
+            .await?
+            .build()
+            .await
+    }
+}
+
+/// A [`PageContext`] for handlers that only render a page when the form they got is
+/// invalid and redirect otherwise. The pending flash messages are only taken when the
+/// context is built, so they're still shown on the page the handler redirects to.
+pub struct LazyPageContext {
+    user: Option<UserSessionData>,
+    workspace: Option<CurrentWorkspace>,
+    flash_msgs: FlashMessages,
+    nonce: CspNonce,
+}
+
+impl LazyPageContext {
+    /// Takes the pending flash messages, to show them on the page about to be rendered.
+    pub async fn build(mut self) -> Result<PageContext> {
+        Ok(PageContext {
+            user: self.user,
+            workspace: self.workspace,
+            flash_msgs: self.flash_msgs.get_msgs().await?,
+            nonce: self.nonce,
+        })
+    }
+}
+
+impl<S> FromRequestParts<S> for LazyPageContext
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let nonce = CspNonce::from_request_parts(parts, state).await?;
+        let flash_msgs = FlashMessages::from_request_parts(parts, state).await?;
+
+        let session = Session::from_request_parts(parts, state)
+            .await
+            .map_err(|_| Error::Other(anyhow!("Session manager layer seems to not be present")))?;
+        let user = match parts.extensions.get::<UserSessionData>() {
+            Some(user) => Some(user.clone()),
+            None => session.user_session().await?,
+        };
+        let workspace = match user {
+            Some(_) => session.current_workspace().await?,
+            None => None,
+        };
+
+        Ok(Self {
+            user,
+            workspace,
+            flash_msgs,
+            nonce,
+        })
+    }
+}
+
+/// Field level errors of a submitted form keyed by field name, shown next to the
+/// fields by the `field` macro in `macros.html`.
+#[derive(Debug, Default)]
+pub struct FormErrors(HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>);
+
+/// Only validation errors become form errors, everything else is handed back as is.
+impl TryFrom<Error> for FormErrors {
+    type Error = Error;
+
+    fn try_from(error: Error) -> Result<Self, Self::Error> {
+        match error {
+            Error::UnprocessableEntity { errors } => Ok(Self(errors)),
+            other => Err(other),
+        }
+    }
+}
+
+impl FormErrors {
+    pub fn get(&self, field: &str) -> &[Cow<'static, str>] {
+        self.0.get(field).map(Vec::as_slice).unwrap_or_default()
+    }
+}
@@ -2,7 +2,8 @@ use anyhow::Context;
 use askama::Template;
 use axum::{
     http::{HeaderName, Request},
-    response::IntoResponse,
+    middleware::{from_fn, from_fn_with_state},
+    response::Html,
     routing::get,
     Router,
 };
@@ -10,53 +11,126 @@ use sqlx::PgPool;
 use tokio::net::TcpListener;
 use tower::ServiceBuilder;
 use tower_http::{request_id::MakeRequestUuid, trace::TraceLayer, ServiceBuilderExt};
-use tower_sessions::{MemoryStore, SessionManagerLayer};
+use tower_sessions::SessionManagerLayer;
+use tower_sessions_sqlx_store::PostgresStore;
 use tracing::{info, info_span, instrument, trace};
-use utilities::{render_template, ApiState, HmacKey};
+use utilities::{render_template, ApiState, HmacKey, Result};
 
-use crate::config::Settings;
+use attachments::Attachments;
+use layout::PageContext;
+use security_headers::{security_headers, SecurityHeadersState};
 
+use crate::config::{Application, Settings};
+
+mod assets;
+pub(crate) mod attachments;
+mod authorization;
+mod caldav;
+mod calendar;
+mod comments;
 mod error;
-mod tasks;
-mod users;
+mod error_pages;
+mod filters;
+mod import_export;
+mod layout;
+mod security_headers;
+mod stats;
+pub(crate) mod tasks;
+mod tls;
+pub(crate) mod users;
 pub mod utilities;
+mod validation;
+mod workspaces;
 
-const REQUEST_ID_HEADER: &'static str = "todo-request-id";
+const REQUEST_ID_HEADER: &str = "todo-request-id";
 
 pub async fn serve_app(
     config: Settings,
     pool: PgPool,
     listener: TcpListener,
 ) -> anyhow::Result<()> {
+    trace!("preparing session store");
+    let session_store = session_store(pool.clone()).await?;
+
     trace!("constructing ApiState");
-    let state = ApiState::new(pool, HmacKey(config.application.hmac_key.clone()));
+    let attachments = Attachments::from_config(&config.attachments)
+        .context("Error setting up attachment storage")?;
+    let state = ApiState::new(
+        pool,
+        HmacKey(config.application.hmac_key.clone()),
+        attachments,
+        config.trash.clone(),
+    );
+
+    trace!("starting the trash purge");
+    tokio::spawn(tasks::trash::purge_periodically(
+        state.pool.clone(),
+        state.attachments.clone(),
+        state.trash.clone(),
+    ));
 
     trace!("making api_router");
-    let app = api_router(state);
+    let app = api_router(state, session_store, &config.application);
 
-    info!("serving app");
-    axum::serve(listener, app)
+    match config.application.tls.clone() {
+        Some(tls) => tls::serve_tls(tls, &config.application.host, app, listener).await,
+        None => {
+            info!("serving app");
+            axum::serve(listener, app)
+                .await
+                .context("Error running HTTP server")
+        }
+    }
+}
+
+/// Postgres backed session store, so that sessions survive restarts and can be
+/// shared between replicas. Creates the store's table if it doesn't exist yet.
+pub async fn session_store(pool: PgPool) -> anyhow::Result<PostgresStore> {
+    let session_store = PostgresStore::new(pool);
+    session_store
+        .migrate()
         .await
-        .context("Error running HTTP server")
+        .context("Error migrating the session store")?;
+    Ok(session_store)
 }
 
 #[derive(Template)]
 #[template(path = "home.html")]
-struct HomeTemplate;
+struct HomeTemplate {
+    ctx: PageContext,
+}
 
-#[instrument(fields(action = "serving home page"))]
-async fn home_page() -> impl IntoResponse {
-    render_template(HomeTemplate)
+#[instrument(skip_all, fields(action = "serving home page"))]
+async fn home_page(ctx: PageContext) -> Result<Html<String>> {
+    render_template(HomeTemplate { ctx })
 }
 
-pub fn api_router(state: ApiState) -> Router {
+/// Session cookies are marked secure when serving over HTTPS, so browsers never send
+/// them over plain HTTP.
+pub fn api_router(
+    state: ApiState,
+    session_store: PostgresStore,
+    application: &Application,
+) -> Router {
     let req_id_header = HeaderName::from_static(REQUEST_ID_HEADER);
-    let session_store = MemoryStore::default();
-    let session_layer = SessionManagerLayer::new(session_store).with_secure(false);
+    let https = application.tls.is_some();
+    let session_layer = SessionManagerLayer::new(session_store).with_secure(https);
+    let security_headers_state = SecurityHeadersState::new(&application.security_headers, https);
+    let attachments = attachments::router(state.attachments.max_file_size);
     Router::new()
         .route("/", get(home_page))
-        .nest("/todo", tasks::router())
+        .route("/static/{*path}", get(assets::serve_asset))
+        .nest(
+            "/todo",
+            tasks::router().merge(comments::router()).merge(attachments),
+        )
+        .merge(caldav::router())
+        .nest("/calendar", calendar::router())
+        .nest("/data", import_export::router())
+        .nest("/stats", stats::router())
         .nest("/users", users::router())
+        .nest("/workspaces", workspaces::router())
+        .fallback(error_pages::not_found)
         .with_state(state)
         .layer(
             ServiceBuilder::new()
@@ -79,6 +153,8 @@ pub fn api_router(state: ApiState) -> Router {
                     }),
                 )
                 .propagate_request_id(req_id_header)
-                .layer(session_layer),
+                .layer(from_fn_with_state(security_headers_state, security_headers))
+                .layer(session_layer)
+                .layer(from_fn(error_pages::error_pages)),
         )
 }
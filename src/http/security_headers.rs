@@ -0,0 +1,129 @@
+use std::{fmt::Display, sync::Arc};
+
+use anyhow::anyhow;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{
+        header::{
+            CONTENT_SECURITY_POLICY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY,
+            X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+        },
+        request::Parts,
+        HeaderName, HeaderValue,
+    },
+    middleware::Next,
+    response::Response,
+};
+
+use crate::config::SecurityHeaders;
+
+use super::error::Error;
+
+/// Per request nonce for the Content-Security-Policy, templates put it on their
+/// inline `<script>` and `<style>` elements.
+#[derive(Debug, Clone)]
+pub struct CspNonce(String);
+
+impl CspNonce {
+    fn generate() -> Self {
+        let mut bytes = [0u8; 16];
+        OsRng.fill_bytes(&mut bytes);
+        Self(hex::encode(bytes))
+    }
+
+    fn policy(&self) -> HeaderValue {
+        let nonce = &self.0;
+        HeaderValue::from_str(&format!(
+            "default-src 'self'; script-src 'self' 'nonce-{nonce}'; \
+            style-src 'self' 'nonce-{nonce}'; img-src 'self' data:; object-src 'none'; \
+            base-uri 'self'; form-action 'self'; frame-ancestors 'none'"
+        ))
+        .expect("hex nonce should make a valid header value")
+    }
+}
+
+impl Display for CspNonce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<S> FromRequestParts<S> for CspNonce
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CspNonce>()
+            .cloned()
+            .ok_or_else(|| Error::Other(anyhow!("Security headers layer seems to not be present")))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersState {
+    content_security_policy: bool,
+    headers: Arc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl SecurityHeadersState {
+    /// `https` decides whether Strict-Transport-Security is sent at all.
+    pub fn new(config: &SecurityHeaders, https: bool) -> Self {
+        let mut headers = vec![
+            (X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+            (
+                X_FRAME_OPTIONS,
+                HeaderValue::from_str(&config.frame_options)
+                    .expect("frame_options should be a valid header value"),
+            ),
+            (
+                REFERRER_POLICY,
+                HeaderValue::from_str(&config.referrer_policy)
+                    .expect("referrer_policy should be a valid header value"),
+            ),
+        ];
+        if https && config.hsts_max_age > 0 {
+            headers.push((
+                STRICT_TRANSPORT_SECURITY,
+                HeaderValue::from_str(&format!(
+                    "max-age={}; includeSubDomains",
+                    config.hsts_max_age
+                ))
+                .expect("hsts header should be a valid header value"),
+            ));
+        }
+
+        Self {
+            content_security_policy: config.content_security_policy,
+            headers: Arc::new(headers),
+        }
+    }
+}
+
+/// Generates the request's [`CspNonce`] and sets the security headers on responses
+/// that don't already carry them.
+pub async fn security_headers(
+    State(state): State<SecurityHeadersState>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let nonce = CspNonce::generate();
+    req.extensions_mut().insert(nonce.clone());
+
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    for (name, value) in state.headers.iter() {
+        headers.entry(name).or_insert_with(|| value.clone());
+    }
+    if state.content_security_policy {
+        headers
+            .entry(CONTENT_SECURITY_POLICY)
+            .or_insert_with(|| nonce.policy());
+    }
+
+    response
+}
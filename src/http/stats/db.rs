@@ -0,0 +1,140 @@
+use sqlx::PgPool;
+use time::Date;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::super::utilities::Result;
+
+/// Totals over every task in scope.
+#[derive(Debug)]
+pub struct Summary {
+    pub completed: i64,
+    pub open: i64,
+    /// Open tasks past their due date.
+    pub overdue: i64,
+    /// Mean time from creating a task to completing it, `None` before any are completed.
+    pub average_seconds_to_complete: Option<f64>,
+    /// Days in a row, up to today or yesterday, with at least one task completed.
+    pub streak_days: i64,
+}
+
+/// How many tasks were completed in a day or a week, starting on `start`.
+#[derive(Debug)]
+pub struct CompletedIn {
+    pub start: Date,
+    pub completed: i64,
+}
+
+/// The tasks counted are the same as on the `/todo` page: the user's personal tasks and
+/// the ones shared with them, or everything in the current workspace. Days are UTC.
+#[instrument(skip(pool))]
+pub async fn get_summary(
+    pool: &PgPool,
+    user_id: Uuid,
+    workspace_id: Option<Uuid>,
+) -> Result<Summary> {
+    let summary = sqlx::query_as!(
+        Summary,
+        r#"
+        with scoped as (
+            select t.created_at, t.completed_at, t.due_at
+            from task t
+            left join task_share s on s.task_id = t.task_id and s.user_id = $1
+            where t.deleted_at is null
+            and case
+                when $2::uuid is null
+                    then (t.workspace_id is null and t.user_id = $1) or s.user_id is not null
+                else t.workspace_id = $2
+            end
+        ),
+        days as (
+            select distinct (completed_at at time zone 'UTC')::date as day
+            from scoped
+            where completed_at is not null
+        ),
+        islands as (
+            select day, day - (row_number() over (order by day))::int as island
+            from days
+        ),
+        today as (
+            select (now() at time zone 'UTC')::date as day
+        )
+        select
+            count(completed_at) as "completed!",
+            count(*) - count(completed_at) as "open!",
+            count(*) filter (where completed_at is null and due_at < now()) as "overdue!",
+            extract(epoch from avg(completed_at - created_at))::float8
+                as average_seconds_to_complete,
+            (
+                select count(*) from islands
+                where island = (
+                    select island from islands
+                    where day >= (select day from today) - 1
+                    order by day desc
+                    limit 1
+                )
+            ) as "streak_days!"
+        from scoped
+        "#,
+        user_id,
+        workspace_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(summary)
+}
+
+/// Completed tasks per day or per week, for the last `periods` of them including the
+/// current one, oldest first. Periods without completed tasks are counted as 0.
+#[instrument(skip(pool))]
+pub async fn get_completed_per(
+    pool: &PgPool,
+    user_id: Uuid,
+    workspace_id: Option<Uuid>,
+    weekly: bool,
+    periods: i32,
+) -> Result<Vec<CompletedIn>> {
+    let completed = sqlx::query_as!(
+        CompletedIn,
+        r#"
+        with periods as (
+            select generate_series(
+                date_trunc(
+                    case when $3 then 'week' else 'day' end,
+                    now() at time zone 'UTC'
+                ) - case when $3 then interval '1 week' else interval '1 day' end * ($4 - 1),
+                date_trunc(case when $3 then 'week' else 'day' end, now() at time zone 'UTC'),
+                case when $3 then interval '1 week' else interval '1 day' end
+            ) as start
+        ),
+        completed as (
+            select date_trunc(
+                case when $3 then 'week' else 'day' end,
+                t.completed_at at time zone 'UTC'
+            ) as start
+            from task t
+            left join task_share s on s.task_id = t.task_id and s.user_id = $1
+            where t.deleted_at is null and t.completed_at is not null
+            and case
+                when $2::uuid is null
+                    then (t.workspace_id is null and t.user_id = $1) or s.user_id is not null
+                else t.workspace_id = $2
+            end
+        )
+        select p.start::date as "start!", count(c.start) as "completed!"
+        from periods p
+        left join completed c on c.start = p.start
+        group by p.start
+        order by p.start
+        "#,
+        user_id,
+        workspace_id,
+        weekly,
+        periods
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(completed)
+}
@@ -0,0 +1,34 @@
+mod db;
+mod routes;
+mod templates;
+
+pub use routes::router;
+
+/// Formats a duration in its largest unit and the one after it, e.g. `2 days 3 hours`
+/// or `5 minutes`.
+pub fn format_duration(seconds: f64) -> String {
+    const UNITS: [(&str, u64); 4] = [
+        ("day", 86_400),
+        ("hour", 3_600),
+        ("minute", 60),
+        ("second", 1),
+    ];
+    let plural = |count: u64, name: &str| match count {
+        1 => format!("1 {name}"),
+        count => format!("{count} {name}s"),
+    };
+
+    let seconds = seconds.max(0.0).round() as u64;
+    let Some(largest) = UNITS.iter().position(|&(_, size)| seconds >= size) else {
+        return plural(0, "second");
+    };
+    let (name, size) = UNITS[largest];
+    let mut formatted = plural(seconds / size, name);
+    if let Some(&(next_name, next_size)) = UNITS.get(largest + 1) {
+        let next = seconds % size / next_size;
+        if next > 0 {
+            formatted = format!("{formatted} {}", plural(next, next_name));
+        }
+    }
+    formatted
+}
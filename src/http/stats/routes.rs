@@ -0,0 +1,53 @@
+use axum::{extract::State, middleware::from_fn, response::Html, routing::get, Extension, Router};
+use sqlx::PgPool;
+use tower_sessions::Session;
+use tracing::instrument;
+
+use crate::http::users::{auth_middleware, UserSessionData};
+
+use super::super::{
+    authorization::WorkspaceAction,
+    layout::PageContext,
+    utilities::{render_template, ApiState, Result},
+    workspaces::current_workspace,
+};
+use super::{db, format_duration, templates::StatsTemplate};
+
+/// Days shown in the per day chart, today included.
+const DAYS: i32 = 14;
+/// Weeks shown in the per week chart, this week included.
+const WEEKS: i32 = 8;
+
+pub fn router() -> Router<ApiState> {
+    Router::new()
+        .route("/", get(stats_page))
+        .route_layer(from_fn(auth_middleware))
+}
+
+#[instrument(skip_all, fields(action = "displaying stats page", %user_session))]
+pub async fn stats_page(
+    State(pool): State<PgPool>,
+    Extension(user_session): Extension<UserSessionData>,
+    session: Session,
+    mut ctx: PageContext,
+) -> Result<Html<String>> {
+    let workspace =
+        current_workspace(&pool, &session, &user_session, WorkspaceAction::View).await?;
+    let workspace_id = workspace
+        .as_ref()
+        .map(|(workspace, _)| workspace.workspace_id);
+    let user_id = user_session.user_id();
+
+    let summary = db::get_summary(&pool, user_id, workspace_id).await?;
+    let daily = db::get_completed_per(&pool, user_id, workspace_id, false, DAYS).await?;
+    let weekly = db::get_completed_per(&pool, user_id, workspace_id, true, WEEKS).await?;
+
+    ctx.workspace = workspace.map(|(workspace, _)| workspace);
+    render_template(StatsTemplate {
+        ctx,
+        average_time_to_complete: summary.average_seconds_to_complete.map(format_duration),
+        summary,
+        daily,
+        weekly,
+    })
+}
@@ -0,0 +1,27 @@
+use askama::Template;
+
+use super::super::{filters, layout::PageContext};
+use super::db::{CompletedIn, Summary};
+
+#[derive(Template)]
+#[template(path = "stats.html")]
+pub struct StatsTemplate {
+    pub ctx: PageContext,
+    pub summary: Summary,
+    /// Shown as text next to the summary, see [`super::format_duration`].
+    pub average_time_to_complete: Option<String>,
+    pub daily: Vec<CompletedIn>,
+    pub weekly: Vec<CompletedIn>,
+}
+
+impl StatsTemplate {
+    /// The most completed in one period, the full length of the bars in `periods`.
+    pub fn busiest(periods: &[CompletedIn]) -> i64 {
+        periods
+            .iter()
+            .map(|period| period.completed)
+            .max()
+            .unwrap_or(0)
+            .max(1)
+    }
+}
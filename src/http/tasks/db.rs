@@ -1,9 +1,15 @@
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use time::OffsetDateTime;
-use tracing::{instrument, warn};
+use tracing::instrument;
 use uuid::Uuid;
 
-use super::super::{error::Error, utilities::Result};
+use super::super::{
+    authorization::{task_grants, ShareRole, TaskAction, WorkspaceRole},
+    error::Error,
+    filters::format_datetime,
+    utilities::Result,
+};
+use super::history::{record_event, FieldChange, TaskEventKind};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Task {
@@ -14,58 +20,693 @@ pub struct Task {
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
     pub user_id: Uuid,
+    pub workspace_id: Option<Uuid>,
+    pub assignee_id: Option<Uuid>,
+    pub due_at: Option<OffsetDateTime>,
+    /// Bumped by every change to the task, for optimistic concurrency control.
+    pub version: i32,
 }
 
-#[instrument(skip_all, fields(%title, %description, %user_id))]
+/// A task on the `/todo` page: one of the user's own, one shared with them or one
+/// in the current workspace.
+#[derive(Debug)]
+pub struct ListedTask {
+    pub task_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    pub owner_name: String,
+    pub assignee_name: Option<String>,
+    pub due_at: Option<OffsetDateTime>,
+    /// Still open after it was due.
+    pub overdue: bool,
+    pub owned: bool,
+    pub share_role: Option<ShareRole>,
+    pub workspace_role: Option<WorkspaceRole>,
+}
+
+impl ListedTask {
+    fn allows(&self, action: TaskAction) -> bool {
+        task_grants(self.owned, false, self.workspace_role, self.share_role).allows(action)
+    }
+
+    pub fn can_edit(&self) -> bool {
+        self.allows(TaskAction::Edit)
+    }
+
+    pub fn can_delete(&self) -> bool {
+        self.allows(TaskAction::Delete)
+    }
+}
+
+/// Everything shown on a task's page besides its comments.
+#[derive(Debug)]
+pub struct TaskDetails {
+    pub task_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+    pub owner_name: String,
+    pub assignee_name: Option<String>,
+    pub workspace_name: Option<String>,
+    pub version: i32,
+    pub completed_at: Option<OffsetDateTime>,
+    pub due_at: Option<OffsetDateTime>,
+    /// Still open after it was due.
+    pub overdue: bool,
+}
+
+/// Narrows down the tasks on the `/todo` page.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskFilter {
+    #[default]
+    All,
+    AssignedToMe,
+    CreatedByMe,
+}
+
+/// A user with access to a task, who it can be assigned to.
+#[derive(Debug)]
+pub struct TaskUser {
+    pub user_id: Uuid,
+    pub username: String,
+}
+
+/// Someone a task is shared with.
+#[derive(Debug)]
+pub struct TaskShare {
+    pub user_id: Uuid,
+    pub username: String,
+    pub email: String,
+    pub role: ShareRole,
+}
+
+#[instrument(skip_all, fields(%title, %description, ?due_at, %user_id, ?workspace_id))]
 pub async fn create_new_task(
     pool: &PgPool,
     title: &str,
     description: &str,
+    due_at: Option<OffsetDateTime>,
     user_id: Uuid,
+    workspace_id: Option<Uuid>,
 ) -> Result<()> {
-    sqlx::query!(
+    let mut transaction = pool.begin().await?;
+    insert_task(
+        &mut transaction,
+        title,
+        description,
+        due_at,
+        user_id,
+        workspace_id,
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// Creates a task in the caller's transaction, recording its creation in the history.
+#[instrument(skip(conn))]
+pub async fn insert_task(
+    conn: &mut PgConnection,
+    title: &str,
+    description: &str,
+    due_at: Option<OffsetDateTime>,
+    user_id: Uuid,
+    workspace_id: Option<Uuid>,
+) -> Result<Uuid> {
+    let task_id = sqlx::query_scalar!(
         r#"
-        insert into task (title, description, user_id)
-        values ($1, $2, $3)
+        insert into task (title, description, due_at, user_id, workspace_id)
+        values ($1, $2, $3, $4, $5)
+        returning task_id
         "#,
         title,
         description,
-        user_id
+        due_at,
+        user_id,
+        workspace_id
     )
-    .execute(pool)
+    .fetch_one(&mut *conn)
     .await?;
+    record_event(conn, task_id, user_id, TaskEventKind::Created, None).await?;
 
-    Ok(())
+    Ok(task_id)
+}
+
+/// Moves the task to the trash, where it can be restored until it's purged. Runs in
+/// the caller's transaction and returns the task's title.
+#[instrument(skip_all, fields(%task_id, %deleted_by))]
+pub async fn delete_task(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    deleted_by: Uuid,
+) -> Result<String> {
+    let title = sqlx::query_scalar!(
+        r#"
+        update task
+        set deleted_at = now(), deleted_by = $2
+        where task_id = $1 and deleted_at is null
+        returning title
+        "#,
+        task_id,
+        deleted_by
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    record_event(conn, task_id, deleted_by, TaskEventKind::Deleted, None).await?;
+    Ok(title)
 }
 
-#[instrument(skip_all, fields(%task_id, %user_id))]
-pub async fn delete_task(pool: &PgPool, task_id: Uuid, user_id: Uuid) -> Result<()> {
+/// Takes the task out of the trash, in the caller's transaction. A task whose CalDAV
+/// resource name was reused while it was in the trash is served under its id instead.
+#[instrument(skip_all, fields(%task_id, %restored_by))]
+pub async fn restore_task(conn: &mut PgConnection, task_id: Uuid, restored_by: Uuid) -> Result<()> {
     let query_result = sqlx::query!(
         r#"
-        delete from task
-        where task_id = $1 and user_id = $2
+        update task
+        set deleted_at = null, deleted_by = null,
+            caldav_name = case when exists (
+                select 1 from task other
+                where other.user_id = task.user_id and other.caldav_name = task.caldav_name
+                    and other.deleted_at is null and other.workspace_id is null
+            ) then null else caldav_name end
+        where task_id = $1 and deleted_at is not null
+        "#,
+        task_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    if query_result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+    record_event(conn, task_id, restored_by, TaskEventKind::Restored, None).await
+}
+
+/// Completes or reopens the task, in the caller's transaction. Tasks that already
+/// are in that state are left alone, returns whether the task changed.
+#[instrument(skip(conn))]
+pub async fn set_completed(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    completed: bool,
+    changed_by: Uuid,
+) -> Result<bool> {
+    let query_result = sqlx::query!(
+        r#"
+        update task
+        set completed = $2, version = version + 1
+        where task_id = $1 and deleted_at is null and completed != $2
         "#,
         task_id,
+        completed
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    if query_result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    let kind = if completed {
+        TaskEventKind::Completed
+    } else {
+        TaskEventKind::Reopened
+    };
+    record_event(conn, task_id, changed_by, kind, None).await?;
+    Ok(true)
+}
+
+/// Whether a task is completed after [`toggle_completed`], and its new version.
+#[derive(Debug, serde::Serialize)]
+pub struct Toggled {
+    pub title: String,
+    pub completed: bool,
+    pub version: i32,
+}
+
+/// Completes an open task or reopens a completed one, in the caller's transaction.
+#[instrument(skip(conn))]
+pub async fn toggle_completed(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    changed_by: Uuid,
+) -> Result<Toggled> {
+    let toggled = sqlx::query_as!(
+        Toggled,
+        r#"
+        update task
+        set completed = not completed, version = version + 1
+        where task_id = $1 and deleted_at is null
+        returning title, completed, version
+        "#,
+        task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let kind = if toggled.completed {
+        TaskEventKind::Completed
+    } else {
+        TaskEventKind::Reopened
+    };
+    record_event(conn, task_id, changed_by, kind, None).await?;
+    Ok(toggled)
+}
+
+/// Space left between the positions of neighbouring tasks, the same as the default
+/// of `task.position`.
+const POSITION_GAP: i64 = 65536;
+
+/// Where a task is moved to in the order users arrange tasks in.
+#[derive(Debug, Clone, Copy)]
+pub enum Placement {
+    Before(Uuid),
+    After(Uuid),
+}
+
+/// Moves the task right before or after another one in the list the user is looking
+/// at, their personal tasks along with the ones shared with them or the tasks of
+/// `workspace_id`. Only the moved task gets a new position, unless there's no room left
+/// between its new neighbours, then the tasks of the list below them are spread out
+/// first.
+///
+/// A task has a single position, so shared and workspace tasks are in the same order
+/// for everyone who sees them: moving one moves it for every other viewer too.
+#[instrument(skip(pool))]
+pub async fn reorder_task(
+    pool: &PgPool,
+    user_id: Uuid,
+    workspace_id: Option<Uuid>,
+    task_id: Uuid,
+    placement: Placement,
+) -> Result<()> {
+    let (anchor_id, before) = match placement {
+        Placement::Before(anchor_id) => (anchor_id, true),
+        Placement::After(anchor_id) => (anchor_id, false),
+    };
+    if anchor_id == task_id {
+        return Ok(());
+    }
+    let list = TaskList {
+        user_id,
+        workspace_id,
+    };
+
+    let mut transaction = pool.begin().await?;
+    //reorders of a list take turns, so two of them can't pick the same free position
+    sqlx::query_scalar!(
+        r#"
+        select true as "locked!"
+        from pg_advisory_xact_lock(hashtext('task_position:' || coalesce($1::uuid, $2::uuid)))
+        "#,
+        workspace_id,
         user_id
     )
-    .execute(pool)
+    .fetch_one(&mut *transaction)
     .await?;
 
+    let position = match free_position(&mut transaction, list, task_id, anchor_id, before).await? {
+        Some(position) => position,
+        None => {
+            //the anchor and its neighbour are packed together, the tasks of the list
+            //below the gap between them make room by moving down
+            sqlx::query!(
+                r#"
+                update task t
+                set position = t.position - $5
+                where t.deleted_at is null
+                and case
+                    when $2::uuid is null then (t.workspace_id is null and t.user_id = $1)
+                        or exists (
+                            select 1 from task_share s
+                            where s.task_id = t.task_id and s.user_id = $1
+                        )
+                    else t.workspace_id = $2
+                end
+                and (
+                    t.position < (select position from task where task_id = $3)
+                    or (not $4 and t.task_id = $3)
+                )
+                "#,
+                user_id,
+                workspace_id,
+                anchor_id,
+                before,
+                POSITION_GAP
+            )
+            .execute(&mut *transaction)
+            .await?;
+            free_position(&mut transaction, list, task_id, anchor_id, before)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("no room to move the task after spreading out"))?
+        }
+    };
+
+    let query_result = sqlx::query!(
+        r#"
+        update task t
+        set position = $4
+        where t.task_id = $3 and t.deleted_at is null
+        and case
+            when $2::uuid is null then (t.workspace_id is null and t.user_id = $1)
+                or exists (
+                    select 1 from task_share s
+                    where s.task_id = t.task_id and s.user_id = $1
+                )
+            else t.workspace_id = $2
+        end
+        "#,
+        user_id,
+        workspace_id,
+        task_id,
+        position
+    )
+    .execute(&mut *transaction)
+    .await?;
     if query_result.rows_affected() == 0 {
         return Err(Error::NotFound);
     }
+
+    transaction.commit().await?;
     Ok(())
 }
 
-#[instrument(skip_all, fields(%user_id))]
-pub async fn get_all_tasks(pool: &PgPool, user_id: Uuid) -> Result<Vec<Task>> {
+/// The tasks listed for a user, see [`reorder_task`].
+#[derive(Debug, Clone, Copy)]
+struct TaskList {
+    user_id: Uuid,
+    workspace_id: Option<Uuid>,
+}
+
+/// A free position in the list right before or after the anchor task, none when the
+/// anchor is packed against its neighbour.
+async fn free_position(
+    conn: &mut PgConnection,
+    list: TaskList,
+    task_id: Uuid,
+    anchor_id: Uuid,
+    before: bool,
+) -> Result<Option<i64>> {
+    let anchor = sqlx::query_scalar!(
+        r#"
+        select t.position from task t
+        where t.task_id = $3 and t.deleted_at is null
+        and case
+            when $2::uuid is null then (t.workspace_id is null and t.user_id = $1)
+                or exists (
+                    select 1 from task_share s
+                    where s.task_id = t.task_id and s.user_id = $1
+                )
+            else t.workspace_id = $2
+        end
+        "#,
+        list.user_id,
+        list.workspace_id,
+        anchor_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let neighbour = sqlx::query_scalar!(
+        r#"
+        select case when $5 then max(t.position) else min(t.position) end
+        from task t
+        where t.task_id <> $4 and t.deleted_at is null
+        and case
+            when $2::uuid is null then (t.workspace_id is null and t.user_id = $1)
+                or exists (
+                    select 1 from task_share s
+                    where s.task_id = t.task_id and s.user_id = $1
+                )
+            else t.workspace_id = $2
+        end
+        and case when $5 then t.position < $3 else t.position > $3 end
+        "#,
+        list.user_id,
+        list.workspace_id,
+        anchor,
+        task_id,
+        before
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let position = match neighbour {
+        Some(neighbour) => {
+            let (lower, upper) = (anchor.min(neighbour), anchor.max(neighbour));
+            if upper - lower < 2 {
+                return Ok(None);
+            }
+            lower + (upper - lower) / 2
+        }
+        None if before => anchor - POSITION_GAP,
+        //past the last task, where new tasks are added too
+        None => {
+            sqlx::query_scalar!(
+                r#"select nextval('task_position_seq') * $1 as "position!""#,
+                POSITION_GAP
+            )
+            .fetch_one(&mut *conn)
+            .await?
+        }
+    };
+    Ok(Some(position))
+}
+
+/// Moves the task into a workspace or, with `None`, to its owner's personal tasks, in
+/// the caller's transaction, unassigning an assignee left without access. Returns the
+/// workspace it was in before. Like restored tasks, tasks moved back whose CalDAV
+/// resource name was reused are served under their id.
+#[instrument(skip(conn))]
+pub async fn move_task(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    workspace_id: Option<Uuid>,
+    moved_by: Uuid,
+) -> Result<Option<Uuid>> {
+    let previous = sqlx::query!(
+        r#"
+        select t.workspace_id, w.name as "workspace_name?", t.assignee_id
+        from task t
+        left join workspace w on w.workspace_id = t.workspace_id
+        where t.task_id = $1 and t.deleted_at is null
+        for update of t
+        "#,
+        task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(Error::NotFound)?;
+    if previous.workspace_id == workspace_id {
+        return Ok(previous.workspace_id);
+    }
+
+    let workspace_name = sqlx::query_scalar!(
+        r#"
+        with moved as (
+            update task
+            set workspace_id = $2, version = version + 1,
+                caldav_name = case when $2::uuid is null and exists (
+                    select 1 from task other
+                    where other.user_id = task.user_id and other.caldav_name = task.caldav_name
+                        and other.deleted_at is null and other.workspace_id is null
+                ) then null else caldav_name end
+            where task_id = $1
+            returning workspace_id
+        )
+        select w.name as "name?"
+        from moved
+        left join workspace w on w.workspace_id = moved.workspace_id
+        "#,
+        task_id,
+        workspace_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    let change = FieldChange::new(
+        "workspace",
+        previous.workspace_name.as_deref(),
+        workspace_name.as_deref(),
+    );
+    record_event(conn, task_id, moved_by, TaskEventKind::Changed, change).await?;
+    if let Some(assignee_id) = previous.assignee_id {
+        unassign_without_access(conn, assignee_id, moved_by).await?;
+    }
+
+    Ok(previous.workspace_id)
+}
+
+/// Permanently deletes a task in the trash, along with its comments and attachments.
+/// Returns the storage keys of the attachments, whose blobs are left to the caller.
+#[instrument(skip_all, fields(%task_id, %purged_by))]
+pub async fn purge_task(pool: &PgPool, task_id: Uuid, purged_by: Uuid) -> Result<Vec<String>> {
+    let mut transaction = pool.begin().await?;
+
+    let storage_keys = sqlx::query_scalar!(
+        r#"
+        select storage_key from task_attachment
+        where task_id = $1
+        "#,
+        task_id
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    let query_result = sqlx::query!(
+        r#"
+        delete from task
+        where task_id = $1 and deleted_at is not null
+        "#,
+        task_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    if query_result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+    record_event(
+        &mut transaction,
+        task_id,
+        purged_by,
+        TaskEventKind::Purged,
+        None,
+    )
+    .await?;
+
+    transaction.commit().await?;
+    Ok(storage_keys)
+}
+
+/// Permanently deletes every task that has been in the trash since before `cutoff`.
+/// Returns how many were purged and the storage keys of their attachments, whose
+/// blobs are left to the caller. The keys come from the statement that deletes the
+/// tasks, so a task restored meanwhile keeps its blobs.
+#[instrument(skip(pool))]
+pub async fn purge_expired_tasks(
+    pool: &PgPool,
+    cutoff: OffsetDateTime,
+) -> Result<(u64, Vec<String>)> {
+    //every part of the statement sees the attachments as they were before the delete
+    let purged = sqlx::query!(
+        r#"
+        with purged as (
+            delete from task
+            where deleted_at < $1
+            returning task_id
+        ), expired as (
+            insert into task_event (task_id, kind)
+            select task_id, 'expired' from purged
+        )
+        select
+            (select count(*) from purged) as "count!",
+            array(
+                select a.storage_key from task_attachment a
+                join purged p on p.task_id = a.task_id
+            ) as "storage_keys!"
+        "#,
+        cutoff
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((purged.count as u64, purged.storage_keys))
+}
+
+/// A task in the trash on the Trash page.
+#[derive(Debug)]
+pub struct TrashedTask {
+    pub task_id: Uuid,
+    pub title: String,
+    pub deleted_at: OffsetDateTime,
+    /// `None` when the account of whoever deleted it is gone.
+    pub deleted_by_name: Option<String>,
+}
+
+/// The trashed tasks the user could restore: without a workspace their own personal
+/// tasks, with one the workspace's tasks they created or, for owners and admins, all
+/// of them.
+#[instrument(skip(pool))]
+pub async fn get_trashed_tasks(
+    pool: &PgPool,
+    user_id: Uuid,
+    workspace_id: Option<Uuid>,
+) -> Result<Vec<TrashedTask>> {
     sqlx::query_as!(
-        Task,
+        TrashedTask,
         r#"
-        select * from task
-        where user_id = $1
+        select
+            t.task_id,
+            t.title,
+            t.deleted_at as "deleted_at!",
+            d.username as "deleted_by_name?"
+        from task t
+        left join users d on d.user_id = t.deleted_by
+        left join workspace_member m on m.workspace_id = t.workspace_id and m.user_id = $1
+        where t.deleted_at is not null
+        and case
+            when $2::uuid is null then t.workspace_id is null and t.user_id = $1
+            else t.workspace_id = $2 and (t.user_id = $1 or m.role in ('owner', 'admin'))
+        end
+        order by t.deleted_at desc
         "#,
-        user_id
+        user_id,
+        workspace_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// Without a workspace these are the user's personal tasks and every task shared with
+/// them, with one all the tasks in that workspace.
+#[instrument(skip_all, fields(%user_id, ?workspace_id, ?filter))]
+pub async fn get_all_tasks(
+    pool: &PgPool,
+    user_id: Uuid,
+    workspace_id: Option<Uuid>,
+    filter: TaskFilter,
+) -> Result<Vec<ListedTask>> {
+    sqlx::query_as!(
+        ListedTask,
+        r#"
+        select
+            t.task_id,
+            t.title,
+            t.description,
+            t.completed,
+            u.username as owner_name,
+            a.username as "assignee_name?",
+            t.due_at,
+            coalesce(not t.completed and t.due_at < now(), false) as "overdue!",
+            t.user_id = $1 as "owned!",
+            s.role as "share_role?: ShareRole",
+            m.role as "workspace_role?: WorkspaceRole"
+        from task t
+        join users u on u.user_id = t.user_id
+        left join users a on a.user_id = t.assignee_id
+        left join task_share s on s.task_id = t.task_id and s.user_id = $1
+        left join workspace_member m on m.workspace_id = t.workspace_id and m.user_id = $1
+        where t.deleted_at is null
+        and case
+            when $2::uuid is null
+                then (t.workspace_id is null and t.user_id = $1) or s.user_id is not null
+            else t.workspace_id = $2
+        end
+        and (not $3 or t.assignee_id = $1)
+        and (not $4 or t.user_id = $1)
+        order by t.position, t.created_at
+        "#,
+        user_id,
+        workspace_id,
+        filter == TaskFilter::AssignedToMe,
+        filter == TaskFilter::CreatedByMe
     )
     .fetch_all(pool)
     .await
@@ -73,59 +714,322 @@ pub async fn get_all_tasks(pool: &PgPool, user_id: Uuid) -> Result<Vec<Task>> {
 }
 
 #[instrument]
-pub async fn get_task(pool: &PgPool, task_id: Uuid, user_id: Uuid) -> Result<Option<Task>> {
-    let task = sqlx::query_as!(
+pub async fn get_task(pool: &PgPool, task_id: Uuid) -> Result<Option<Task>> {
+    sqlx::query_as!(
         Task,
         r#"
-        select * from task
-        where task_id = $1
+        select
+            task_id,
+            title,
+            description,
+            completed,
+            created_at,
+            updated_at,
+            user_id,
+            workspace_id,
+            assignee_id,
+            due_at,
+            version
+        from task
+        where task_id = $1 and deleted_at is null
         "#,
         task_id
     )
     .fetch_optional(pool)
-    .await?;
-
-    if task.is_none() {
-        return Ok(None);
-    } else if task.as_ref().unwrap().user_id != user_id {
-        warn!(
-            ?task,
-            ?user_id,
-            "user trying to access todo of another user"
-        );
-        return Ok(None);
-    }
+    .await
+    .map_err(Error::SQLx)
+}
 
-    Ok(task)
+/// The task's current version, locked until the caller's transaction ends so it can't
+/// change before the caller's update.
+#[instrument(skip(conn))]
+pub async fn lock_task_version(conn: &mut PgConnection, task_id: Uuid) -> Result<i32> {
+    sqlx::query_scalar!(
+        "select version from task where task_id = $1 and deleted_at is null for update",
+        task_id
+    )
+    .fetch_optional(conn)
+    .await?
+    .ok_or(Error::NotFound)
 }
 
 #[instrument]
+pub async fn get_task_details(pool: &PgPool, task_id: Uuid) -> Result<Option<TaskDetails>> {
+    sqlx::query_as!(
+        TaskDetails,
+        r#"
+        select
+            t.task_id,
+            t.title,
+            t.description,
+            t.completed,
+            t.created_at,
+            t.updated_at,
+            u.username as owner_name,
+            a.username as "assignee_name?",
+            w.name as "workspace_name?",
+            t.version,
+            t.completed_at,
+            t.due_at,
+            coalesce(not t.completed and t.due_at < now(), false) as "overdue!"
+        from task t
+        join users u on u.user_id = t.user_id
+        left join users a on a.user_id = t.assignee_id
+        left join workspace w on w.workspace_id = t.workspace_id
+        where t.task_id = $1 and t.deleted_at is null
+        "#,
+        task_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// Updates the task in the caller's transaction, recording every change in its
+/// history. Returns whether the update completed the task.
+///
+/// With an `expected_version` the update is only made to that version of the task,
+/// anything newer is an [`Error::Conflict`].
+#[allow(clippy::too_many_arguments)]
+#[instrument(skip(conn))]
 pub async fn update_task(
-    pool: &PgPool,
+    conn: &mut PgConnection,
     task_id: Uuid,
-    user_id: Uuid,
     title: &str,
     description: &str,
     completed: bool,
-) -> Result<()> {
-    let query_result = sqlx::query!(
+    assignee_id: Option<Uuid>,
+    due_at: Option<OffsetDateTime>,
+    changed_by: Uuid,
+    expected_version: Option<i32>,
+) -> Result<bool> {
+    let previous = sqlx::query!(
+        r#"
+        select
+            t.version,
+            t.title,
+            t.description,
+            t.completed,
+            t.assignee_id,
+            a.username as "assignee_name?",
+            t.due_at
+        from task t
+        left join users a on a.user_id = t.assignee_id
+        where t.task_id = $1 and t.deleted_at is null
+        for update of t
+        "#,
+        task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(Error::NotFound)?;
+    if expected_version.is_some_and(|version| version != previous.version) {
+        return Err(Error::Conflict);
+    }
+
+    sqlx::query!(
         r#"
         update task
-        set title = $1, description = $2, completed = $3
-        where task_id = $4 and user_id = $5
+        set title = $1, description = $2, completed = $3, assignee_id = $4, due_at = $5,
+            version = version + 1
+        where task_id = $6
         "#,
         title,
         description,
         completed,
+        assignee_id,
+        due_at,
+        task_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let assignee_name = match assignee_id {
+        Some(assignee_id) if previous.assignee_id != Some(assignee_id) => {
+            sqlx::query_scalar!("select username from users where user_id = $1", assignee_id)
+                .fetch_optional(&mut *conn)
+                .await?
+        }
+        Some(_) => previous.assignee_name.clone(),
+        None => None,
+    };
+    let old_due_at = previous.due_at.map(format_datetime);
+    let new_due_at = due_at.map(format_datetime);
+    let changes = [
+        FieldChange::new("title", Some(&previous.title), Some(title)),
+        FieldChange::new(
+            "description",
+            previous.description.as_deref(),
+            Some(description),
+        ),
+        FieldChange::new(
+            "assignee",
+            previous.assignee_name.as_deref(),
+            assignee_name.as_deref(),
+        ),
+        FieldChange::new("due date", old_due_at.as_deref(), new_due_at.as_deref()),
+    ];
+    for change in changes.into_iter().flatten() {
+        record_event(
+            conn,
+            task_id,
+            changed_by,
+            TaskEventKind::Changed,
+            Some(change),
+        )
+        .await?;
+    }
+    if previous.completed != completed {
+        let kind = if completed {
+            TaskEventKind::Completed
+        } else {
+            TaskEventKind::Reopened
+        };
+        record_event(conn, task_id, changed_by, kind, None).await?;
+    }
+
+    Ok(completed && !previous.completed)
+}
+
+/// Everyone with access to the task: its owner, the users it's shared with and the
+/// members of its workspace.
+#[instrument(skip_all, fields(%task_id))]
+pub async fn get_task_users(pool: &PgPool, task_id: Uuid) -> Result<Vec<TaskUser>> {
+    sqlx::query_as!(
+        TaskUser,
+        r#"
+        select u.user_id, u.username
+        from users u
+        where u.user_id = (select user_id from task where task_id = $1)
+            or u.user_id in (select user_id from task_share where task_id = $1)
+            or u.user_id in (
+                select m.user_id from workspace_member m
+                join task t on t.workspace_id = m.workspace_id
+                where t.task_id = $1
+            )
+        order by u.username
+        "#,
+        task_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+#[instrument(skip_all, fields(%task_id))]
+pub async fn get_task_shares(pool: &PgPool, task_id: Uuid) -> Result<Vec<TaskShare>> {
+    sqlx::query_as!(
+        TaskShare,
+        r#"
+        select u.user_id, u.username, u.email, s.role as "role: ShareRole"
+        from task_share s
+        join users u on u.user_id = s.user_id
+        where s.task_id = $1
+        order by s.created_at
+        "#,
+        task_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// Shares the task with the user, or changes their role if it already is. The task is
+/// locked like in [`authorize_task_for_update`], so a role taken away waits for
+/// changes that were authorized with it.
+///
+/// [`authorize_task_for_update`]: super::super::authorization::authorize_task_for_update
+#[instrument(skip_all, fields(%task_id, %user_id, %role))]
+pub async fn share_task(
+    pool: &PgPool,
+    task_id: Uuid,
+    user_id: Uuid,
+    role: ShareRole,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        with locked as (select task_id from task where task_id = $1 for update)
+        insert into task_share (task_id, user_id, role)
+        select task_id, $2, $3 from locked
+        on conflict (task_id, user_id) do update set role = excluded.role
+        "#,
         task_id,
-        user_id
+        user_id,
+        role as ShareRole
     )
     .execute(pool)
     .await?;
 
+    Ok(())
+}
+
+/// Stops sharing the task with the user, locking the task like [`share_task`], and
+/// unassigns them if that was their only access to it.
+#[instrument(skip_all, fields(%task_id, %user_id, %unshared_by))]
+pub async fn unshare_task(
+    pool: &PgPool,
+    task_id: Uuid,
+    user_id: Uuid,
+    unshared_by: Uuid,
+) -> Result<()> {
+    let mut transaction = pool.begin().await?;
+
+    let query_result = sqlx::query!(
+        r#"
+        with locked as (select task_id from task where task_id = $1 for update)
+        delete from task_share
+        where task_id = (select task_id from locked) and user_id = $2
+        "#,
+        task_id,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
     if query_result.rows_affected() == 0 {
         return Err(Error::NotFound);
     }
+    unassign_without_access(&mut transaction, user_id, unshared_by).await?;
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// Unassigns the user from the tasks they're assigned but no longer have access to,
+/// see [`get_task_users`], and records it in their history. Runs in the caller's
+/// transaction, after it took the access away.
+#[instrument(skip(conn))]
+pub async fn unassign_without_access(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    changed_by: Uuid,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        with unassigned as (
+            update task t
+            set assignee_id = null, version = version + 1
+            where t.assignee_id = $1
+                and t.user_id <> $1
+                and not exists (
+                    select 1 from task_share s where s.task_id = t.task_id and s.user_id = $1
+                )
+                and not exists (
+                    select 1 from workspace_member m
+                    where m.workspace_id = t.workspace_id and m.user_id = $1
+                )
+            returning t.task_id
+        )
+        insert into task_event (task_id, actor_id, kind, field, old_value)
+        select u.task_id, $2, 'changed', 'assignee', a.username
+        from unassigned u
+        left join users a on a.user_id = $1
+        "#,
+        user_id,
+        changed_by
+    )
+    .execute(conn)
+    .await?;
 
     Ok(())
 }
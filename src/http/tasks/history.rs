@@ -0,0 +1,161 @@
+use sqlx::{PgConnection, PgPool};
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::super::{error::Error, utilities::Result};
+
+/// What happened to a task, see the `task_event` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum TaskEventKind {
+    Created,
+    /// One of the task's fields got a new value.
+    Changed,
+    Completed,
+    Reopened,
+    /// Moved to the trash.
+    Deleted,
+    /// Taken out of the trash.
+    Restored,
+    /// Permanently deleted from the trash.
+    Purged,
+    /// Permanently deleted after being in the trash for longer than the retention
+    /// period, these events have no actor.
+    Expired,
+}
+
+/// The field of changed events for attachments, the file name is the new value when
+/// one is uploaded and the old value when it's removed.
+pub const ATTACHMENT_FIELD: &str = "attachment";
+
+/// An entry in a task's history.
+#[derive(Debug)]
+pub struct TaskEvent {
+    pub kind: TaskEventKind,
+    pub field: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    /// `None` when the actor's account is gone.
+    pub actor_name: Option<String>,
+    pub created_at: OffsetDateTime,
+}
+
+impl TaskEvent {
+    /// Events that happened on their own rather than by someone's hand.
+    pub fn automatic(&self) -> bool {
+        self.kind == TaskEventKind::Expired
+    }
+
+    /// What the actor did, e.g. `changed the title from "a" to "b"`. For automatic
+    /// events the subject is the task itself.
+    pub fn describe(&self) -> String {
+        let field = self.field.as_deref().unwrap_or("task");
+        match (self.kind, &self.old_value, &self.new_value) {
+            (TaskEventKind::Created, _, _) => "created the task".into(),
+            (TaskEventKind::Completed, _, _) => "completed the task".into(),
+            (TaskEventKind::Reopened, _, _) => "reopened the task".into(),
+            (TaskEventKind::Deleted, _, _) => "moved the task to the trash".into(),
+            (TaskEventKind::Restored, _, _) => "restored the task from the trash".into(),
+            (TaskEventKind::Purged, _, _) => "permanently deleted the task".into(),
+            (TaskEventKind::Expired, _, _) => {
+                "was permanently deleted after its time in the trash".into()
+            }
+            (TaskEventKind::Changed, None, Some(new)) if field == ATTACHMENT_FIELD => {
+                format!("attached \"{new}\"")
+            }
+            (TaskEventKind::Changed, Some(old), None) if field == ATTACHMENT_FIELD => {
+                format!("removed the attachment \"{old}\"")
+            }
+            (TaskEventKind::Changed, Some(old), Some(new)) => {
+                format!("changed the {field} from \"{old}\" to \"{new}\"")
+            }
+            (TaskEventKind::Changed, None, Some(new)) => format!("set the {field} to \"{new}\""),
+            (TaskEventKind::Changed, Some(old), None) => {
+                format!("cleared the {field}, it was \"{old}\"")
+            }
+            (TaskEventKind::Changed, None, None) => format!("changed the {field}"),
+        }
+    }
+}
+
+/// A field of a task going from one value to another, empty values are `None`.
+#[derive(Debug)]
+pub struct FieldChange<'a> {
+    pub field: &'static str,
+    pub old_value: Option<&'a str>,
+    pub new_value: Option<&'a str>,
+}
+
+impl<'a> FieldChange<'a> {
+    /// `None` when the value stayed the same.
+    pub fn new(
+        field: &'static str,
+        old_value: Option<&'a str>,
+        new_value: Option<&'a str>,
+    ) -> Option<Self> {
+        let old_value = old_value.filter(|value| !value.is_empty());
+        let new_value = new_value.filter(|value| !value.is_empty());
+        (old_value != new_value).then_some(Self {
+            field,
+            old_value,
+            new_value,
+        })
+    }
+}
+
+/// Appends to a task's history, meant to run in the transaction making the change.
+#[instrument(skip(conn))]
+pub async fn record_event(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    actor_id: Uuid,
+    kind: TaskEventKind,
+    change: Option<FieldChange<'_>>,
+) -> Result<()> {
+    let (field, old_value, new_value) = match change {
+        Some(change) => (Some(change.field), change.old_value, change.new_value),
+        None => (None, None, None),
+    };
+    sqlx::query!(
+        r#"
+        insert into task_event (task_id, actor_id, kind, field, old_value, new_value)
+        values ($1, $2, $3, $4, $5, $6)
+        "#,
+        task_id,
+        actor_id,
+        kind as TaskEventKind,
+        field,
+        old_value,
+        new_value
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// A task's history, oldest first. Also works for tasks that were deleted.
+#[instrument(skip(pool))]
+pub async fn get_history(pool: &PgPool, task_id: Uuid) -> Result<Vec<TaskEvent>> {
+    sqlx::query_as!(
+        TaskEvent,
+        r#"
+        select
+            e.kind as "kind: TaskEventKind",
+            e.field,
+            e.old_value,
+            e.new_value,
+            u.username as "actor_name?",
+            e.created_at
+        from task_event e
+        left join users u on u.user_id = e.actor_id
+        where e.task_id = $1
+        order by e.created_at, e.event_id
+        "#,
+        task_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
@@ -1,5 +1,9 @@
-mod db;
+pub(crate) mod db;
+pub(crate) mod history;
 mod routes;
 mod templates;
+pub(crate) mod trash;
+mod undo;
 
 pub use routes::router;
+pub(crate) use routes::{count_tasks, validate_task};
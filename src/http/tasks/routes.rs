@@ -1,50 +1,208 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::{
+        header::{ETAG, IF_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
     middleware::from_fn,
-    response::{Html, Redirect},
-    routing::{delete, get, post},
-    Extension, Form, Router,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Extension, Form, Json, Router,
 };
-use serde::Deserialize;
-use sqlx::PgPool;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::{fmt, str::FromStr};
+use time::{
+    format_description::BorrowedFormatItem, macros::format_description, OffsetDateTime,
+    PrimitiveDateTime,
+};
+use tower_sessions::Session;
 use tracing::instrument;
 use uuid::Uuid;
 
-use crate::http::users::{auth_middleware, UserSessionData};
+use crate::{
+    config::Trash,
+    http::users::{self, auth_middleware, UserSessionData},
+};
 
 use super::super::{
+    attachments::{self, Attachments},
+    authorization::{
+        authorize_task, authorize_task_for_update, authorize_trashed_task, authorize_workspace,
+        Grants, ShareRole, TaskAction, WorkspaceAction,
+    },
+    comments::{self, thread},
     error::Error,
-    utilities::{render_template, ApiState, Result},
+    error_pages::wants_json,
+    layout::{FormErrors, LazyPageContext, PageContext},
+    utilities::{
+        empty_string_as_none, etag_listed, present_empty_string_as_none, render_invalid_form,
+        render_template, ApiState, FlashMessageLevel, FlashMessages, HmacKey, Result,
+    },
+    validation::{Validate, Validator},
+    workspaces::{self, current_workspace},
 };
 
-use super::{db, templates::*};
+use super::{
+    db::{self, Placement, TaskFilter, TaskShare, TaskUser, Toggled},
+    history,
+    templates::*,
+    undo::{record_undo, undo, Inverse, TaskMove, UndoOutcome},
+};
 
 pub fn router() -> Router<ApiState> {
     Router::new()
         .route("/", post(create_task).get(tasks_page))
-        .route("/{task_id}", delete(delete_task).post(update_task))
+        .route(
+            "/{task_id}",
+            get(task_page)
+                .delete(delete_task)
+                .post(update_task)
+                .patch(patch_task),
+        )
+        .route("/{task_id}/toggle", post(toggle_task))
         .route("/{task_id}/edit", get(edit_task_page))
+        .route("/{task_id}/shares", post(share_task))
+        .route("/{task_id}/shares/{user_id}/delete", post(unshare_task))
+        .route("/{task_id}/restore", post(restore_task))
+        .route("/{task_id}/purge", post(purge_task))
+        .route("/{task_id}/reorder", post(reorder_task))
         .route("/new", get(new_todo_page))
+        .route("/trash", get(trash_page))
+        .route("/undo", post(undo_action))
+        .route("/bulk", post(bulk_action))
         .route_layer(from_fn(auth_middleware))
 }
 
 #[instrument(skip_all)]
-pub async fn new_todo_page() -> Result<Html<String>> {
-    render_template(NewTodoTemplate)
+pub async fn new_todo_page(ctx: PageContext) -> Result<Html<String>> {
+    render_template(NewTodoTemplate {
+        ctx,
+        errors: FormErrors::default(),
+        form: NewTask::default(),
+    })
 }
 
-#[derive(Debug, Deserialize)]
+const MAX_TITLE_CHARS: usize = 200;
+const MAX_DESCRIPTION_CHARS: usize = 10_000;
+
+pub(crate) fn validate_task(title: &str, description: &str) -> Validator {
+    Validator::new()
+        .required("title", title)
+        .max_chars("title", title, MAX_TITLE_CHARS)
+        .max_chars("description", description, MAX_DESCRIPTION_CHARS)
+}
+
+/// The format of `<input type="datetime-local">`, the seconds are only sent when the
+/// input asks for them.
+const DUE_AT_FORMAT: &[BorrowedFormatItem] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute][optional [:[second]]]");
+
+/// When a task is due, as `2026-10-20T17:00` in forms. Like the days on the stats page
+/// it's in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DueAt(pub OffsetDateTime);
+
+impl FromStr for DueAt {
+    type Err = time::error::Parse;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        PrimitiveDateTime::parse(value, DUE_AT_FORMAT).map(|due_at| Self(due_at.assume_utc()))
+    }
+}
+
+impl fmt::Display for DueAt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let due_at = self.0.to_offset(time::UtcOffset::UTC);
+        let formatted = due_at
+            .format(format_description!("[year]-[month]-[day]T[hour]:[minute]"))
+            .map_err(|_| fmt::Error)?;
+        f.write_str(&formatted)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct NewTask {
-    title: String,
-    description: String,
+    pub title: String,
+    pub description: String,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub due_at: Option<DueAt>,
+}
+
+impl Validate for NewTask {
+    fn validate(&self) -> Result<()> {
+        validate_task(&self.title, &self.description).finish()
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateTask {
-    title: String,
-    description: String,
+    pub title: String,
+    pub description: String,
     #[serde(default)]
-    completed: bool,
+    pub completed: bool,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub assignee_id: Option<Uuid>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub due_at: Option<DueAt>,
+    /// The version of the task the edit was made to, see [`if_match`] for API clients.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub version: Option<i32>,
+}
+
+impl UpdateTask {
+    pub fn is_assigned_to(&self, user_id: &Uuid) -> bool {
+        self.assignee_id.as_ref() == Some(user_id)
+    }
+
+    /// Tasks can only be assigned to one of the `task_users` with access to them.
+    fn validate(&self, task_users: &[TaskUser]) -> Result<()> {
+        let assignee_has_access = self
+            .assignee_id
+            .is_none_or(|assignee_id| task_users.iter().any(|user| user.user_id == assignee_id));
+        validate_task(&self.title, &self.description)
+            .check(
+                "assignee_id",
+                assignee_has_access,
+                "the assignee needs access to the task",
+            )
+            .finish()
+    }
+}
+
+/// A partial update, fields that are left out keep their current value.
+#[derive(Debug, Deserialize)]
+pub struct PatchTask {
+    title: Option<String>,
+    description: Option<String>,
+    completed: Option<bool>,
+    /// An empty value unassigns the task.
+    #[serde(default, deserialize_with = "present_empty_string_as_none")]
+    assignee_id: Option<Option<Uuid>>,
+    /// An empty value takes the due date away.
+    #[serde(default, deserialize_with = "present_empty_string_as_none")]
+    due_at: Option<Option<DueAt>>,
+}
+
+impl PatchTask {
+    fn apply(self, task: db::Task) -> UpdateTask {
+        UpdateTask {
+            title: self.title.unwrap_or(task.title),
+            description: self
+                .description
+                .unwrap_or_else(|| task.description.unwrap_or_default()),
+            completed: self.completed.unwrap_or(task.completed),
+            assignee_id: self.assignee_id.unwrap_or(task.assignee_id),
+            due_at: self.due_at.unwrap_or(task.due_at.map(DueAt)),
+            version: Some(task.version),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TasksQuery {
+    #[serde(default)]
+    filter: TaskFilter,
 }
 
 #[instrument(
@@ -59,58 +217,220 @@ pub struct UpdateTask {
 pub async fn create_task(
     State(pool): State<PgPool>,
     Extension(user_session): Extension<UserSessionData>,
+    session: Session,
+    ctx: LazyPageContext,
     Form(new_task): Form<NewTask>,
-) -> Result<Redirect> {
+) -> Result<Response> {
+    let workspace =
+        current_workspace(&pool, &session, &user_session, WorkspaceAction::CreateTasks).await?;
+
+    if let Err(error) = new_task.validate() {
+        return render_invalid_form(NewTodoTemplate {
+            ctx: ctx.build().await?,
+            errors: error.try_into()?,
+            form: new_task,
+        });
+    }
+
     db::create_new_task(
         &pool,
         &new_task.title,
         &new_task.description,
+        new_task.due_at.map(|due_at| due_at.0),
         user_session.user_id(),
+        workspace.map(|(workspace, _)| workspace.workspace_id),
     )
     .await?;
 
-    Ok(Redirect::to("/todo"))
+    Ok(Redirect::to("/todo").into_response())
 }
 
 #[instrument(skip_all, fields(action = "deleting a task", %task_id, %user_session))]
 pub async fn delete_task(
     State(pool): State<PgPool>,
+    State(hmac_key): State<HmacKey>,
     Path(task_id): Path<Uuid>,
     Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
 ) -> Result<()> {
-    db::delete_task(&pool, task_id, user_session.user_id()).await
+    authorize_task(&pool, &user_session, task_id, TaskAction::Delete).await?;
+
+    let mut transaction = pool.begin().await?;
+    let title = db::delete_task(&mut transaction, task_id, user_session.user_id()).await?;
+    let undo_token = record_undo(
+        &mut transaction,
+        &hmac_key,
+        user_session.user_id(),
+        Inverse::Restore {
+            task_ids: vec![task_id],
+        },
+    )
+    .await?;
+    transaction.commit().await?;
+
+    flash_msgs
+        .set_undoable(&format!("Moved \"{title}\" to the trash"), undo_token)
+        .await
 }
 
-#[instrument(skip_all, fields(action = "displaying tasks page", %user_session))]
+#[instrument(skip_all, fields(action = "displaying trash page", %user_session))]
+pub async fn trash_page(
+    State(pool): State<PgPool>,
+    State(trash): State<Trash>,
+    Extension(user_session): Extension<UserSessionData>,
+    session: Session,
+    mut ctx: PageContext,
+) -> Result<Html<String>> {
+    let workspace =
+        current_workspace(&pool, &session, &user_session, WorkspaceAction::View).await?;
+    let tasks = db::get_trashed_tasks(
+        &pool,
+        user_session.user_id(),
+        workspace
+            .as_ref()
+            .map(|(workspace, _)| workspace.workspace_id),
+    )
+    .await?;
+
+    ctx.workspace = workspace.map(|(workspace, _)| workspace);
+    render_template(TrashTemplate {
+        ctx,
+        tasks,
+        retention_days: trash.retention_days,
+    })
+}
+
+#[instrument(skip_all, fields(action = "restoring a task", %task_id, %user_session))]
+pub async fn restore_task(
+    State(pool): State<PgPool>,
+    Path(task_id): Path<Uuid>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+) -> Result<Redirect> {
+    authorize_trashed_task(&pool, &user_session, task_id).await?;
+    let mut transaction = pool.begin().await?;
+    db::restore_task(&mut transaction, task_id, user_session.user_id()).await?;
+    transaction.commit().await?;
+
+    flash_msgs
+        .set_msg(FlashMessageLevel::Success, "Restored the task")
+        .await?;
+    Ok(Redirect::to("/todo/trash"))
+}
+
+#[instrument(skip_all, fields(action = "purging a task", %task_id, %user_session))]
+pub async fn purge_task(
+    State(pool): State<PgPool>,
+    State(attachments): State<Attachments>,
+    Path(task_id): Path<Uuid>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+) -> Result<Redirect> {
+    authorize_trashed_task(&pool, &user_session, task_id).await?;
+    let storage_keys = db::purge_task(&pool, task_id, user_session.user_id()).await?;
+    attachments.delete_blobs(&storage_keys).await;
+
+    flash_msgs
+        .set_msg(FlashMessageLevel::Success, "Permanently deleted the task")
+        .await?;
+    Ok(Redirect::to("/todo/trash"))
+}
+
+#[instrument(skip_all, fields(action = "displaying tasks page", ?query.filter, %user_session))]
 pub async fn tasks_page(
     State(pool): State<PgPool>,
+    Query(query): Query<TasksQuery>,
     Extension(user_session): Extension<UserSessionData>,
+    session: Session,
+    mut ctx: PageContext,
 ) -> Result<Html<String>> {
-    let tasks = db::get_all_tasks(&pool, user_session.user_id()).await?;
+    let workspace =
+        current_workspace(&pool, &session, &user_session, WorkspaceAction::View).await?;
+    let tasks = db::get_all_tasks(
+        &pool,
+        user_session.user_id(),
+        workspace
+            .as_ref()
+            .map(|(workspace, _)| workspace.workspace_id),
+        query.filter,
+    )
+    .await?;
 
+    let can_create = workspace
+        .as_ref()
+        .is_none_or(|(_, role)| role.allows(WorkspaceAction::CreateTasks));
+    let workspaces = workspaces::db::get_memberships(&pool, user_session.user_id())
+        .await?
+        .into_iter()
+        .filter(|membership| membership.role.allows(WorkspaceAction::CreateTasks))
+        .collect();
+    ctx.workspace = workspace.map(|(workspace, _)| workspace);
     render_template(TodosTemplate {
+        ctx,
         todos: tasks,
-        username: user_session.username(),
+        can_create,
+        filter: query.filter,
+        workspaces,
     })
 }
 
+#[instrument(skip_all, fields(action = "displaying task page", %task_id, %user_session))]
+pub async fn task_page(
+    State(pool): State<PgPool>,
+    Path(task_id): Path<Uuid>,
+    Extension(user_session): Extension<UserSessionData>,
+    ctx: PageContext,
+) -> Result<Response> {
+    let grants = authorize_task(&pool, &user_session, task_id, TaskAction::View).await?;
+    let task = db::get_task_details(&pool, task_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    let etag = task_etag(task.version);
+    let page = render_template(TaskTemplate {
+        ctx,
+        task,
+        can_edit: grants.allows(TaskAction::Edit),
+        can_comment: grants.allows(TaskAction::Comment),
+        comments: thread(comments::db::get_comments(&pool, task_id).await?),
+        attachments: attachments::db::get_attachments(&pool, task_id).await?,
+        history: history::get_history(&pool, task_id).await?,
+        user_id: user_session.user_id(),
+    })?;
+    Ok(([(ETAG, etag)], page).into_response())
+}
+
 #[instrument(skip_all, fields(action = "displaying edit task page", %task_id, %user_session))]
 pub async fn edit_task_page(
     State(pool): State<PgPool>,
     Path(task_id): Path<Uuid>,
     Extension(user_session): Extension<UserSessionData>,
-) -> Result<Html<String>> {
-    let task = db::get_task(&pool, task_id, user_session.user_id()).await?;
-
-    if task.is_none() {
-        return Err(Error::NotFound);
-    }
+    ctx: PageContext,
+) -> Result<Response> {
+    let grants = authorize_task(&pool, &user_session, task_id, TaskAction::Edit).await?;
+    let task = db::get_task(&pool, task_id).await?.ok_or(Error::NotFound)?;
 
-    render_template(EditTodoTemplate {
-        todo: task.unwrap(),
-    })
+    let etag = task_etag(task.version);
+    let page = render_template(EditTodoTemplate {
+        ctx,
+        task_id: task.task_id,
+        shares: task_shares(&pool, &grants, task_id).await?,
+        users: db::get_task_users(&pool, task_id).await?,
+        errors: FormErrors::default(),
+        form: UpdateTask {
+            title: task.title,
+            description: task.description.unwrap_or_default(),
+            completed: task.completed,
+            assignee_id: task.assignee_id,
+            due_at: task.due_at.map(DueAt),
+            version: Some(task.version),
+        },
+        conflict: None,
+    })?;
+    Ok(([(ETAG, etag)], page).into_response())
 }
 
+#[allow(clippy::too_many_arguments)]
 #[instrument(
     skip_all,
     fields(
@@ -119,23 +439,500 @@ pub async fn edit_task_page(
         %update_task.title,
         %update_task.description,
         %update_task.completed,
+        ?update_task.assignee_id,
         %user_session
 ))]
 pub async fn update_task(
     State(pool): State<PgPool>,
+    State(hmac_key): State<HmacKey>,
     Path(task_id): Path<Uuid>,
     Extension(user_session): Extension<UserSessionData>,
-    Form(update_task): Form<UpdateTask>,
-) -> Result<Redirect> {
-    db::update_task(
-        &pool,
+    mut flash_msgs: FlashMessages,
+    ctx: LazyPageContext,
+    headers: HeaderMap,
+    Form(mut update_task): Form<UpdateTask>,
+) -> Result<Response> {
+    let grants = authorize_task(&pool, &user_session, task_id, TaskAction::Edit).await?;
+    let users = db::get_task_users(&pool, task_id).await?;
+    if let Err(error) = update_task.validate(&users) {
+        return render_invalid_form(EditTodoTemplate {
+            ctx: ctx.build().await?,
+            task_id,
+            shares: task_shares(&pool, &grants, task_id).await?,
+            users,
+            errors: error.try_into()?,
+            form: update_task,
+            conflict: None,
+        });
+    }
+
+    let mut transaction = pool.begin().await?;
+    let expected_version = if_match(&mut transaction, &headers, task_id)
+        .await?
+        .or(update_task.version);
+    let updated = db::update_task(
+        &mut transaction,
         task_id,
+        &update_task.title,
+        &update_task.description,
+        update_task.completed,
+        update_task.assignee_id,
+        update_task.due_at.map(|due_at| due_at.0),
         user_session.user_id(),
+        expected_version,
+    )
+    .await;
+    let completed = match updated {
+        Err(Error::Conflict) => {
+            //the edit is shown again next to the current task, saving it once more
+            //knowingly replaces the other change
+            let current = db::get_task(&pool, task_id).await?.ok_or(Error::NotFound)?;
+            update_task.version = Some(current.version);
+            let page = render_template(EditTodoTemplate {
+                ctx: ctx.build().await?,
+                task_id,
+                shares: task_shares(&pool, &grants, task_id).await?,
+                users,
+                errors: FormErrors::default(),
+                form: update_task,
+                conflict: Some(current),
+            })?;
+            return Ok((StatusCode::CONFLICT, page).into_response());
+        }
+        updated => updated?,
+    };
+    let undo_token = if completed {
+        let inverse = Inverse::Reopen {
+            task_ids: vec![task_id],
+        };
+        Some(record_undo(&mut transaction, &hmac_key, user_session.user_id(), inverse).await?)
+    } else {
+        None
+    };
+    transaction.commit().await?;
+
+    if let Some(undo_token) = undo_token {
+        flash_msgs
+            .set_undoable(&format!("Completed \"{}\"", update_task.title), undo_token)
+            .await?;
+    }
+    Ok(Redirect::to("/todo").into_response())
+}
+
+/// Changes only the fields that are sent, answering with the task's new `ETag`. The
+/// task is updated from the version that was read, so a change made in between is a
+/// conflict rather than being overwritten.
+#[instrument(skip_all, fields(action = "patching a task", %task_id, ?patch, %user_session))]
+pub async fn patch_task(
+    State(pool): State<PgPool>,
+    Path(task_id): Path<Uuid>,
+    Extension(user_session): Extension<UserSessionData>,
+    headers: HeaderMap,
+    Form(patch): Form<PatchTask>,
+) -> Result<Response> {
+    authorize_task(&pool, &user_session, task_id, TaskAction::Edit).await?;
+    let mut transaction = pool.begin().await?;
+    //with If-Match the task is locked first, so it's read at the version that matched
+    if_match(&mut transaction, &headers, task_id).await?;
+    let task = db::get_task(&pool, task_id).await?.ok_or(Error::NotFound)?;
+    let expected_version = task.version;
+    let update_task = patch.apply(task);
+    update_task.validate(&db::get_task_users(&pool, task_id).await?)?;
+
+    db::update_task(
+        &mut transaction,
+        task_id,
         &update_task.title,
         &update_task.description,
         update_task.completed,
+        update_task.assignee_id,
+        update_task.due_at.map(|due_at| due_at.0),
+        user_session.user_id(),
+        Some(expected_version),
     )
     .await?;
+    transaction.commit().await?;
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [(ETAG, task_etag(expected_version + 1))],
+    )
+        .into_response())
+}
+
+/// The new state of a toggled task, with the token undoing it when it was completed.
+#[derive(Debug, Serialize)]
+struct ToggleResponse {
+    #[serde(flatten)]
+    toggled: Toggled,
+    undo_token: Option<String>,
+}
+
+/// Completes an open task or reopens a completed one, honouring `If-Match` like the
+/// other updates. Completing can be undone like in [`update_task`]. Scripts asking for
+/// JSON get the new state and the undo token back, forms are sent back to the tasks
+/// page with the undo button in a flash message.
+#[instrument(skip_all, fields(action = "toggling a task", %task_id, %user_session))]
+pub async fn toggle_task(
+    State(pool): State<PgPool>,
+    State(hmac_key): State<HmacKey>,
+    Path(task_id): Path<Uuid>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+    headers: HeaderMap,
+) -> Result<Response> {
+    authorize_task(&pool, &user_session, task_id, TaskAction::Edit).await?;
+    let mut transaction = pool.begin().await?;
+    if_match(&mut transaction, &headers, task_id).await?;
+    let toggled = db::toggle_completed(&mut transaction, task_id, user_session.user_id()).await?;
+    let undo_token = if toggled.completed {
+        let inverse = Inverse::Reopen {
+            task_ids: vec![task_id],
+        };
+        Some(record_undo(&mut transaction, &hmac_key, user_session.user_id(), inverse).await?)
+    } else {
+        None
+    };
+    transaction.commit().await?;
+
+    if wants_json(&headers) {
+        let etag = task_etag(toggled.version);
+        return Ok((
+            [(ETAG, etag)],
+            Json(ToggleResponse {
+                toggled,
+                undo_token,
+            }),
+        )
+            .into_response());
+    }
+    if let Some(undo_token) = undo_token {
+        flash_msgs
+            .set_undoable(&format!("Completed \"{}\"", toggled.title), undo_token)
+            .await?;
+    }
+    Ok(Redirect::to("/todo").into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReorderForm {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    before: Option<Uuid>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    after: Option<Uuid>,
+}
+
+impl ReorderForm {
+    fn placement(&self) -> Result<Placement> {
+        match (self.before, self.after) {
+            (Some(anchor_id), None) => Ok(Placement::Before(anchor_id)),
+            (None, Some(anchor_id)) => Ok(Placement::After(anchor_id)),
+            _ => Err(Error::unprocessable_entity([(
+                "before",
+                "give either the task to move before or the one to move after",
+            )])),
+        }
+    }
+}
+
+#[instrument(skip_all, fields(action = "reordering a task", %task_id, ?form.before, ?form.after, %user_session))]
+pub async fn reorder_task(
+    State(pool): State<PgPool>,
+    Path(task_id): Path<Uuid>,
+    Extension(user_session): Extension<UserSessionData>,
+    session: Session,
+    Form(form): Form<ReorderForm>,
+) -> Result<()> {
+    let placement = form.placement()?;
+    let (Placement::Before(anchor_id) | Placement::After(anchor_id)) = placement;
+    authorize_task(&pool, &user_session, task_id, TaskAction::Edit).await?;
+    authorize_task(&pool, &user_session, anchor_id, TaskAction::View).await?;
+    let workspace =
+        current_workspace(&pool, &session, &user_session, WorkspaceAction::View).await?;
+
+    db::reorder_task(
+        &pool,
+        user_session.user_id(),
+        workspace.map(|(workspace, _)| workspace.workspace_id),
+        task_id,
+        placement,
+    )
+    .await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UndoForm {
+    token: String,
+}
+
+#[instrument(skip_all, fields(action = "undoing a task action", %user_session))]
+pub async fn undo_action(
+    State(pool): State<PgPool>,
+    State(hmac_key): State<HmacKey>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+    Form(form): Form<UndoForm>,
+) -> Result<Redirect> {
+    let (level, msg) = match undo(&pool, &hmac_key, &user_session, &form.token).await? {
+        UndoOutcome::Undone => (FlashMessageLevel::Success, "Undone"),
+        UndoOutcome::Expired => (FlashMessageLevel::Error, "It's too late to undo that"),
+        UndoOutcome::AlreadyUndone => (FlashMessageLevel::Error, "That was already undone"),
+    };
+    flash_msgs.set_msg(level, msg).await?;
+
+    Ok(Redirect::to("/todo"))
+}
+
+/// How many tasks one bulk action can take.
+const MAX_BULK_TASKS: usize = 500;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkAction {
+    Complete,
+    /// Moving the tasks to the trash.
+    Delete,
+    /// Moving the tasks to `workspace_id`, or to their owners' personal tasks.
+    Move,
+}
+
+impl BulkAction {
+    /// What the user needs to be allowed to do with each of the tasks.
+    fn task_action(self) -> TaskAction {
+        match self {
+            Self::Complete => TaskAction::Edit,
+            Self::Delete | Self::Move => TaskAction::Delete,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkForm {
+    #[serde(default)]
+    task_ids: Vec<Uuid>,
+    action: BulkAction,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    workspace_id: Option<Uuid>,
+}
+
+pub(crate) fn count_tasks(count: usize) -> String {
+    match count {
+        1 => "1 task".to_string(),
+        count => format!("{count} tasks"),
+    }
+}
+
+/// Applies one action to every selected task in a single transaction. Every task is
+/// checked and locked in it before anything changes, so the batch is done for all of
+/// them or none. The ids are sorted, so concurrent batches lock tasks in the same order.
+#[instrument(skip_all, fields(action = "bulk task action", ?form.action, tasks = form.task_ids.len(), %user_session))]
+pub async fn bulk_action(
+    State(pool): State<PgPool>,
+    State(hmac_key): State<HmacKey>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+    axum_extra::extract::Form(mut form): axum_extra::extract::Form<BulkForm>,
+) -> Result<Redirect> {
+    form.task_ids.sort_unstable();
+    form.task_ids.dedup();
+    let validation = Validator::new()
+        .check(
+            "task_ids",
+            !form.task_ids.is_empty(),
+            "select some tasks first",
+        )
+        .check(
+            "task_ids",
+            form.task_ids.len() <= MAX_BULK_TASKS,
+            format!("at most {MAX_BULK_TASKS} tasks can be changed at once"),
+        )
+        .finish();
+    if let Err(error) = validation {
+        flash_msgs.set_invalid(error).await?;
+        return Ok(Redirect::to("/todo"));
+    }
+
+    let destination = match (form.action, form.workspace_id) {
+        (BulkAction::Move, Some(workspace_id)) => {
+            authorize_workspace(
+                &pool,
+                &user_session,
+                workspace_id,
+                WorkspaceAction::CreateTasks,
+            )
+            .await?;
+            workspaces::db::get_memberships(&pool, user_session.user_id())
+                .await?
+                .into_iter()
+                .find(|membership| membership.workspace_id == workspace_id)
+                .map(|membership| membership.name)
+                .ok_or(Error::NotFound)?
+        }
+        _ => "their owners' personal tasks".to_string(),
+    };
+    let user_id = user_session.user_id();
+    let mut transaction = pool.begin().await?;
+    for &task_id in &form.task_ids {
+        let authorized = authorize_task_for_update(
+            &mut transaction,
+            &user_session,
+            task_id,
+            form.action.task_action(),
+        )
+        .await;
+        match authorized {
+            Ok(_) => {}
+            Err(Error::Forbidden | Error::NotFound) => {
+                flash_msgs
+                    .set_msg(
+                        FlashMessageLevel::Error,
+                        "You're not allowed to do that with some of the selected tasks, nothing was changed",
+                    )
+                    .await?;
+                return Ok(Redirect::to("/todo"));
+            }
+            Err(error) => return Err(error),
+        }
+    }
 
+    let (inverse, msg) = match form.action {
+        BulkAction::Complete => {
+            let mut task_ids = Vec::new();
+            for &task_id in &form.task_ids {
+                if db::set_completed(&mut transaction, task_id, true, user_id).await? {
+                    task_ids.push(task_id);
+                }
+            }
+            let msg = format!("Completed {}", count_tasks(task_ids.len()));
+            (Inverse::Reopen { task_ids }, msg)
+        }
+        BulkAction::Delete => {
+            for &task_id in &form.task_ids {
+                db::delete_task(&mut transaction, task_id, user_id).await?;
+            }
+            let msg = format!("Moved {} to the trash", count_tasks(form.task_ids.len()));
+            let task_ids = form.task_ids;
+            (Inverse::Restore { task_ids }, msg)
+        }
+        BulkAction::Move => {
+            let mut moves = Vec::new();
+            for &task_id in &form.task_ids {
+                let previous =
+                    db::move_task(&mut transaction, task_id, form.workspace_id, user_id).await?;
+                if previous != form.workspace_id {
+                    moves.push(TaskMove {
+                        task_id,
+                        workspace_id: previous,
+                    });
+                }
+            }
+            let msg = format!("Moved {} to {destination}", count_tasks(moves.len()));
+            (Inverse::Move { moves }, msg)
+        }
+    };
+    let undo_token = record_undo(&mut transaction, &hmac_key, user_id, inverse).await?;
+    transaction.commit().await?;
+
+    flash_msgs.set_undoable(&msg, undo_token).await?;
     Ok(Redirect::to("/todo"))
 }
+
+/// The strong `ETag` of a version of a task.
+fn task_etag(version: i32) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{version}\"")).expect("a quoted number is a valid header")
+}
+
+/// The task version an `If-Match` header allows updating, `None` without one. The task
+/// is locked in the caller's transaction first, so it's still at that version when it's
+/// updated. A header that doesn't list the task's current `ETag` is
+/// [`Error::PreconditionFailed`], the same as for CalDAV clients.
+async fn if_match(
+    conn: &mut PgConnection,
+    headers: &HeaderMap,
+    task_id: Uuid,
+) -> Result<Option<i32>> {
+    let Some(value) = headers.get(IF_MATCH) else {
+        return Ok(None);
+    };
+    let version = db::lock_task_version(conn, task_id).await?;
+    if !etag_listed(value, Some(&task_etag(version))) {
+        return Err(Error::PreconditionFailed);
+    }
+    Ok(Some(version))
+}
+
+/// The task's shares when the user is allowed to manage them.
+async fn task_shares(
+    pool: &PgPool,
+    grants: &Grants,
+    task_id: Uuid,
+) -> Result<Option<Vec<TaskShare>>> {
+    if !grants.allows(TaskAction::Share) {
+        return Ok(None);
+    }
+    db::get_task_shares(pool, task_id).await.map(Some)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShareTask {
+    /// Username or email of the user to share with.
+    user: String,
+    role: ShareRole,
+}
+
+#[instrument(
+    skip_all,
+    fields(action = "sharing a task", %task_id, %share.user, %share.role, %user_session)
+)]
+pub async fn share_task(
+    State(pool): State<PgPool>,
+    Path(task_id): Path<Uuid>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+    Form(share): Form<ShareTask>,
+) -> Result<Redirect> {
+    authorize_task(&pool, &user_session, task_id, TaskAction::Share).await?;
+    let edit_page = Redirect::to(&format!("/todo/{task_id}/edit"));
+    let task = db::get_task(&pool, task_id).await?.ok_or(Error::NotFound)?;
+
+    let name = share.user.trim();
+    let Some(user) = users::db::get_user_by_username_or_email(&pool, name).await? else {
+        flash_msgs
+            .set_msg(
+                FlashMessageLevel::Error,
+                &format!("There is no user with the username or email {name}"),
+            )
+            .await?;
+        return Ok(edit_page);
+    };
+    if user.user_id == task.user_id {
+        flash_msgs
+            .set_msg(
+                FlashMessageLevel::Error,
+                &format!("{} already owns this task", user.username),
+            )
+            .await?;
+        return Ok(edit_page);
+    }
+
+    db::share_task(&pool, task_id, user.user_id, share.role).await?;
+    flash_msgs
+        .set_msg(
+            FlashMessageLevel::Success,
+            &format!("Shared with {} as {}", user.username, share.role),
+        )
+        .await?;
+    Ok(edit_page)
+}
+
+#[instrument(skip_all, fields(action = "unsharing a task", %task_id, %user_id, %user_session))]
+pub async fn unshare_task(
+    State(pool): State<PgPool>,
+    Path((task_id, user_id)): Path<(Uuid, Uuid)>,
+    Extension(user_session): Extension<UserSessionData>,
+) -> Result<Redirect> {
+    authorize_task(&pool, &user_session, task_id, TaskAction::Share).await?;
+    db::unshare_task(&pool, task_id, user_id, user_session.user_id()).await?;
+
+    Ok(Redirect::to(&format!("/todo/{task_id}/edit")))
+}
@@ -1,20 +1,80 @@
 use askama::Template;
+use uuid::Uuid;
 
-use super::db::Task;
+use super::super::{
+    attachments::Attachment,
+    comments::ThreadedComment,
+    filters,
+    layout::{FormErrors, PageContext},
+    workspaces::db::Membership,
+};
+use super::{
+    db::{ListedTask, Task, TaskDetails, TaskFilter, TaskShare, TaskUser, TrashedTask},
+    history::TaskEvent,
+    routes::{NewTask, UpdateTask},
+};
 
 #[derive(Template)]
 #[template(path = "new_todo.html")]
-pub struct NewTodoTemplate;
+pub struct NewTodoTemplate {
+    pub ctx: PageContext,
+    pub errors: FormErrors,
+    pub form: NewTask,
+}
 
 #[derive(Template)]
 #[template(path = "todos.html")]
-pub struct TodosTemplate<'a> {
-    pub username: &'a str,
-    pub todos: Vec<Task>,
+pub struct TodosTemplate {
+    pub ctx: PageContext,
+    pub todos: Vec<ListedTask>,
+    /// Guests can't create tasks in a workspace.
+    pub can_create: bool,
+    pub filter: TaskFilter,
+    /// Where the selected tasks can be moved to, besides their owners' personal tasks.
+    pub workspaces: Vec<Membership>,
+}
+
+impl TodosTemplate {
+    /// Moving tasks to the trash or elsewhere is only offered when some of them can be
+    /// deleted, the page narrows it down to the selected ones.
+    pub fn can_delete_any(&self) -> bool {
+        self.todos.iter().any(ListedTask::can_delete)
+    }
 }
 
 #[derive(Template)]
 #[template(path = "edit_todo.html")]
 pub struct EditTodoTemplate {
-    pub todo: Task,
+    pub ctx: PageContext,
+    pub task_id: Uuid,
+    /// `None` when the user can't change who the task is shared with.
+    pub shares: Option<Vec<TaskShare>>,
+    /// Everyone the task can be assigned to.
+    pub users: Vec<TaskUser>,
+    pub errors: FormErrors,
+    pub form: UpdateTask,
+    /// The task as it is now, when the edit was made to an older version of it.
+    pub conflict: Option<Task>,
+}
+
+#[derive(Template)]
+#[template(path = "task.html")]
+pub struct TaskTemplate {
+    pub ctx: PageContext,
+    pub task: TaskDetails,
+    pub can_edit: bool,
+    pub can_comment: bool,
+    pub comments: Vec<ThreadedComment>,
+    pub attachments: Vec<Attachment>,
+    pub history: Vec<TaskEvent>,
+    /// The current user, who can edit and delete their own comments.
+    pub user_id: Uuid,
+}
+
+#[derive(Template)]
+#[template(path = "trash.html")]
+pub struct TrashTemplate {
+    pub ctx: PageContext,
+    pub tasks: Vec<TrashedTask>,
+    pub retention_days: u32,
 }
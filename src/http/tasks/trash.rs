@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::{error, info, instrument};
+
+use crate::config::Trash;
+
+use super::super::{attachments::Attachments, utilities::Result};
+use super::db;
+
+/// Permanently deletes the tasks that have been in the trash for longer than the
+/// retention period, returns how many there were.
+#[instrument(skip(pool, attachments))]
+pub async fn purge_expired(
+    pool: &PgPool,
+    attachments: &Attachments,
+    retention_days: u32,
+) -> Result<u64> {
+    let cutoff = OffsetDateTime::now_utc() - time::Duration::days(retention_days.into());
+    let (purged, storage_keys) = db::purge_expired_tasks(pool, cutoff).await?;
+    attachments.delete_blobs(&storage_keys).await;
+    if purged > 0 {
+        info!(purged, "purged expired tasks from the trash");
+    }
+    Ok(purged)
+}
+
+/// Runs [`purge_expired`] every `purge_interval` seconds for as long as the app is
+/// served, failed runs are logged and retried on the next one.
+pub async fn purge_periodically(pool: PgPool, attachments: Attachments, trash: Trash) {
+    let mut interval = tokio::time::interval(Duration::from_secs(trash.purge_interval.max(1)));
+    loop {
+        interval.tick().await;
+        if let Err(error) = purge_expired(&pool, &attachments, trash.retention_days).await {
+            error!(?error, "couldn't purge the trash");
+        }
+    }
+}
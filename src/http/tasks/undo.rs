@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, PgConnection, PgPool};
+use time::Duration;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::super::{
+    authorization::{
+        authorize_task, authorize_trashed_task, authorize_workspace, TaskAction, WorkspaceAction,
+    },
+    error::Error,
+    users::UserSessionData,
+    utilities::{HmacKey, Result},
+};
+use super::db;
+
+/// How long after an action it can be undone.
+const UNDO_WINDOW: Duration = Duration::minutes(5);
+
+/// What reverses a task action, kept server side until it's used or expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Inverse {
+    /// Undoes moving tasks to the trash.
+    Restore { task_ids: Vec<Uuid> },
+    /// Undoes completing tasks.
+    Reopen { task_ids: Vec<Uuid> },
+    /// Undoes moving tasks, putting each back in the workspace it was in.
+    Move { moves: Vec<TaskMove> },
+}
+
+/// Where a task goes back to, `None` being its owner's personal tasks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskMove {
+    pub task_id: Uuid,
+    pub workspace_id: Option<Uuid>,
+}
+
+impl Inverse {
+    /// Checks the user may still do what undoing takes, tasks might have been
+    /// unshared or purged in the meantime.
+    async fn authorize(&self, pool: &PgPool, user: &UserSessionData) -> Result<()> {
+        match self {
+            Self::Restore { task_ids } => {
+                for &task_id in task_ids {
+                    authorize_trashed_task(pool, user, task_id).await?;
+                }
+            }
+            Self::Reopen { task_ids } => {
+                for &task_id in task_ids {
+                    authorize_task(pool, user, task_id, TaskAction::Edit).await?;
+                }
+            }
+            Self::Move { moves } => {
+                for task_move in moves {
+                    authorize_task(pool, user, task_move.task_id, TaskAction::Delete).await?;
+                    if let Some(workspace_id) = task_move.workspace_id {
+                        authorize_workspace(pool, user, workspace_id, WorkspaceAction::CreateTasks)
+                            .await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn apply(&self, conn: &mut PgConnection, user_id: Uuid) -> Result<()> {
+        match self {
+            Self::Restore { task_ids } => {
+                for &task_id in task_ids {
+                    db::restore_task(conn, task_id, user_id).await?;
+                }
+            }
+            Self::Reopen { task_ids } => {
+                for &task_id in task_ids {
+                    db::set_completed(conn, task_id, false, user_id).await?;
+                }
+            }
+            Self::Move { moves } => {
+                for task_move in moves {
+                    db::move_task(conn, task_move.task_id, task_move.workspace_id, user_id).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Stores how to reverse an action the user just took, in the transaction taking it.
+/// Returns the signed token that undoes it, for [`super::super::utilities::FlashMessages::set_undoable`].
+#[instrument(skip(conn, hmac_key))]
+pub async fn record_undo(
+    conn: &mut PgConnection,
+    hmac_key: &HmacKey,
+    user_id: Uuid,
+    inverse: Inverse,
+) -> Result<String> {
+    sqlx::query!(
+        r#"
+        delete from task_undo
+        where user_id = $1 and expires_at < now()
+        "#,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let undo_id = sqlx::query_scalar!(
+        r#"
+        insert into task_undo (user_id, inverse, expires_at)
+        values ($1, $2, now() + make_interval(secs => $3))
+        returning undo_id
+        "#,
+        user_id,
+        Json(inverse) as _,
+        UNDO_WINDOW.as_seconds_f64()
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(hmac_key.sign(&undo_id.to_string()))
+}
+
+/// What came of using an undo token.
+#[derive(Debug, PartialEq, Eq)]
+pub enum UndoOutcome {
+    Undone,
+    /// The undo window has passed.
+    Expired,
+    AlreadyUndone,
+}
+
+/// Reverses the action behind `token`, at most once and only within the undo window.
+/// Tokens that weren't signed by us or belong to another user are [`Error::NotFound`].
+/// Expired records are deleted by [`record_undo`], so a token we signed without one
+/// expired too.
+#[instrument(skip_all, fields(%user))]
+pub async fn undo(
+    pool: &PgPool,
+    hmac_key: &HmacKey,
+    user: &UserSessionData,
+    token: &str,
+) -> Result<UndoOutcome> {
+    let undo_id: Uuid = hmac_key
+        .verify(token)
+        .and_then(|undo_id| undo_id.parse().ok())
+        .ok_or(Error::NotFound)?;
+
+    let mut transaction = pool.begin().await?;
+    let record = sqlx::query!(
+        r#"
+        select
+            user_id,
+            inverse as "inverse: Json<Inverse>",
+            expires_at < now() as "expired!",
+            undone_at is not null as "undone!"
+        from task_undo
+        where undo_id = $1
+        for update
+        "#,
+        undo_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    let Some(record) = record else {
+        return Ok(UndoOutcome::Expired);
+    };
+    if record.user_id != user.user_id() {
+        return Err(Error::NotFound);
+    }
+    if record.undone {
+        return Ok(UndoOutcome::AlreadyUndone);
+    }
+    if record.expired {
+        return Ok(UndoOutcome::Expired);
+    }
+
+    let Json(inverse) = record.inverse;
+    inverse.authorize(pool, user).await?;
+    inverse.apply(&mut transaction, user.user_id()).await?;
+    sqlx::query!(
+        r#"
+        update task_undo
+        set undone_at = now()
+        where undo_id = $1
+        "#,
+        undo_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+    Ok(UndoOutcome::Undone)
+}
@@ -0,0 +1,133 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use axum::{
+    handler::HandlerWithoutStateExt,
+    http::{
+        header::HOST,
+        uri::{Authority, Scheme},
+        HeaderMap, StatusCode, Uri,
+    },
+    response::{IntoResponse, Redirect},
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::net::TcpListener;
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::config::Tls;
+
+/// Serves the app over HTTPS on the given listener, reloading the certificate when
+/// its files change and, if configured, redirecting plain HTTP requests to HTTPS.
+pub async fn serve_tls(
+    tls: Tls,
+    host: &str,
+    app: Router,
+    listener: TcpListener,
+) -> anyhow::Result<()> {
+    let rustls_config = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+        .await
+        .context("Error loading the TLS certificate and key")?;
+    let https_port = listener
+        .local_addr()
+        .context("Error getting the address of the HTTPS listener")?
+        .port();
+
+    if let Some(http_port) = tls.http_redirect_port {
+        let redirect_listener = TcpListener::bind((host, http_port))
+            .await
+            .context("Error binding the HTTP redirect listener")?;
+        tokio::spawn(serve_https_redirect(redirect_listener, https_port));
+    }
+
+    tokio::spawn(reload_on_change(rustls_config.clone(), tls));
+
+    info!("serving app over HTTPS");
+    axum_server::from_tcp_rustls(listener.into_std()?, rustls_config)
+        .serve(app.into_make_service())
+        .await
+        .context("Error running HTTPS server")
+}
+
+#[instrument(skip_all)]
+async fn reload_on_change(rustls_config: RustlsConfig, tls: Tls) {
+    let mut last_modified = modified_at(&tls).await;
+    let mut interval = tokio::time::interval(Duration::from_secs(tls.reload_interval.max(1)));
+
+    loop {
+        interval.tick().await;
+        let modified = modified_at(&tls).await;
+        if modified == last_modified {
+            continue;
+        }
+
+        debug!("certificate files changed, reloading");
+        match rustls_config
+            .reload_from_pem_file(&tls.cert_path, &tls.key_path)
+            .await
+        {
+            Ok(()) => {
+                info!("reloaded TLS certificate");
+                last_modified = modified;
+            }
+            Err(error) => warn!(
+                ?error,
+                "failed to reload TLS certificate, keeping the old one"
+            ),
+        }
+    }
+}
+
+async fn modified_at(tls: &Tls) -> Option<(SystemTime, SystemTime)> {
+    let cert = tokio::fs::metadata(&tls.cert_path)
+        .await
+        .ok()?
+        .modified()
+        .ok()?;
+    let key = tokio::fs::metadata(&tls.key_path)
+        .await
+        .ok()?
+        .modified()
+        .ok()?;
+    Some((cert, key))
+}
+
+async fn serve_https_redirect(listener: TcpListener, https_port: u16) {
+    let redirect = move |headers: HeaderMap, uri: Uri| async move {
+        match https_uri(&headers, uri, https_port) {
+            Ok(uri) => Redirect::permanent(&uri.to_string()).into_response(),
+            Err(error) => {
+                debug!(?error, "can't redirect request to HTTPS");
+                StatusCode::BAD_REQUEST.into_response()
+            }
+        }
+    };
+
+    info!("redirecting plain HTTP to HTTPS");
+    if let Err(error) = axum::serve(listener, redirect.into_make_service()).await {
+        error!(?error, "HTTP redirect server stopped");
+    }
+}
+
+fn https_uri(headers: &HeaderMap, uri: Uri, https_port: u16) -> anyhow::Result<Uri> {
+    let host = headers
+        .get(HOST)
+        .context("request has no Host header")?
+        .to_str()?
+        .parse::<Authority>()?;
+
+    let authority = if https_port == 443 {
+        host.host().parse()?
+    } else {
+        format!("{}:{}", host.host(), https_port).parse()?
+    };
+
+    let mut parts = uri.into_parts();
+    parts.scheme = Some(Scheme::HTTPS);
+    parts.authority = Some(authority);
+    if parts.path_and_query.is_none() {
+        parts.path_and_query = Some("/".parse()?);
+    }
+
+    Ok(Uri::from_parts(parts)?)
+}
@@ -0,0 +1,127 @@
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{header::WWW_AUTHENTICATE, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+use axum_extra::{
+    headers::{authorization::Basic, Authorization},
+    TypedHeader,
+};
+use secrecy::SecretString;
+use sqlx::PgPool;
+use time::{Duration, OffsetDateTime};
+use tracing::{debug, instrument, warn};
+
+use super::super::utilities::Result;
+use super::{db, verify_password, UserSessionData, APP_PASSWORD_SEPARATOR};
+
+/// The user behind HTTP Basic credentials, for clients that can't keep a session like
+/// CalDAV apps. Their login is a username or email, and the password can be the account
+/// password or one of the user's app passwords.
+pub struct BasicAuth(pub UserSessionData);
+
+impl<S> FromRequestParts<S> for BasicAuth
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Ok(TypedHeader(Authorization(credentials))) =
+            TypedHeader::<Authorization<Basic>>::from_request_parts(parts, state).await
+        else {
+            return Err(challenge());
+        };
+
+        let pool = PgPool::from_ref(state);
+        match authenticate(&pool, credentials.username(), credentials.password()).await {
+            Ok(Some(user)) => Ok(Self(user)),
+            Ok(None) => Err(challenge()),
+            Err(error) => Err(error.into_response()),
+        }
+    }
+}
+
+/// Asks the client for credentials, rather than redirecting to the login page.
+fn challenge() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(
+            WWW_AUTHENTICATE,
+            r#"Basic realm="todo-web-app", charset="UTF-8""#,
+        )],
+    )
+        .into_response()
+}
+
+/// Failed logins in a row after which a user's credentials aren't checked until
+/// [`FAILURE_WINDOW`] has passed since the last one.
+const MAX_FAILURES: i32 = 5;
+const FAILURE_WINDOW: Duration = Duration::minutes(1);
+
+/// How stale an app password's last use may get before it's written again, so syncing
+/// doesn't write on every request.
+const LAST_USED_PRECISION: Duration = Duration::minutes(10);
+
+/// A hash of a password nobody knows, made with the same parameters as the users'
+/// hashes. Unknown logins are checked against it, so they take as long to answer as
+/// known ones and don't tell which logins exist.
+const DUMMY_PASSWORD_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$d8fpXBbn66EcP1v6Ua+6WA$RAX0zTy1My8MU68v9JCD3HrZaRv5RWZlvb7rsQthB7s";
+
+/// Checks the password against a single hash: the app password its lookup id points to
+/// or else the account password.
+#[instrument(skip(pool, password))]
+async fn authenticate(
+    pool: &PgPool,
+    login: &str,
+    password: &str,
+) -> Result<Option<UserSessionData>> {
+    let Some(user) = db::get_user_by_username_or_email(pool, login).await? else {
+        debug!("no such user");
+        verify_password(
+            &SecretString::from(password),
+            &SecretString::from(DUMMY_PASSWORD_HASH),
+        )
+        .await?;
+        return Ok(None);
+    };
+    let failures = match user.basic_auth_failed_at {
+        Some(failed_at) if OffsetDateTime::now_utc() - failed_at < FAILURE_WINDOW => {
+            user.basic_auth_failures
+        }
+        _ => 0,
+    };
+    if failures >= MAX_FAILURES {
+        warn!(target: "audit", user_id = %user.user_id, "too many failed logins");
+        return Ok(None);
+    }
+
+    let app_password = match password.split_once(APP_PASSWORD_SEPARATOR) {
+        Some((lookup_id, _)) => db::get_app_password_hash(pool, user.user_id, lookup_id).await?,
+        None => None,
+    };
+    let password_hash = match &app_password {
+        Some(app_password) => &app_password.password_hash,
+        None => &user.password_hash,
+    };
+    if !verify_password(&SecretString::from(password), password_hash).await? {
+        debug!("wrong password");
+        db::record_basic_auth_failure(pool, user.user_id, FAILURE_WINDOW.as_seconds_f64()).await?;
+        return Ok(None);
+    }
+
+    if failures > 0 {
+        db::clear_basic_auth_failures(pool, user.user_id).await?;
+    }
+    if let Some(app_password) = app_password {
+        let stale = app_password.last_used_at.is_none_or(|last_used_at| {
+            OffsetDateTime::now_utc() - last_used_at > LAST_USED_PRECISION
+        });
+        if stale {
+            db::touch_app_password(pool, app_password.app_password_id).await?;
+        }
+    }
+    Ok(Some((&user).into()))
+}
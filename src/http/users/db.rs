@@ -40,8 +40,14 @@ pub struct User {
     pub email: String,
     pub username: String,
     pub password_hash: SecretString,
+    pub is_admin: bool,
     pub updated_at: OffsetDateTime,
     pub created_at: OffsetDateTime,
+    /// Part of the signed address of the user's calendar feed.
+    pub calendar_secret: Uuid,
+    /// Failed HTTP Basic logins in a row, the last of them at `basic_auth_failed_at`.
+    pub basic_auth_failures: i32,
+    pub basic_auth_failed_at: Option<OffsetDateTime>,
 }
 
 pub async fn get_user_by_email(pool: &PgPool, email: &str) -> Result<Option<User>> {
@@ -57,3 +63,229 @@ pub async fn get_user_by_email(pool: &PgPool, email: &str) -> Result<Option<User
     .await
     .map_err(Into::into)
 }
+
+pub async fn get_user_by_username_or_email(pool: &PgPool, name: &str) -> Result<Option<User>> {
+    sqlx::query_as!(
+        User,
+        r#"
+        select * from users
+        where username = $1 or email = $1
+        "#,
+        name
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(Into::into)
+}
+
+pub async fn get_all_users(pool: &PgPool) -> Result<Vec<User>> {
+    sqlx::query_as!(
+        User,
+        r#"
+        select * from users
+        order by created_at
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+pub async fn update_password_hash(pool: &PgPool, email: &str, password_hash: &str) -> Result<()> {
+    let query_result = sqlx::query!(
+        r#"
+        update users
+        set password_hash = $1
+        where email = $2
+        "#,
+        password_hash,
+        email
+    )
+    .execute(pool)
+    .await?;
+
+    if query_result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+
+    Ok(())
+}
+
+pub async fn set_admin(pool: &PgPool, email: &str, is_admin: bool) -> Result<()> {
+    let query_result = sqlx::query!(
+        r#"
+        update users
+        set is_admin = $1
+        where email = $2
+        "#,
+        is_admin,
+        email
+    )
+    .execute(pool)
+    .await?;
+
+    if query_result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+
+    Ok(())
+}
+
+/// A password for a CalDAV client, only shown once when it's created.
+#[derive(Debug)]
+pub struct AppPassword {
+    pub app_password_id: Uuid,
+    pub name: String,
+    pub last_used_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+}
+
+pub async fn insert_app_password(
+    pool: &PgPool,
+    user_id: Uuid,
+    name: &str,
+    lookup_id: &str,
+    password_hash: &str,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        insert into app_password (user_id, name, lookup_id, password_hash)
+        values ($1, $2, $3, $4)
+        "#,
+        user_id,
+        name,
+        lookup_id,
+        password_hash
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_app_passwords(pool: &PgPool, user_id: Uuid) -> Result<Vec<AppPassword>> {
+    sqlx::query_as!(
+        AppPassword,
+        r#"
+        select app_password_id, name, last_used_at, created_at
+        from app_password
+        where user_id = $1
+        order by created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// The hash of the user's app password with this lookup id, to check a password against.
+pub struct AppPasswordHash {
+    pub app_password_id: Uuid,
+    pub password_hash: SecretString,
+    pub last_used_at: Option<OffsetDateTime>,
+}
+
+pub async fn get_app_password_hash(
+    pool: &PgPool,
+    user_id: Uuid,
+    lookup_id: &str,
+) -> Result<Option<AppPasswordHash>> {
+    let hash = sqlx::query!(
+        r#"
+        select app_password_id, password_hash, last_used_at
+        from app_password
+        where user_id = $1 and lookup_id = $2
+        "#,
+        user_id,
+        lookup_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(hash.map(|hash| AppPasswordHash {
+        app_password_id: hash.app_password_id,
+        password_hash: hash.password_hash.into(),
+        last_used_at: hash.last_used_at,
+    }))
+}
+
+pub async fn touch_app_password(pool: &PgPool, app_password_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        update app_password
+        set last_used_at = now()
+        where app_password_id = $1
+        "#,
+        app_password_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Counts a failed HTTP Basic login, starting over when the last one is more than
+/// `window_secs` ago.
+pub async fn record_basic_auth_failure(
+    pool: &PgPool,
+    user_id: Uuid,
+    window_secs: f64,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        update users
+        set basic_auth_failures = case
+                when basic_auth_failed_at > now() - make_interval(secs => $2)
+                then basic_auth_failures + 1
+                else 1
+            end,
+            basic_auth_failed_at = now()
+        where user_id = $1
+        "#,
+        user_id,
+        window_secs
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn clear_basic_auth_failures(pool: &PgPool, user_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        update users
+        set basic_auth_failures = 0
+        where user_id = $1 and basic_auth_failures > 0
+        "#,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_app_password(
+    pool: &PgPool,
+    user_id: Uuid,
+    app_password_id: Uuid,
+) -> Result<()> {
+    let query_result = sqlx::query!(
+        r#"
+        delete from app_password
+        where app_password_id = $1 and user_id = $2
+        "#,
+        app_password_id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    if query_result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+
+    Ok(())
+}
@@ -1,21 +1,41 @@
 use anyhow::Context;
 use argon2::{
-    password_hash::{rand_core::OsRng, SaltString},
+    password_hash::{
+        rand_core::{OsRng, RngCore},
+        SaltString,
+    },
     Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
 };
 use secrecy::{ExposeSecret, SecretString};
 
-mod db;
+mod basic_auth;
+pub(crate) mod db;
 mod routes;
 mod session;
 mod templates;
 
+pub use basic_auth::BasicAuth;
 pub use routes::router;
-pub use session::{auth_middleware, UserSessionData};
+pub use session::{auth_middleware, CurrentWorkspace, SessionExt, UserSessionData};
 
 use super::{error::Error, utilities::Result};
 
-async fn hash_password(password: &SecretString) -> Result<String> {
+/// Separates the lookup id an app password starts with from the rest of it.
+const APP_PASSWORD_SEPARATOR: char = '.';
+
+/// Makes an app password, returning it with the lookup id it starts with. The id is
+/// stored as is, so logging in with an app password checks a single hash.
+pub(crate) fn generate_app_password() -> (String, String) {
+    let mut lookup_id = [0u8; 8];
+    OsRng.fill_bytes(&mut lookup_id);
+    let lookup_id = hex::encode(lookup_id);
+    let mut secret = [0u8; 16];
+    OsRng.fill_bytes(&mut secret);
+    let password = format!("{lookup_id}{APP_PASSWORD_SEPARATOR}{}", hex::encode(secret));
+    (lookup_id, password)
+}
+
+pub(crate) async fn hash_password(password: &SecretString) -> Result<String> {
     let current_span = tracing::Span::current();
     let password = password.clone();
     tokio::task::spawn_blocking(move || -> Result<String> {
@@ -31,7 +51,10 @@ async fn hash_password(password: &SecretString) -> Result<String> {
     .context("panic in spawned blocking thread for hashing")?
 }
 
-async fn verify_password(password: &SecretString, password_hash: &SecretString) -> Result<bool> {
+pub(crate) async fn verify_password(
+    password: &SecretString,
+    password_hash: &SecretString,
+) -> Result<bool> {
     let argon_password_hash = PasswordHash::new(password_hash.expose_secret())
         .context("failed to parse password in phc format")
         .map_err(Error::Other)?;
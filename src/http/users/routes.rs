@@ -1,10 +1,10 @@
 use axum::{
     extract::State,
-    response::{Html, IntoResponse, Redirect},
+    response::{Html, IntoResponse, Redirect, Response},
     routing::{get, Router},
     Form,
 };
-use secrecy::SecretString;
+use secrecy::{ExposeSecret, SecretString};
 use serde::Deserialize;
 use sqlx::PgPool;
 use tower_sessions::Session;
@@ -14,10 +14,14 @@ use crate::http::users::verify_password;
 
 use super::super::{
     error::Error,
-    utilities::{render_template, ApiState, FlashMessageLevel, FlashMessages, Result},
+    layout::{FormErrors, LazyPageContext, PageContext},
+    utilities::{
+        render_invalid_form, render_template, ApiState, FlashMessageLevel, FlashMessages, Result,
+    },
+    validation::{Validate, Validator},
 };
 
-use super::{db, hash_password, session::SessionExt, templates::*};
+use super::{db, hash_password, templates::*, SessionExt};
 
 pub fn router() -> Router<ApiState> {
     Router::new()
@@ -26,24 +30,24 @@ pub fn router() -> Router<ApiState> {
         .route("/logout", get(logout_user))
 }
 
-#[instrument(skip_all, fields(%flash_msgs))]
-async fn login_page(mut flash_msgs: FlashMessages) -> Result<Html<String>> {
-    let error_flash = flash_msgs.get_msgs().await?.into_iter().find_map(|fm| {
-        if fm.level == FlashMessageLevel::Error {
-            Some(fm.msg)
-        } else {
-            None
-        }
-    });
-
-    debug!(flash_errors = ?error_flash);
+#[instrument(skip_all)]
+async fn login_page(ctx: PageContext) -> Result<Html<String>> {
+    debug!(flash_msgs = ?ctx.flash_msgs);
 
-    render_template(LoginTemplate::new(error_flash))
+    render_template(LoginTemplate {
+        ctx,
+        errors: FormErrors::default(),
+    })
 }
 
-#[instrument]
-async fn register_page() -> impl IntoResponse {
-    render_template(RegisterTemplate)
+#[instrument(skip_all)]
+async fn register_page(ctx: PageContext) -> Result<Html<String>> {
+    render_template(RegisterTemplate {
+        ctx,
+        errors: FormErrors::default(),
+        email: String::new(),
+        username: String::new(),
+    })
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,6 +55,34 @@ struct CreateUser {
     username: String,
     email: String,
     password: SecretString,
+    confirm_password: SecretString,
+}
+
+impl Validate for CreateUser {
+    fn validate(&self) -> Result<()> {
+        let password = self.password.expose_secret();
+        Validator::new()
+            .required("email", &self.email)
+            .email("email", &self.email)
+            .required("username", &self.username)
+            .min_chars("username", &self.username, 3)
+            .max_chars("username", &self.username, 64)
+            .check(
+                "username",
+                self.username
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-'),
+                "username can only contain letters, digits, '_' and '-'",
+            )
+            .min_chars("password", password, 8)
+            .max_chars("password", password, 128)
+            .check(
+                "confirm_password",
+                password == self.confirm_password.expose_secret(),
+                "passwords don't match",
+            )
+            .finish()
+    }
 }
 
 #[instrument(skip_all, fields(
@@ -60,18 +92,33 @@ struct CreateUser {
 ))]
 async fn register_user(
     State(pool): State<PgPool>,
+    ctx: LazyPageContext,
     Form(create_user): Form<CreateUser>,
-) -> Result<Redirect> {
-    let password_hash = hash_password(&create_user.password).await?;
-    db::insert_user(
-        &pool,
-        &create_user.email,
-        &create_user.username,
-        &password_hash,
-    )
-    .await?;
+) -> Result<Response> {
+    let registered = async {
+        create_user.validate()?;
+        let password_hash = hash_password(&create_user.password).await?;
+        db::insert_user(
+            &pool,
+            &create_user.email,
+            &create_user.username,
+            &password_hash,
+        )
+        .await
+    }
+    .await;
+
+    if let Err(error) = registered {
+        return render_invalid_form(RegisterTemplate {
+            ctx: ctx.build().await?,
+            errors: error.try_into()?,
+            email: create_user.email,
+            username: create_user.username,
+        });
+    }
+
     info!("finished registering a user");
-    Ok(Redirect::to("/users/login"))
+    Ok(Redirect::to("/users/login").into_response())
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,37 +28,74 @@ impl UserSessionData {
     }
 }
 
+impl From<&User> for UserSessionData {
+    fn from(user: &User) -> Self {
+        Self {
+            user_id: user.user_id,
+            username: user.username.clone(),
+        }
+    }
+}
+
 impl Display for UserSessionData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "uuid: {}, username: {}", self.user_id, self.username)
     }
 }
 
+/// The workspace the user switched to, kept in the session next to
+/// [`UserSessionData`]. Without one the user works on their personal tasks.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CurrentWorkspace {
+    pub workspace_id: Uuid,
+    pub name: String,
+}
+
+impl CurrentWorkspace {
+    const SESSION_KEY: &'static str = "current_workspace";
+}
+
 pub trait SessionExt {
     async fn create_user_session(&self, user: &User) -> Result<()>;
+    async fn user_session(&self) -> Result<Option<UserSessionData>>;
+    async fn current_workspace(&self) -> Result<Option<CurrentWorkspace>>;
+    async fn set_current_workspace(&self, workspace: Option<CurrentWorkspace>) -> Result<()>;
 }
 
 impl SessionExt for Session {
     async fn create_user_session(&self, user: &User) -> Result<()> {
         debug!("inserting user session into session store");
-        self.insert(
-            UserSessionData::SESSION_KEY,
-            UserSessionData {
-                user_id: user.user_id,
-                username: user.username.clone(),
-            },
-        )
-        .await?;
+        self.insert(UserSessionData::SESSION_KEY, UserSessionData::from(user))
+            .await?;
         self.cycle_id().await?;
         Ok(())
     }
+
+    async fn user_session(&self) -> Result<Option<UserSessionData>> {
+        Ok(self.get(UserSessionData::SESSION_KEY).await?)
+    }
+
+    async fn current_workspace(&self) -> Result<Option<CurrentWorkspace>> {
+        Ok(self.get(CurrentWorkspace::SESSION_KEY).await?)
+    }
+
+    async fn set_current_workspace(&self, workspace: Option<CurrentWorkspace>) -> Result<()> {
+        match workspace {
+            Some(workspace) => {
+                self.insert(CurrentWorkspace::SESSION_KEY, workspace)
+                    .await?
+            }
+            None => {
+                self.remove::<CurrentWorkspace>(CurrentWorkspace::SESSION_KEY)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
 }
 
 pub async fn auth_middleware(session: Session, mut req: Request, next: Next) -> Result<Response> {
-    match session
-        .get::<UserSessionData>(UserSessionData::SESSION_KEY)
-        .await?
-    {
+    match session.user_session().await? {
         Some(user_session_data) => {
             req.extensions_mut().insert(user_session_data);
             let response = next.run(req).await;
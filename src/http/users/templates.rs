@@ -1,17 +1,22 @@
 use askama::Template;
 
+use super::super::{
+    filters,
+    layout::{FormErrors, PageContext},
+};
+
 #[derive(Template)]
 #[template(path = "register.html")]
-pub struct RegisterTemplate;
+pub struct RegisterTemplate {
+    pub ctx: PageContext,
+    pub errors: FormErrors,
+    pub email: String,
+    pub username: String,
+}
 
 #[derive(Template)]
 #[template(path = "login.html")]
 pub struct LoginTemplate {
-    errors: Option<String>,
-}
-
-impl LoginTemplate {
-    pub fn new(errors: Option<String>) -> Self {
-        Self { errors }
-    }
+    pub ctx: PageContext,
+    pub errors: FormErrors,
 }
@@ -1,18 +1,22 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
 use anyhow::anyhow;
 use askama::Template;
 use axum::{
     extract::{FromRef, FromRequestParts},
-    http::request::Parts,
-    response::Html,
+    http::{request::Parts, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
 };
-use secrecy::SecretString;
-use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::Sha256;
 use sqlx::PgPool;
 use tower_sessions::Session;
 
-use super::error::Error;
+use crate::config::Trash;
+
+use super::{attachments::Attachments, error::Error};
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -20,17 +24,48 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub struct ApiState {
     pub pool: PgPool,
     pub hmac_key: HmacKey,
+    pub attachments: Attachments,
+    pub trash: Trash,
 }
 
 impl ApiState {
-    pub fn new(pool: PgPool, hmac_key: HmacKey) -> Self {
-        Self { pool, hmac_key }
+    pub fn new(pool: PgPool, hmac_key: HmacKey, attachments: Attachments, trash: Trash) -> Self {
+        Self {
+            pool,
+            hmac_key,
+            attachments,
+            trash,
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct HmacKey(pub SecretString);
 
+impl HmacKey {
+    fn mac(&self, data: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.0.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(data.as_bytes());
+        mac
+    }
+
+    /// Signs `data` as `data.signature`, so it can be handed to clients and trusted
+    /// when it comes back.
+    pub fn sign(&self, data: &str) -> String {
+        let signature = hex::encode(self.mac(data).finalize().into_bytes());
+        format!("{data}.{signature}")
+    }
+
+    /// The data of a value made by [`HmacKey::sign`], `None` if it was tampered with.
+    pub fn verify<'a>(&self, signed: &'a str) -> Option<&'a str> {
+        let (data, signature) = signed.rsplit_once('.')?;
+        let signature = hex::decode(signature).ok()?;
+        self.mac(data).verify_slice(&signature).ok()?;
+        Some(data)
+    }
+}
+
 pub fn render_template<T>(template: T) -> Result<Html<String>>
 where
     T: Template,
@@ -38,10 +73,64 @@ where
     Ok(Html(template.render()?))
 }
 
+/// Renders a form again with a 422 status, after its submission failed validation
+pub fn render_invalid_form<T>(template: T) -> Result<Response>
+where
+    T: Template,
+{
+    Ok((StatusCode::UNPROCESSABLE_ENTITY, render_template(template)?).into_response())
+}
+
+/// Whether an `If-Match` or `If-None-Match` header value lists `current`, the `ETag`
+/// of a resource or `None` when it doesn't exist. Tags are compared with the strong
+/// comparison function of RFC 9110, so weak tags never match, and `*` matches any
+/// resource that exists.
+pub fn etag_listed(value: &HeaderValue, current: Option<&HeaderValue>) -> bool {
+    value
+        .to_str()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .any(|tag| (tag == "*" && current.is_some()) || current.is_some_and(|etag| etag == tag))
+}
+
+/// For optional form fields, e.g. a `<select>` whose "none" option has an empty value,
+/// use with `#[serde(default, deserialize_with = "empty_string_as_none")]`.
+pub fn empty_string_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => value.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Tells a field that was left out apart from one that was sent empty, for partial
+/// updates: `None` when missing, `Some(None)` when empty. Use with
+/// `#[serde(default, deserialize_with = "present_empty_string_as_none")]`.
+pub fn present_empty_string_as_none<'de, D, T>(
+    deserializer: D,
+) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    empty_string_as_none(deserializer).map(Some)
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FlashMessage {
     pub level: FlashMessageLevel,
     pub msg: String,
+    /// Signed token of the undo record reversing what the message is about, shown as
+    /// an "Undo" button.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undo_token: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -79,10 +168,35 @@ impl FlashMessages {
         self.msgs.push(FlashMessage {
             level,
             msg: msg.to_string(),
+            undo_token: None,
         });
         self.update_session().await
     }
 
+    /// A success message with an "Undo" button posting `undo_token` to `/todo/undo`.
+    pub async fn set_undoable(&mut self, msg: &str, undo_token: String) -> Result<()> {
+        self.msgs.push(FlashMessage {
+            level: FlashMessageLevel::Success,
+            msg: msg.to_string(),
+            undo_token: Some(undo_token),
+        });
+        self.update_session().await
+    }
+
+    /// Shows the messages of a failed validation as errors, for forms that redirect
+    /// back to a page instead of rendering it again. Other errors are passed on.
+    pub async fn set_invalid(&mut self, error: Error) -> Result<()> {
+        match error {
+            Error::UnprocessableEntity { errors } => {
+                for msg in errors.into_values().flatten() {
+                    self.set_msg(FlashMessageLevel::Error, &msg).await?;
+                }
+                Ok(())
+            }
+            other => Err(other),
+        }
+    }
+
     pub async fn get_msgs(&mut self) -> Result<Vec<FlashMessage>> {
         let out = std::mem::take(&mut self.msgs);
         self.update_session().await?;
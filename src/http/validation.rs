@@ -0,0 +1,86 @@
+use std::borrow::Cow;
+
+use super::{error::Error, utilities::Result};
+
+/// Input that is checked before it's acted on, failures come back as
+/// [`Error::UnprocessableEntity`] with messages keyed by field name.
+pub trait Validate {
+    fn validate(&self) -> Result<()>;
+}
+
+/// Collects field level errors, e.g.
+/// `Validator::new().required("title", &self.title).finish()`.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<(&'static str, Cow<'static, str>)>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn check(
+        mut self,
+        field: &'static str,
+        valid: bool,
+        msg: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        if !valid {
+            self.errors.push((field, msg.into()));
+        }
+        self
+    }
+
+    pub fn required(self, field: &'static str, value: &str) -> Self {
+        self.check(
+            field,
+            !value.trim().is_empty(),
+            format!("{field} is required"),
+        )
+    }
+
+    pub fn min_chars(self, field: &'static str, value: &str, min: usize) -> Self {
+        self.check(
+            field,
+            value.chars().count() >= min,
+            format!("{field} must be at least {min} characters long"),
+        )
+    }
+
+    pub fn max_chars(self, field: &'static str, value: &str, max: usize) -> Self {
+        self.check(
+            field,
+            value.chars().count() <= max,
+            format!("{field} must be at most {max} characters long"),
+        )
+    }
+
+    pub fn email(self, field: &'static str, value: &str) -> Self {
+        self.check(
+            field,
+            is_email(value),
+            format!("{field} is not a valid email address"),
+        )
+    }
+
+    pub fn finish(self) -> Result<()> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::unprocessable_entity(self.errors))
+        }
+    }
+}
+
+/// Deliberately loose, the only real check of an address is sending mail to it.
+fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.rsplit_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && value.len() <= 254
+        && !value.chars().any(char::is_whitespace)
+        && domain.contains('.')
+        && domain.split('.').all(|label| !label.is_empty())
+}
@@ -0,0 +1,181 @@
+use sqlx::{PgConnection, PgPool};
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::super::{
+    authorization::WorkspaceRole, error::Error, tasks::db as tasks_db, utilities::Result,
+};
+
+#[derive(Debug)]
+pub struct Workspace {
+    pub workspace_id: Uuid,
+    pub name: String,
+}
+
+/// A workspace as seen by one of its members.
+#[derive(Debug)]
+pub struct Membership {
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub role: WorkspaceRole,
+}
+
+#[derive(Debug)]
+pub struct Member {
+    pub user_id: Uuid,
+    pub username: String,
+    pub email: String,
+    pub role: WorkspaceRole,
+}
+
+/// Creates a workspace with `owner_id` as its first owner.
+#[instrument(skip_all, fields(%name, %owner_id))]
+pub async fn create_workspace(pool: &PgPool, name: &str, owner_id: Uuid) -> Result<Uuid> {
+    let mut transaction = pool.begin().await?;
+
+    let workspace_id = sqlx::query_scalar!(
+        r#"
+        insert into workspace (name)
+        values ($1)
+        returning workspace_id
+        "#,
+        name
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        insert into workspace_member (workspace_id, user_id, role)
+        values ($1, $2, $3)
+        "#,
+        workspace_id,
+        owner_id,
+        WorkspaceRole::Owner as WorkspaceRole
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+    Ok(workspace_id)
+}
+
+#[instrument(skip_all, fields(%workspace_id))]
+pub async fn get_workspace(pool: &PgPool, workspace_id: Uuid) -> Result<Option<Workspace>> {
+    sqlx::query_as!(
+        Workspace,
+        r#"
+        select workspace_id, name from workspace
+        where workspace_id = $1
+        "#,
+        workspace_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+#[instrument(skip_all, fields(%user_id))]
+pub async fn get_memberships(pool: &PgPool, user_id: Uuid) -> Result<Vec<Membership>> {
+    sqlx::query_as!(
+        Membership,
+        r#"
+        select w.workspace_id, w.name, m.role as "role: WorkspaceRole"
+        from workspace_member m
+        join workspace w on w.workspace_id = m.workspace_id
+        where m.user_id = $1
+        order by w.name
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+#[instrument(skip_all, fields(%workspace_id))]
+pub async fn get_members(pool: &PgPool, workspace_id: Uuid) -> Result<Vec<Member>> {
+    sqlx::query_as!(
+        Member,
+        r#"
+        select u.user_id, u.username, u.email, m.role as "role: WorkspaceRole"
+        from workspace_member m
+        join users u on u.user_id = m.user_id
+        where m.workspace_id = $1
+        order by m.created_at
+        "#,
+        workspace_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// The workspace's owners, locked until the caller's transaction ends so that changes
+/// to owners made at the same time can't leave the workspace without one.
+#[instrument(skip_all, fields(%workspace_id))]
+pub async fn lock_owners(conn: &mut PgConnection, workspace_id: Uuid) -> Result<Vec<Uuid>> {
+    sqlx::query_scalar!(
+        r#"
+        select user_id from workspace_member
+        where workspace_id = $1 and role = 'owner'
+        for update
+        "#,
+        workspace_id
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::SQLx)
+}
+
+/// Adds the user to the workspace, or changes their role if they already are a member.
+#[instrument(skip_all, fields(%workspace_id, %user_id, %role))]
+pub async fn upsert_member(
+    conn: &mut PgConnection,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    role: WorkspaceRole,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        insert into workspace_member (workspace_id, user_id, role)
+        values ($1, $2, $3)
+        on conflict (workspace_id, user_id) do update set role = excluded.role
+        "#,
+        workspace_id,
+        user_id,
+        role as WorkspaceRole
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Takes the user out of the workspace and unassigns them from its tasks they no
+/// longer have access to, in the caller's transaction.
+#[instrument(skip_all, fields(%workspace_id, %user_id, %removed_by))]
+pub async fn remove_member(
+    conn: &mut PgConnection,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    removed_by: Uuid,
+) -> Result<()> {
+    let query_result = sqlx::query!(
+        r#"
+        delete from workspace_member
+        where workspace_id = $1 and user_id = $2
+        "#,
+        workspace_id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    if query_result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+    tasks_db::unassign_without_access(conn, user_id, removed_by).await?;
+
+    Ok(())
+}
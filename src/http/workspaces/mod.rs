@@ -0,0 +1,37 @@
+use sqlx::PgPool;
+use tower_sessions::Session;
+
+use super::{
+    authorization::{authorize_workspace, WorkspaceAction, WorkspaceRole},
+    error::Error,
+    users::{CurrentWorkspace, SessionExt, UserSessionData},
+    utilities::Result,
+};
+
+pub(crate) mod db;
+mod routes;
+mod templates;
+
+pub use routes::router;
+
+/// The workspace the user switched to along with their role in it, checked against
+/// `action`. Users that were removed from it get switched back to their personal tasks.
+pub async fn current_workspace(
+    pool: &PgPool,
+    session: &Session,
+    user: &UserSessionData,
+    action: WorkspaceAction,
+) -> Result<Option<(CurrentWorkspace, WorkspaceRole)>> {
+    let Some(workspace) = session.current_workspace().await? else {
+        return Ok(None);
+    };
+
+    match authorize_workspace(pool, user, workspace.workspace_id, action).await {
+        Ok(role) => Ok(Some((workspace, role))),
+        Err(Error::NotFound) => {
+            session.set_current_workspace(None).await?;
+            Ok(None)
+        }
+        Err(error) => Err(error),
+    }
+}
@@ -0,0 +1,281 @@
+use axum::{
+    extract::{Path, State},
+    middleware::from_fn,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Extension, Form, Router,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tower_sessions::Session;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::http::users::{self, auth_middleware, CurrentWorkspace, SessionExt, UserSessionData};
+
+use super::super::{
+    authorization::{authorize_workspace, WorkspaceAction, WorkspaceRole},
+    error::Error,
+    layout::{FormErrors, LazyPageContext, PageContext},
+    utilities::{
+        render_invalid_form, render_template, ApiState, FlashMessageLevel, FlashMessages, Result,
+    },
+    validation::{Validate, Validator},
+};
+
+use super::{db, templates::*};
+
+pub fn router() -> Router<ApiState> {
+    Router::new()
+        .route("/", get(workspaces_page).post(create_workspace))
+        .route("/switch", post(switch_workspace))
+        .route("/{workspace_id}", get(workspace_page))
+        .route("/{workspace_id}/members", post(invite_member))
+        .route(
+            "/{workspace_id}/members/{user_id}/delete",
+            post(remove_member),
+        )
+        .route_layer(from_fn(auth_middleware))
+}
+
+const MAX_NAME_CHARS: usize = 100;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct NewWorkspace {
+    pub name: String,
+}
+
+impl Validate for NewWorkspace {
+    fn validate(&self) -> Result<()> {
+        Validator::new()
+            .required("name", &self.name)
+            .max_chars("name", &self.name, MAX_NAME_CHARS)
+            .finish()
+    }
+}
+
+#[instrument(skip_all, fields(action = "displaying workspaces page", %user_session))]
+async fn workspaces_page(
+    State(pool): State<PgPool>,
+    Extension(user_session): Extension<UserSessionData>,
+    ctx: PageContext,
+) -> Result<Html<String>> {
+    render_template(WorkspacesTemplate {
+        ctx,
+        workspaces: db::get_memberships(&pool, user_session.user_id()).await?,
+        errors: FormErrors::default(),
+        form: NewWorkspace::default(),
+    })
+}
+
+#[instrument(
+    skip_all,
+    fields(action = "creating a workspace", %new_workspace.name, %user_session)
+)]
+async fn create_workspace(
+    State(pool): State<PgPool>,
+    Extension(user_session): Extension<UserSessionData>,
+    session: Session,
+    ctx: LazyPageContext,
+    Form(new_workspace): Form<NewWorkspace>,
+) -> Result<Response> {
+    if let Err(error) = new_workspace.validate() {
+        return render_invalid_form(WorkspacesTemplate {
+            ctx: ctx.build().await?,
+            workspaces: db::get_memberships(&pool, user_session.user_id()).await?,
+            errors: error.try_into()?,
+            form: new_workspace,
+        });
+    }
+
+    let name = new_workspace.name.trim();
+    let workspace_id = db::create_workspace(&pool, name, user_session.user_id()).await?;
+    session
+        .set_current_workspace(Some(CurrentWorkspace {
+            workspace_id,
+            name: name.to_string(),
+        }))
+        .await?;
+
+    Ok(Redirect::to(&format!("/workspaces/{workspace_id}")).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct SwitchWorkspace {
+    /// Left out to switch back to the personal tasks.
+    workspace_id: Option<Uuid>,
+}
+
+#[instrument(skip_all, fields(action = "switching workspace", ?switch.workspace_id, %user_session))]
+async fn switch_workspace(
+    State(pool): State<PgPool>,
+    Extension(user_session): Extension<UserSessionData>,
+    session: Session,
+    Form(switch): Form<SwitchWorkspace>,
+) -> Result<Redirect> {
+    let workspace = match switch.workspace_id {
+        Some(workspace_id) => {
+            authorize_workspace(&pool, &user_session, workspace_id, WorkspaceAction::View).await?;
+            let workspace = db::get_workspace(&pool, workspace_id)
+                .await?
+                .ok_or(Error::NotFound)?;
+            Some(CurrentWorkspace {
+                workspace_id: workspace.workspace_id,
+                name: workspace.name,
+            })
+        }
+        None => None,
+    };
+    session.set_current_workspace(workspace).await?;
+
+    Ok(Redirect::to("/todo"))
+}
+
+#[instrument(skip_all, fields(action = "displaying workspace page", %workspace_id, %user_session))]
+async fn workspace_page(
+    State(pool): State<PgPool>,
+    Path(workspace_id): Path<Uuid>,
+    Extension(user_session): Extension<UserSessionData>,
+    ctx: PageContext,
+) -> Result<Html<String>> {
+    let role =
+        authorize_workspace(&pool, &user_session, workspace_id, WorkspaceAction::View).await?;
+    let workspace = db::get_workspace(&pool, workspace_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    render_template(WorkspaceTemplate {
+        ctx,
+        workspace,
+        can_manage: role.allows(WorkspaceAction::ManageMembers),
+        role,
+        members: db::get_members(&pool, workspace_id).await?,
+        user_id: user_session.user_id(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct InviteMember {
+    email: String,
+    role: WorkspaceRole,
+}
+
+/// Adds a registered user to the workspace, inviting someone who already is a member
+/// changes their role. Only owners can hand out or take away the owner role.
+#[instrument(
+    skip_all,
+    fields(action = "inviting a workspace member", %workspace_id, %invite.email, %invite.role, %user_session)
+)]
+async fn invite_member(
+    State(pool): State<PgPool>,
+    Path(workspace_id): Path<Uuid>,
+    Extension(user_session): Extension<UserSessionData>,
+    mut flash_msgs: FlashMessages,
+    Form(invite): Form<InviteMember>,
+) -> Result<Redirect> {
+    authorize_workspace(
+        &pool,
+        &user_session,
+        workspace_id,
+        WorkspaceAction::ManageMembers,
+    )
+    .await?;
+    let workspace_page = Redirect::to(&format!("/workspaces/{workspace_id}"));
+
+    let email = invite.email.trim();
+    let Some(user) = users::db::get_user_by_email(&pool, email).await? else {
+        flash_msgs
+            .set_msg(
+                FlashMessageLevel::Error,
+                &format!("There is no registered user with the email {email}"),
+            )
+            .await?;
+        return Ok(workspace_page);
+    };
+
+    let mut transaction = pool.begin().await?;
+    let owners = db::lock_owners(&mut transaction, workspace_id).await?;
+    let is_owner = owners.contains(&user.user_id);
+    let touches_owner = invite.role == WorkspaceRole::Owner || is_owner;
+    if touches_owner && !owners.contains(&user_session.user_id()) {
+        return Err(Error::Forbidden);
+    }
+    if is_owner && invite.role != WorkspaceRole::Owner && owners.len() == 1 {
+        flash_msgs
+            .set_msg(
+                FlashMessageLevel::Error,
+                "A workspace needs at least one owner",
+            )
+            .await?;
+        return Ok(workspace_page);
+    }
+
+    db::upsert_member(&mut transaction, workspace_id, user.user_id, invite.role).await?;
+    transaction.commit().await?;
+    flash_msgs
+        .set_msg(
+            FlashMessageLevel::Success,
+            &format!(
+                "{} is now a {} of this workspace",
+                user.username, invite.role
+            ),
+        )
+        .await?;
+    Ok(workspace_page)
+}
+
+/// Removes a member, members can always remove themselves to leave the workspace.
+#[instrument(
+    skip_all,
+    fields(action = "removing a workspace member", %workspace_id, %user_id, %user_session)
+)]
+async fn remove_member(
+    State(pool): State<PgPool>,
+    Path((workspace_id, user_id)): Path<(Uuid, Uuid)>,
+    Extension(user_session): Extension<UserSessionData>,
+    session: Session,
+    mut flash_msgs: FlashMessages,
+) -> Result<Redirect> {
+    let leaving = user_id == user_session.user_id();
+    let action = if leaving {
+        WorkspaceAction::View
+    } else {
+        WorkspaceAction::ManageMembers
+    };
+    authorize_workspace(&pool, &user_session, workspace_id, action).await?;
+
+    let mut transaction = pool.begin().await?;
+    let owners = db::lock_owners(&mut transaction, workspace_id).await?;
+    if owners.contains(&user_id) {
+        if !owners.contains(&user_session.user_id()) {
+            return Err(Error::Forbidden);
+        }
+        if owners.len() == 1 {
+            flash_msgs
+                .set_msg(
+                    FlashMessageLevel::Error,
+                    "A workspace needs at least one owner",
+                )
+                .await?;
+            return Ok(Redirect::to(&format!("/workspaces/{workspace_id}")));
+        }
+    }
+
+    db::remove_member(
+        &mut transaction,
+        workspace_id,
+        user_id,
+        user_session.user_id(),
+    )
+    .await?;
+    transaction.commit().await?;
+    if !leaving {
+        return Ok(Redirect::to(&format!("/workspaces/{workspace_id}")));
+    }
+
+    let current = session.current_workspace().await?;
+    if current.is_some_and(|current| current.workspace_id == workspace_id) {
+        session.set_current_workspace(None).await?;
+    }
+    Ok(Redirect::to("/workspaces"))
+}
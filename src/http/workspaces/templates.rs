@@ -0,0 +1,33 @@
+use askama::Template;
+use uuid::Uuid;
+
+use super::super::{
+    authorization::WorkspaceRole,
+    filters,
+    layout::{FormErrors, PageContext},
+};
+use super::{
+    db::{Member, Membership, Workspace},
+    routes::NewWorkspace,
+};
+
+#[derive(Template)]
+#[template(path = "workspaces.html")]
+pub struct WorkspacesTemplate {
+    pub ctx: PageContext,
+    pub workspaces: Vec<Membership>,
+    pub errors: FormErrors,
+    pub form: NewWorkspace,
+}
+
+#[derive(Template)]
+#[template(path = "workspace.html")]
+pub struct WorkspaceTemplate {
+    pub ctx: PageContext,
+    pub workspace: Workspace,
+    pub role: WorkspaceRole,
+    pub can_manage: bool,
+    pub members: Vec<Member>,
+    /// The current user, who can leave the workspace.
+    pub user_id: Uuid,
+}
@@ -1,7 +1,12 @@
+mod cli;
 mod config;
 mod http;
 mod logging;
+mod migrations;
+mod storage;
 
-pub use config::get_config;
+pub use cli::Cli;
+pub use config::{get_config, try_get_config, Attachments, Settings, Storage, Tls, Trash, S3};
 pub use http::serve_app;
 pub use logging::init_tracing_subscriber;
+pub use migrations::run_migrations;
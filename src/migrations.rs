@@ -0,0 +1,18 @@
+use anyhow::Context;
+use sqlx::{migrate::Migrator, PgPool};
+use tracing::{info, instrument};
+
+/// The migrations in `migrations/`, embedded into the binary at compile time.
+///
+/// Locking is left enabled, so the migrator holds a Postgres advisory lock while it
+/// runs and replicas starting at the same time apply the migrations one after another.
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+#[instrument(skip_all)]
+pub async fn run_migrations(pool: &PgPool) -> anyhow::Result<()> {
+    info!("running database migrations");
+    MIGRATOR
+        .run(pool)
+        .await
+        .context("Error running database migrations")
+}
@@ -0,0 +1,60 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use anyhow::Context;
+use axum::body::Bytes;
+use tokio::fs;
+
+use super::Storage;
+
+/// Stores every blob as a file below `root`, keys are relative paths.
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        anyhow::ensure!(
+            key.split('/')
+                .all(|segment| !segment.is_empty() && segment != "." && segment != ".."),
+            "invalid storage key {key}"
+        );
+        Ok(self.root.join(key))
+    }
+}
+
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, data: Bytes, _content_type: &str) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Error creating directory {}", parent.display()))?;
+        }
+        fs::write(&path, data)
+            .await
+            .with_context(|| format!("Error writing {}", path.display()))
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Bytes> {
+        let path = self.path(key)?;
+        fs::read(&path)
+            .await
+            .map(Bytes::from)
+            .with_context(|| format!("Error reading {}", path.display()))
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+        match fs::remove_file(&path).await {
+            Err(error) if error.kind() != ErrorKind::NotFound => {
+                Err(error).with_context(|| format!("Error deleting {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
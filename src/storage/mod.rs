@@ -0,0 +1,59 @@
+//! Blob storage for task attachments, either on the local filesystem or in an S3
+//! compatible object store, picked by the `attachments.storage` setting.
+
+use axum::body::Bytes;
+
+use crate::config;
+
+mod local;
+mod s3;
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+/// Keeps blobs under string keys, keys are made of path safe segments joined by `/`.
+pub trait Storage {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> anyhow::Result<()>;
+    async fn get(&self, key: &str) -> anyhow::Result<Bytes>;
+    /// Deleting a blob that doesn't exist is not an error.
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+}
+
+/// The backend chosen in the configuration.
+#[derive(Debug, Clone)]
+pub enum AnyStorage {
+    Local(LocalStorage),
+    S3(S3Storage),
+}
+
+impl AnyStorage {
+    pub fn from_config(config: &config::Storage) -> anyhow::Result<Self> {
+        Ok(match config {
+            config::Storage::Local { path } => Self::Local(LocalStorage::new(path.clone())),
+            config::Storage::S3(s3) => Self::S3(S3Storage::new(s3)?),
+        })
+    }
+}
+
+impl Storage for AnyStorage {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Local(storage) => storage.put(key, data, content_type).await,
+            Self::S3(storage) => storage.put(key, data, content_type).await,
+        }
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Bytes> {
+        match self {
+            Self::Local(storage) => storage.get(key).await,
+            Self::S3(storage) => storage.get(key).await,
+        }
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Local(storage) => storage.delete(key).await,
+            Self::S3(storage) => storage.delete(key).await,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::body::Bytes;
+use reqwest::{header::CONTENT_TYPE, StatusCode, Url};
+use rusty_s3::{Bucket, Credentials, S3Action, UrlStyle};
+use secrecy::ExposeSecret;
+
+use crate::config;
+
+use super::Storage;
+
+/// How long the presigned URLs of a request are valid for, they're used right away.
+const SIGNATURE_DURATION: Duration = Duration::from_secs(60);
+
+/// Talks to an S3 compatible object store with presigned URLs, so any store that
+/// understands AWS signature v4 query authentication works.
+#[derive(Debug, Clone)]
+pub struct S3Storage {
+    client: reqwest::Client,
+    bucket: Bucket,
+    credentials: Credentials,
+}
+
+impl S3Storage {
+    pub fn new(config: &config::S3) -> anyhow::Result<Self> {
+        let endpoint: Url = config
+            .endpoint
+            .parse()
+            .context("The S3 endpoint should be a valid URL")?;
+        let url_style = if config.path_style {
+            UrlStyle::Path
+        } else {
+            UrlStyle::VirtualHost
+        };
+        let bucket = Bucket::new(
+            endpoint,
+            url_style,
+            config.bucket.clone(),
+            config.region.clone(),
+        )
+        .context("Error setting up the S3 bucket")?;
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            bucket,
+            credentials: Credentials::new(
+                config.access_key.clone(),
+                config.secret_key.expose_secret().to_owned(),
+            ),
+        })
+    }
+}
+
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> anyhow::Result<()> {
+        let url = self
+            .bucket
+            .put_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_DURATION);
+        self.client
+            .put(url)
+            .header(CONTENT_TYPE, content_type)
+            .body(data)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Error uploading {key} to S3"))?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Bytes> {
+        let url = self
+            .bucket
+            .get_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_DURATION);
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Error downloading {key} from S3"))?;
+        response
+            .bytes()
+            .await
+            .with_context(|| format!("Error reading {key} from S3"))
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let url = self
+            .bucket
+            .delete_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_DURATION);
+        let response = self
+            .client
+            .delete(url)
+            .send()
+            .await
+            .with_context(|| format!("Error deleting {key} from S3"))?;
+        if response.status() != StatusCode::NOT_FOUND {
+            response
+                .error_for_status()
+                .with_context(|| format!("Error deleting {key} from S3"))?;
+        }
+        Ok(())
+    }
+}
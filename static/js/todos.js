@@ -0,0 +1,234 @@
+// Function to delete a todo
+function deleteTodo(taskId) {
+  if (confirm('Move this todo to the trash?')) {
+    fetch('/todo/' + taskId, {
+      method: 'DELETE'
+    })
+    .then(response => {
+      if (response.ok) {
+        window.location.reload();
+      } else if (response.status === 403) {
+        alert('You are not allowed to delete this todo.');
+      } else {
+        alert('Failed to delete the todo. Please try again.');
+      }
+    })
+    .catch(error => {
+      console.error('Error:', error);
+      alert('An error occurred while trying to delete the todo.');
+    });
+  }
+}
+
+// Function to save where a dragged todo was dropped, relative to its new neighbour
+function reorderTodo(taskId, placement, anchorId) {
+  fetch('/todo/' + taskId + '/reorder', {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
+    body: new URLSearchParams([[placement, anchorId]])
+  })
+  .then(response => {
+    if (!response.ok) {
+      alert('Failed to move the todo. Please try again.');
+      window.location.reload();
+    }
+  })
+  .catch(error => {
+    console.error('Error:', error);
+    alert('An error occurred while trying to move the todo.');
+    window.location.reload();
+  });
+}
+
+// Function to let todos be dragged into a new order within their list
+function enableReordering(list) {
+  let dragged = null;
+
+  list.addEventListener('dragstart', function(event) {
+    dragged = event.target.closest('.todo-item');
+    event.dataTransfer.effectAllowed = 'move';
+    dragged.classList.add('dragging');
+  });
+
+  list.addEventListener('dragend', function() {
+    if (dragged) {
+      dragged.classList.remove('dragging');
+    }
+    dragged = null;
+  });
+
+  list.addEventListener('dragover', function(event) {
+    const target = event.target.closest('.todo-item');
+    if (dragged && target && target !== dragged && target.parentNode === list) {
+      event.preventDefault();
+    }
+  });
+
+  list.addEventListener('drop', function(event) {
+    const target = event.target.closest('.todo-item');
+    if (!dragged || !target || target === dragged) {
+      return;
+    }
+    event.preventDefault();
+
+    // Dropping on the lower half of a todo puts the dragged one after it
+    const box = target.getBoundingClientRect();
+    const after = event.clientY > box.top + box.height / 2;
+    list.insertBefore(dragged, after ? target.nextSibling : target);
+    reorderTodo(
+      dragged.getAttribute('data-task-id'),
+      after ? 'after' : 'before',
+      target.getAttribute('data-task-id')
+    );
+  });
+}
+
+// Function to update the counts of both lists and say when one is empty
+function updateCounts() {
+  const lists = [
+    ['pending-list', 'pending-count', 'No pending tasks. Great job!'],
+    ['completed-list', 'completed-count', 'No completed tasks yet.']
+  ];
+  lists.forEach(function([listId, countId, emptyText]) {
+    const list = document.getElementById(listId);
+    const count = list.querySelectorAll('.todo-item').length;
+    document.getElementById(countId).textContent = count;
+
+    const emptyMessage = list.querySelector('.empty-list');
+    if (count === 0 && !emptyMessage) {
+      const message = document.createElement('li');
+      message.className = 'empty-list';
+      message.textContent = emptyText;
+      list.appendChild(message);
+    } else if (count > 0 && emptyMessage) {
+      emptyMessage.remove();
+    }
+  });
+}
+
+// Function to show a flash message with an Undo button, like the ones the server renders
+function showUndo(message, undoToken) {
+  const flash = document.createElement('div');
+  flash.className = 'flash flash-success';
+  flash.textContent = message;
+
+  const form = document.createElement('form');
+  form.className = 'flash-undo';
+  form.action = '/todo/undo';
+  form.method = 'post';
+  const token = document.createElement('input');
+  token.type = 'hidden';
+  token.name = 'token';
+  token.value = undoToken;
+  const button = document.createElement('button');
+  button.type = 'submit';
+  button.className = 'secondary';
+  button.textContent = 'Undo';
+  form.append(token, button);
+  flash.appendChild(form);
+
+  const main = document.querySelector('main');
+  main.insertBefore(flash, main.querySelector('h1'));
+}
+
+// Function to complete or reopen a todo and move it to the other list
+function toggleTodo(item, url) {
+  fetch(url, {
+    method: 'POST',
+    headers: { 'Accept': 'application/json' }
+  })
+  .then(response => {
+    if (!response.ok) {
+      throw new Error('Failed to toggle the todo: ' + response.status);
+    }
+    return response.json();
+  })
+  .then(toggled => {
+    item.setAttribute('data-completed', toggled.completed);
+    const status = item.querySelector('.todo-completed');
+    status.textContent = toggled.completed ? 'Completed' : 'Pending';
+    status.classList.toggle('status-complete', toggled.completed);
+    status.classList.toggle('status-pending', !toggled.completed);
+    item.querySelector('.toggle-button').textContent = toggled.completed ? 'Reopen' : 'Complete';
+
+    const list = document.getElementById(toggled.completed ? 'completed-list' : 'pending-list');
+    list.appendChild(item);
+    updateCounts();
+
+    if (toggled.undo_token) {
+      showUndo('Completed "' + toggled.title + '"', toggled.undo_token);
+    }
+  })
+  .catch(error => {
+    console.error('Error:', error);
+    alert('Failed to update the todo. Please try again.');
+  });
+}
+
+// Function to organize todos when the page loads
+document.addEventListener('DOMContentLoaded', function() {
+  // Inline event handlers are blocked by the Content-Security-Policy,
+  // so the buttons get their listeners here
+  document.querySelectorAll('.delete-button').forEach(function(button) {
+    button.addEventListener('click', function() {
+      deleteTodo(button.getAttribute('data-task-id'));
+    });
+  });
+
+  // The workspace only matters when moving the selected tasks
+  const bulkAction = document.getElementById('bulk-action');
+  if (bulkAction) {
+    const bulkWorkspace = document.getElementById('bulk-workspace');
+    const toggleWorkspace = function() {
+      bulkWorkspace.hidden = bulkAction.value !== 'move';
+    };
+    bulkAction.addEventListener('change', toggleWorkspace);
+    toggleWorkspace();
+
+    // Trashing and moving take being allowed to delete every selected todo
+    const deleteActions = bulkAction.querySelectorAll('option[value="delete"], option[value="move"]');
+    const restrictActions = function() {
+      const canDelete = Array.from(document.querySelectorAll('.bulk-select:checked'))
+        .every(box => box.hasAttribute('data-can-delete'));
+      deleteActions.forEach(function(option) {
+        option.disabled = !canDelete;
+      });
+      if (!canDelete && bulkAction.value !== 'complete') {
+        bulkAction.value = 'complete';
+        toggleWorkspace();
+      }
+    };
+    document.querySelectorAll('.bulk-select').forEach(function(box) {
+      box.addEventListener('change', restrictActions);
+    });
+    restrictActions();
+  }
+
+  // Get references to our sections
+  const pendingList = document.getElementById('pending-list');
+  const completedList = document.getElementById('completed-list');
+
+  // Move each todo item to the list for its completion status
+  document.querySelectorAll('.todo-item').forEach(function(item) {
+    const isCompleted = item.getAttribute('data-completed') === 'true';
+    (isCompleted ? completedList : pendingList).appendChild(item);
+
+    // Make the item visible now that it's in the right list
+    item.style.display = 'flex';
+  });
+  updateCounts();
+
+  // Completing and reopening happens in place, the form is the fallback without scripts
+  document.querySelectorAll('.toggle-form').forEach(function(form) {
+    form.addEventListener('submit', function(event) {
+      event.preventDefault();
+      toggleTodo(form.closest('.todo-item'), form.getAttribute('action'));
+    });
+  });
+
+  enableReordering(pendingList);
+  enableReordering(completedList);
+
+  // Hide loading message
+  document.getElementById('loading-message').style.display = 'none';
+});
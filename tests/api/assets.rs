@@ -0,0 +1,91 @@
+use crate::helpers::TestApp;
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+
+async fn stylesheet_url(app: &TestApp) -> String {
+    let body = app.get_login().await.text().await.unwrap();
+    let start = body
+        .find("/static/css/base.")
+        .expect("login page should link the hashed base stylesheet");
+    let end = start + body[start..].find('"').unwrap();
+    body[start..end].to_string()
+}
+
+#[test]
+async fn hashed_assets_are_cached_and_precompressed(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let url = stylesheet_url(&app).await;
+
+    let response = app
+        .client
+        .get(app.route_url(&url))
+        .header("accept-encoding", "gzip, br")
+        .send()
+        .await
+        .expect("couldn't send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(headers.get("content-encoding").unwrap(), "br");
+    assert_eq!(
+        headers.get("content-type").unwrap(),
+        "text/css; charset=utf-8"
+    );
+    assert!(headers
+        .get("cache-control")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .contains("immutable"));
+
+    let response = app
+        .client
+        .get(app.route_url(&url))
+        .header("accept-encoding", "gzip")
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.headers().get("content-encoding").unwrap(), "gzip");
+
+    let response = app
+        .client
+        .get(app.route_url(&url))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert!(response.headers().get("content-encoding").is_none());
+    assert!(response.text().await.unwrap().contains(".flash-error"));
+}
+
+#[test]
+async fn unchanged_assets_are_not_resent(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let url = stylesheet_url(&app).await;
+
+    let response = app.client.get(app.route_url(&url)).send().await.unwrap();
+    let etag = response.headers().get("etag").unwrap().clone();
+
+    let response = app
+        .client
+        .get(app.route_url(&url))
+        .header("if-none-match", etag)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[test]
+async fn unknown_assets_are_not_found(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let response = app
+        .client
+        .get(app.route_url("/static/css/missing.css"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,194 @@
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn assign(app: &TestApp, task_id: Uuid, title: &str, assignee: &str) -> reqwest::Response {
+    app.client
+        .post(app.route_url(&format!("/todo/{task_id}")))
+        .form(&[
+            ("title", title),
+            ("description", ""),
+            ("assignee_id", assignee),
+        ])
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+#[test]
+async fn tasks_can_be_assigned_to_users_with_access(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let mut assignee = owner.new_client();
+    let assignee_user = assignee.register_test_user().await;
+    assignee.login_test_user(&assignee_user).await;
+
+    let title = Uuid::new_v4().to_string();
+    let task_id = owner.create_task(&title).await;
+    owner
+        .client
+        .post(owner.route_url(&format!("/todo/{task_id}/shares")))
+        .form(&[("user", assignee_user.email.as_str()), ("role", "viewer")])
+        .send()
+        .await
+        .expect("couldn't send request");
+
+    let assignee_id = owner.user_id(&assignee_user.email).await;
+    let response = assign(&owner, task_id, &title, &assignee_id.to_string()).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let assigned = assignee.filtered_todos_page("assigned_to_me").await;
+    assert!(assigned.contains(&title));
+    assert!(assigned.contains(&format!("Assigned to {}", assignee_user.username)));
+    assert!(!assignee
+        .filtered_todos_page("created_by_me")
+        .await
+        .contains(&title));
+    assert!(owner
+        .filtered_todos_page("created_by_me")
+        .await
+        .contains(&title));
+    assert!(!owner
+        .filtered_todos_page("assigned_to_me")
+        .await
+        .contains(&title));
+
+    //unassigning is recorded as well
+    assign(&owner, task_id, &title, "").await;
+    let changes = sqlx::query!(
+        r#"
+        select old_value, new_value, actor_id from task_event
+        where task_id = $1 and field = 'assignee'
+        order by created_at
+        "#,
+        task_id
+    )
+    .fetch_all(&owner.pool)
+    .await
+    .unwrap();
+    let owner_id = owner.user_id(&owner_user.email).await;
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].old_value, None);
+    assert_eq!(
+        changes[0].new_value.as_deref(),
+        Some(assignee_user.username.as_str())
+    );
+    assert_eq!(changes[0].actor_id, Some(owner_id));
+    assert_eq!(changes[1].new_value, None);
+}
+
+#[test]
+async fn tasks_cannot_be_assigned_to_users_without_access(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let mut outsider = owner.new_client();
+    let outsider_user = outsider.register_test_user().await;
+
+    let title = Uuid::new_v4().to_string();
+    let task_id = owner.create_task(&title).await;
+    let outsider_id = owner.user_id(&outsider_user.email).await;
+
+    let response = assign(&owner, task_id, &title, &outsider_id.to_string()).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("the assignee needs access to the task"));
+}
+
+#[test]
+async fn assignees_are_unassigned_when_they_lose_access(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let mut assignee = owner.new_client();
+    let assignee_user = assignee.register_test_user().await;
+    let owner_id = owner.user_id(&owner_user.email).await;
+    let assignee_id = owner.user_id(&assignee_user.email).await;
+
+    //a task that was shared with the assignee
+    let shared_title = Uuid::new_v4().to_string();
+    let shared = owner.create_task(&shared_title).await;
+    owner
+        .client
+        .post(owner.route_url(&format!("/todo/{shared}/shares")))
+        .form(&[("user", assignee_user.email.as_str()), ("role", "viewer")])
+        .send()
+        .await
+        .expect("couldn't send request");
+    assign(&owner, shared, &shared_title, &assignee_id.to_string()).await;
+    owner
+        .client
+        .post(owner.route_url(&format!("/todo/{shared}/shares/{assignee_id}/delete")))
+        .send()
+        .await
+        .expect("couldn't send request");
+
+    //and one in a workspace they were a member of
+    let workspace_id = owner.create_workspace(&Uuid::new_v4().to_string()).await;
+    owner
+        .invite(workspace_id, &assignee_user.email, "member")
+        .await;
+    let workspace_title = Uuid::new_v4().to_string();
+    let in_workspace = owner.create_task(&workspace_title).await;
+    assign(
+        &owner,
+        in_workspace,
+        &workspace_title,
+        &assignee_id.to_string(),
+    )
+    .await;
+    owner
+        .client
+        .post(owner.route_url(&format!(
+            "/workspaces/{workspace_id}/members/{assignee_id}/delete"
+        )))
+        .send()
+        .await
+        .expect("couldn't send request");
+
+    for task_id in [shared, in_workspace] {
+        let assigned =
+            sqlx::query_scalar!("select assignee_id from task where task_id = $1", task_id)
+                .fetch_one(&owner.pool)
+                .await
+                .unwrap();
+        assert_eq!(assigned, None);
+        let unassigned = sqlx::query!(
+            r#"
+            select old_value, new_value, actor_id from task_event
+            where task_id = $1 and field = 'assignee'
+            order by created_at desc limit 1
+            "#,
+            task_id
+        )
+        .fetch_one(&owner.pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            unassigned.old_value.as_deref(),
+            Some(assignee_user.username.as_str())
+        );
+        assert_eq!(unassigned.new_value, None);
+        assert_eq!(unassigned.actor_id, Some(owner_id));
+
+        //the former assignee doesn't hold up other changes
+        let response = owner
+            .client
+            .patch(owner.route_url(&format!("/todo/{task_id}")))
+            .form(&[("completed", "true")])
+            .send()
+            .await
+            .expect("couldn't send request");
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+    assert!(!owner
+        .todos_page()
+        .await
+        .contains(&format!("Assigned to {}", assignee_user.username)));
+}
@@ -0,0 +1,274 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::StatusCode as AxumStatusCode,
+    routing::put,
+    Router,
+};
+use reqwest::{
+    header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    multipart::{Form, Part},
+    StatusCode,
+};
+use sqlx::{test, PgPool};
+use todo_web_app::{get_config, Storage, S3};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+/// The first bytes of a PNG file, enough for its type to be recognised.
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x06\0\0\0";
+
+async fn upload(app: &TestApp, task_id: Uuid, file_name: &str, data: Vec<u8>) -> reqwest::Response {
+    let part = Part::bytes(data)
+        .file_name(file_name.to_string())
+        .mime_str("text/html")
+        .unwrap();
+    app.client
+        .post(app.route_url(&format!("/todo/{task_id}/attachments")))
+        .multipart(Form::new().part("file", part))
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+async fn task_page(app: &TestApp, task_id: Uuid) -> String {
+    app.client
+        .get(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap()
+}
+
+async fn attachment_id(app: &TestApp, file_name: &str) -> Uuid {
+    sqlx::query_scalar!(
+        "select attachment_id from task_attachment where file_name = $1",
+        file_name
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap()
+}
+
+async fn download(app: &TestApp, task_id: Uuid, attachment_id: Uuid) -> reqwest::Response {
+    app.client
+        .get(app.route_url(&format!("/todo/{task_id}/attachments/{attachment_id}")))
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+#[test]
+async fn attachments_are_sniffed_and_only_served_to_users_with_access(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let mut outsider = owner.new_client();
+    let outsider_user = outsider.register_test_user().await;
+    outsider.login_test_user(&outsider_user).await;
+    let task_id = owner.create_task(&Uuid::new_v4().to_string()).await;
+
+    //the browser's claimed content type and the path in the name are ignored
+    let response = upload(&owner, task_id, "C:\\Users\\me\\pixel.png", PNG.to_vec()).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(task_page(&owner, task_id).await.contains("pixel.png"));
+
+    let image_id = attachment_id(&owner, "pixel.png").await;
+    let response = download(&owner, task_id, image_id).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "image/png");
+    assert_eq!(
+        response.headers()[CONTENT_DISPOSITION],
+        "inline; filename*=UTF-8''pixel.png"
+    );
+    assert_eq!(response.bytes().await.unwrap(), PNG);
+
+    upload(&owner, task_id, "notes.html", b"remember the milk".to_vec()).await;
+    let response = download(&owner, task_id, attachment_id(&owner, "notes.html").await).await;
+    assert_eq!(
+        response.headers()[CONTENT_TYPE],
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers()[CONTENT_DISPOSITION],
+        "attachment; filename*=UTF-8''notes.html"
+    );
+
+    assert_eq!(
+        download(&outsider, task_id, image_id).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        upload(&outsider, task_id, "sneaky.txt", b"hi".to_vec())
+            .await
+            .status(),
+        StatusCode::FORBIDDEN
+    );
+
+    let response = owner
+        .client
+        .post(owner.route_url(&format!("/todo/{task_id}/attachments/{image_id}/delete")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(
+        download(&owner, task_id, image_id).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    //uploads and removals are in the task's history
+    let page = task_page(&owner, task_id).await;
+    let timeline = &page[page.find("id=\"history\"").unwrap()..];
+    assert!(timeline.contains("attached &quot;pixel.png&quot;"));
+    assert!(timeline.contains("attached &quot;notes.html&quot;"));
+    assert!(timeline.contains("removed the attachment &quot;pixel.png&quot;"));
+}
+
+#[test]
+async fn uploads_are_limited_in_size_and_by_the_users_quota(pool: PgPool) {
+    let mut config = get_config();
+    config.attachments.storage = Storage::Local {
+        path: std::env::temp_dir().join(format!("todo-attachments-{}", Uuid::new_v4())),
+    };
+    config.attachments.max_file_size = 1024;
+    config.attachments.user_quota = 1536;
+    let mut app = TestApp::with_config(pool, config).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+
+    let response = upload(&app, task_id, "big.txt", vec![b'a'; 2000]).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(task_page(&app, task_id)
+        .await
+        .contains("files can be at most 1.0 KB"));
+
+    upload(&app, task_id, "first.txt", vec![b'a'; 1000]).await;
+    upload(&app, task_id, "second.txt", vec![b'a'; 1000]).await;
+    let page = task_page(&app, task_id).await;
+    assert!(page.contains("first.txt"));
+    assert!(page.contains("Uploading second.txt would take you over your 1.5 KB attachment quota"));
+
+    let stored: Vec<String> = sqlx::query_scalar!("select file_name from task_attachment")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(stored, ["first.txt"]);
+}
+
+#[test]
+async fn concurrent_uploads_cannot_both_fit_under_the_quota(pool: PgPool) {
+    let mut config = get_config();
+    config.attachments.storage = Storage::Local {
+        path: std::env::temp_dir().join(format!("todo-attachments-{}", Uuid::new_v4())),
+    };
+    config.attachments.max_file_size = 1024;
+    config.attachments.user_quota = 1536;
+    let mut app = TestApp::with_config(pool, config).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+
+    tokio::join!(
+        upload(&app, task_id, "first.txt", vec![b'a'; 1000]),
+        upload(&app, task_id, "second.txt", vec![b'a'; 1000]),
+        upload(&app, task_id, "third.txt", vec![b'a'; 1000]),
+    );
+    let stored = sqlx::query_scalar!(r#"select count(*) as "count!" from task_attachment"#)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(stored, 1);
+}
+
+type Objects = Arc<Mutex<HashMap<String, Bytes>>>;
+
+/// Stands in for an S3 compatible object store, only accepting presigned requests.
+async fn spawn_object_store(objects: Objects) -> String {
+    async fn object(
+        State(objects): State<Objects>,
+        Path((bucket, key)): Path<(String, String)>,
+        Query(query): Query<HashMap<String, String>>,
+        method: axum::http::Method,
+        body: Bytes,
+    ) -> (AxumStatusCode, Bytes) {
+        if bucket != "attachments" || !query.contains_key("X-Amz-Signature") {
+            return (AxumStatusCode::FORBIDDEN, Bytes::new());
+        }
+        let mut objects = objects.lock().unwrap();
+        match method.as_str() {
+            "PUT" => {
+                objects.insert(key, body);
+                (AxumStatusCode::OK, Bytes::new())
+            }
+            "GET" => match objects.get(&key) {
+                Some(data) => (AxumStatusCode::OK, data.clone()),
+                None => (AxumStatusCode::NOT_FOUND, Bytes::new()),
+            },
+            _ => {
+                objects.remove(&key);
+                (AxumStatusCode::NO_CONTENT, Bytes::new())
+            }
+        }
+    }
+
+    let router = Router::new()
+        .route("/{bucket}/{*key}", put(object).get(object).delete(object))
+        .with_state(objects);
+    let listener = TcpListener::bind("localhost:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    format!("http://{address}")
+}
+
+#[test]
+async fn attachments_can_be_kept_in_s3_compatible_storage(pool: PgPool) {
+    let objects = Objects::default();
+    let mut config = get_config();
+    config.attachments.storage = Storage::S3(S3 {
+        endpoint: spawn_object_store(objects.clone()).await,
+        bucket: "attachments".into(),
+        region: "us-east-1".into(),
+        access_key: "access".into(),
+        secret_key: "secret".into(),
+        path_style: true,
+    });
+    let mut app = TestApp::with_config(pool, config).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+
+    let response = upload(&app, task_id, "plan.txt", b"step one".to_vec()).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(objects.lock().unwrap().len(), 1);
+
+    let response = download(&app, task_id, attachment_id(&app, "plan.txt").await).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.bytes().await.unwrap(), "step one");
+
+    //purging the task from the trash removes its attachments from the store too
+    app.client
+        .delete(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(objects.lock().unwrap().len(), 1);
+    let response = app
+        .client
+        .post(app.route_url(&format!("/todo/{task_id}/purge")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(objects.lock().unwrap().is_empty());
+}
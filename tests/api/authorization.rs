@@ -0,0 +1,181 @@
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+/// Whether the task is there and not in the trash.
+async fn task_exists(app: &TestApp, task_id: Uuid) -> bool {
+    sqlx::query_scalar!(
+        r#"select exists(select 1 from task where task_id = $1 and deleted_at is null) as "exists!""#,
+        task_id
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap()
+}
+
+#[test]
+async fn tasks_of_other_users_are_forbidden(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let task_id = owner.create_task(&Uuid::new_v4().to_string()).await;
+
+    let mut other = owner.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+
+    let response = other
+        .client
+        .get(other.route_url(&format!("/todo/{task_id}/edit")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = other
+        .client
+        .post(other.route_url(&format!("/todo/{task_id}")))
+        .form(&[("title", "hijacked"), ("description", "")])
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = other
+        .client
+        .delete(other.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(task_exists(&owner, task_id).await);
+
+    //a task that doesn't exist at all is still a 404
+    let response = other
+        .client
+        .get(other.route_url(&format!("/todo/{}/edit", Uuid::new_v4())))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+async fn shared_tasks_can_be_edited_but_not_deleted(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let task_id = owner.create_task(&Uuid::new_v4().to_string()).await;
+
+    let mut other = owner.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+    sqlx::query!(
+        "insert into task_share (task_id, user_id, role) select $1, user_id, 'editor' from users where email = $2",
+        task_id,
+        other_user.email
+    )
+    .execute(&other.pool)
+    .await
+    .unwrap();
+
+    let response = other
+        .client
+        .get(other.route_url(&format!("/todo/{task_id}/edit")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = other
+        .client
+        .delete(other.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(task_exists(&owner, task_id).await);
+}
+
+#[test]
+async fn admins_can_act_on_any_task(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let task_id = owner.create_task(&Uuid::new_v4().to_string()).await;
+
+    let mut admin = owner.new_client();
+    let admin_user = admin.register_test_user().await;
+    sqlx::query!(
+        "update users set is_admin = true where email = $1",
+        admin_user.email
+    )
+    .execute(&admin.pool)
+    .await
+    .unwrap();
+    admin.login_test_user(&admin_user).await;
+
+    let response = admin
+        .client
+        .delete(admin.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!task_exists(&owner, task_id).await);
+
+    //revoking the flag applies to the admin's current session too
+    let task_id = owner.create_task(&Uuid::new_v4().to_string()).await;
+    sqlx::query!(
+        "update users set is_admin = false where email = $1",
+        admin_user.email
+    )
+    .execute(&admin.pool)
+    .await
+    .unwrap();
+    let response = admin
+        .client
+        .delete(admin.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(task_exists(&owner, task_id).await);
+}
+
+#[test]
+async fn every_grant_of_a_user_counts(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let mut admin = owner.new_client();
+    let admin_user = admin.register_test_user().await;
+    sqlx::query!(
+        "update users set is_admin = true where email = $1",
+        admin_user.email
+    )
+    .execute(&admin.pool)
+    .await
+    .unwrap();
+    admin.login_test_user(&admin_user).await;
+
+    let workspace_id = owner.create_workspace(&Uuid::new_v4().to_string()).await;
+    owner
+        .invite(workspace_id, &admin_user.email, "member")
+        .await;
+    let task_id = owner.create_task(&Uuid::new_v4().to_string()).await;
+
+    //being a member lets the admin edit, being an admin lets them manage shares too
+    let edit_page = admin
+        .client
+        .get(admin.route_url(&format!("/todo/{task_id}/edit")))
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap();
+    assert!(edit_page.contains(&format!(r#"action="/todo/{task_id}/shares""#)));
+}
@@ -0,0 +1,163 @@
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::{undo_token, TestApp};
+
+async fn bulk(
+    app: &TestApp,
+    action: &str,
+    task_ids: &[Uuid],
+    workspace_id: Option<Uuid>,
+) -> reqwest::Response {
+    let mut form = vec![("action", action.to_string())];
+    form.extend(task_ids.iter().map(|id| ("task_ids", id.to_string())));
+    form.extend(workspace_id.map(|id| ("workspace_id", id.to_string())));
+    app.client
+        .post(app.route_url("/todo/bulk"))
+        .form(&form)
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+async fn undo(app: &TestApp, page: &str) {
+    let response = app
+        .client
+        .post(app.route_url("/todo/undo"))
+        .form(&[("token", undo_token(page))])
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+}
+
+async fn count_where(app: &TestApp, task_ids: &[Uuid], column: &str) -> i64 {
+    let completed = column == "completed";
+    sqlx::query_scalar!(
+        r#"
+        select count(*) as "count!" from task
+        where task_id = any($1)
+            and case when $2 then completed else deleted_at is not null end
+        "#,
+        task_ids,
+        completed
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap()
+}
+
+#[test]
+async fn selected_tasks_can_be_completed_and_trashed_together(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let mut task_ids = Vec::new();
+    for _ in 0..3 {
+        task_ids.push(app.create_task(&Uuid::new_v4().to_string()).await);
+    }
+
+    let page = app.todos_page().await;
+    assert!(page.contains(r#"action="/todo/bulk""#));
+    assert!(page.contains(&format!(r#"name="task_ids" value="{}""#, task_ids[0])));
+
+    let response = bulk(&app, "complete", &task_ids[..2], None).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(count_where(&app, &task_ids, "completed").await, 2);
+    let page = app.todos_page().await;
+    assert!(page.contains("Completed 2 tasks"));
+
+    undo(&app, &page).await;
+    assert_eq!(count_where(&app, &task_ids, "completed").await, 0);
+
+    bulk(&app, "delete", &task_ids, None).await;
+    assert_eq!(count_where(&app, &task_ids, "deleted").await, 3);
+    let page = app.todos_page().await;
+    assert!(page.contains("Moved 3 tasks to the trash"));
+
+    undo(&app, &page).await;
+    assert_eq!(count_where(&app, &task_ids, "deleted").await, 0);
+
+    bulk(&app, "complete", &[], None).await;
+    assert!(app.todos_page().await.contains("select some tasks first"));
+}
+
+#[test]
+async fn a_batch_is_rejected_as_a_whole_when_any_task_is_off_limits(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let mut other = app.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+    let mine = app.create_task(&Uuid::new_v4().to_string()).await;
+    let theirs = other.create_task(&Uuid::new_v4().to_string()).await;
+
+    bulk(&app, "delete", &[mine, theirs], None).await;
+    assert_eq!(count_where(&app, &[mine, theirs], "deleted").await, 0);
+    assert!(app.todos_page().await.contains("nothing was changed"));
+
+    //moving needs a workspace the user can create tasks in, others are hidden
+    let workspace_name = Uuid::new_v4().to_string();
+    let workspace_id = app.create_workspace(&workspace_name).await;
+    let response = bulk(&other, "move", &[theirs], Some(workspace_id)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    bulk(&app, "move", &[mine], Some(workspace_id)).await;
+    let workspace = || {
+        sqlx::query_scalar!("select workspace_id from task where task_id = $1", mine)
+            .fetch_one(&app.pool)
+    };
+    assert_eq!(workspace().await.unwrap(), Some(workspace_id));
+    let page = app.todos_page().await;
+    assert!(page.contains(&format!("Moved 1 task to {workspace_name}")));
+
+    undo(&app, &page).await;
+    assert_eq!(workspace().await.unwrap(), None);
+}
+
+#[test]
+async fn trashing_and_moving_are_only_offered_for_tasks_that_can_be_deleted(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let mut editor = owner.new_client();
+    let editor_user = editor.register_test_user().await;
+    editor.login_test_user(&editor_user).await;
+    let shared = owner.create_task(&Uuid::new_v4().to_string()).await;
+    owner
+        .client
+        .post(owner.route_url(&format!("/todo/{shared}/shares")))
+        .form(&[("user", editor_user.email.as_str()), ("role", "editor")])
+        .send()
+        .await
+        .expect("couldn't send request");
+
+    //editors can complete the task along with others but not trash or move it
+    let page = editor.todos_page().await;
+    assert!(page.contains(&format!(r#"name="task_ids" value="{shared}""#)));
+    assert!(!page.contains(r#"<option value="delete">"#));
+    assert!(!page.contains(r#"<option value="move">"#));
+
+    let own = editor.create_task(&Uuid::new_v4().to_string()).await;
+    let page = editor.todos_page().await;
+    assert!(page.contains(r#"<option value="delete">"#));
+    let checkbox = |task_id: Uuid| {
+        let start = page
+            .find(&format!(r#"value="{task_id}" form="bulk-form""#))
+            .unwrap();
+        page[start..start + page[start..].find('>').unwrap()].to_string()
+    };
+    assert!(checkbox(own).contains("data-can-delete"));
+    assert!(!checkbox(shared).contains("data-can-delete"));
+
+    //moved tasks go back to their owners' personal tasks
+    let workspace_id = editor.create_workspace(&Uuid::new_v4().to_string()).await;
+    bulk(&editor, "move", &[own], Some(workspace_id)).await;
+    bulk(&editor, "move", &[own], None).await;
+    assert!(editor
+        .todos_page()
+        .await
+        .contains("Moved 1 task to their owners&#x27; personal tasks"));
+}
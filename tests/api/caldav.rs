@@ -0,0 +1,499 @@
+use reqwest::{
+    header::{ETAG, IF_MATCH, IF_NONE_MATCH, WWW_AUTHENTICATE},
+    Method, StatusCode,
+};
+use sqlx::{test, PgPool};
+use time::macros::datetime;
+use uuid::Uuid;
+
+use crate::helpers::{TestApp, TestUser};
+
+async fn dav(
+    app: &TestApp,
+    method: &str,
+    route: &str,
+    (login, password): (&str, &str),
+    headers: &[(&str, &str)],
+    body: &str,
+) -> reqwest::Response {
+    let mut request = app
+        .client
+        .request(
+            Method::from_bytes(method.as_bytes()).unwrap(),
+            app.route_url(route),
+        )
+        .basic_auth(login, Some(password))
+        .body(body.to_string());
+    for &(name, value) in headers {
+        request = request.header(name, value);
+    }
+    request.send().await.expect("couldn't send request")
+}
+
+fn credentials(user: &TestUser) -> (&str, &str) {
+    (&user.username, &user.password)
+}
+
+fn vtodo(uid: &str, summary: &str, status: &str) -> String {
+    format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//test//EN\r\nBEGIN:VTODO\r\nUID:{uid}\r\n\
+         SUMMARY:{summary}\r\nSTATUS:{status}\r\nBEGIN:VALARM\r\nACTION:DISPLAY\r\n\
+         DESCRIPTION:not the task's description\r\nEND:VALARM\r\nEND:VTODO\r\nEND:VCALENDAR\r\n"
+    )
+}
+
+const MULTIGET: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/><c:calendar-data/></d:prop>
+  <d:href>/caldav/tasks/HREF</d:href>
+  <d:href>/caldav/tasks/missing.ics</d:href>
+</c:calendar-multiget>"#;
+
+fn calendar_query(component: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/></d:prop>
+  <c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="{component}"/></c:comp-filter></c:filter>
+</c:calendar-query>"#
+    )
+}
+
+#[test]
+async fn clients_sync_tasks_both_ways(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+
+    let response = dav(
+        &app,
+        "PROPFIND",
+        "/caldav/tasks/",
+        ("nobody", "wrong"),
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(response.headers().contains_key(WWW_AUTHENTICATE));
+
+    let response = dav(
+        &app,
+        "PROPFIND",
+        "/caldav/tasks/",
+        credentials(&user),
+        &[("Depth", "1")],
+        "",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let listing = response.text().await.unwrap();
+    assert!(listing.contains("<c:calendar/>"));
+    assert!(listing.contains(r#"<c:comp name="VTODO"/>"#));
+    assert!(listing.contains("<cs:getctag>"));
+    assert!(listing.contains(&format!("<d:href>/caldav/tasks/{task_id}.ics</d:href>")));
+
+    let multiget = MULTIGET.replace("HREF", &format!("{task_id}.ics"));
+    let response = dav(
+        &app,
+        "REPORT",
+        "/caldav/tasks/",
+        credentials(&user),
+        &[],
+        &multiget,
+    )
+    .await;
+    let report = response.text().await.unwrap();
+    assert!(report.contains(&format!("UID:{task_id}")));
+    assert!(report.contains("<d:getetag>&quot;1&quot;</d:getetag>"));
+    assert!(report.contains("HTTP/1.1 404 Not Found"));
+
+    //a task made in the app
+    let route = "/caldav/tasks/from-the-app.ics";
+    let body = vtodo("app-uid", "Made in the app", "NEEDS-ACTION");
+    let response = dav(
+        &app,
+        "PUT",
+        route,
+        credentials(&user),
+        &[(IF_NONE_MATCH.as_str(), "*")],
+        &body,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert!(app
+        .get_todo()
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains("Made in the app"));
+    let response = dav(
+        &app,
+        "PUT",
+        route,
+        credentials(&user),
+        &[(IF_NONE_MATCH.as_str(), "*")],
+        &body,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+    let response = dav(&app, "GET", route, credentials(&user), &[], "").await;
+    assert_eq!(response.headers()[ETAG], r#""1""#);
+    let data = response.text().await.unwrap();
+    assert!(data.contains("UID:app-uid"));
+    assert!(!data.contains("not the task's description"));
+
+    let completed = vtodo("app-uid", "Made in the app", "COMPLETED");
+    let response = dav(
+        &app,
+        "PUT",
+        route,
+        credentials(&user),
+        &[(IF_MATCH.as_str(), r#""1""#)],
+        &completed,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let stale = dav(
+        &app,
+        "PUT",
+        route,
+        credentials(&user),
+        &[(IF_MATCH.as_str(), r#""1""#)],
+        &body,
+    )
+    .await;
+    assert_eq!(stale.status(), StatusCode::PRECONDITION_FAILED);
+    let completed =
+        sqlx::query_scalar!("select completed from task where caldav_name = 'from-the-app.ics'")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+    assert!(completed);
+
+    let response = dav(
+        &app,
+        "DELETE",
+        route,
+        credentials(&user),
+        &[(IF_MATCH.as_str(), r#""1""#)],
+        "",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+    let response = dav(
+        &app,
+        "DELETE",
+        route,
+        credentials(&user),
+        &[(IF_MATCH.as_str(), r#""2""#)],
+        "",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = dav(&app, "GET", route, credentials(&user), &[], "").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+async fn deleted_names_can_be_reused(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+
+    let route = "/caldav/tasks/reused.ics";
+    let put = |summary: &'static str| {
+        let app = &app;
+        let user = &user;
+        async move {
+            dav(
+                app,
+                "PUT",
+                route,
+                credentials(user),
+                &[(IF_NONE_MATCH.as_str(), "*")],
+                &vtodo(summary, summary, "NEEDS-ACTION"),
+            )
+            .await
+        }
+    };
+    assert_eq!(put("First").await.status(), StatusCode::CREATED);
+    let first_id = sqlx::query_scalar!("select task_id from task where title = 'First'")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    let response = dav(&app, "DELETE", route, credentials(&user), &[], "").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(put("Second").await.status(), StatusCode::CREATED);
+    let response = dav(&app, "GET", route, credentials(&user), &[], "").await;
+    assert!(response.text().await.unwrap().contains("SUMMARY:Second"));
+
+    //the first task is served under its id once it's restored
+    let response = app
+        .client
+        .post(app.route_url(&format!("/todo/{first_id}/restore")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert!(response.status().is_redirection());
+    let response = dav(&app, "GET", route, credentials(&user), &[], "").await;
+    assert!(response.text().await.unwrap().contains("SUMMARY:Second"));
+    let route = format!("/caldav/tasks/{first_id}.ics");
+    let response = dav(&app, "GET", &route, credentials(&user), &[], "").await;
+    assert!(response.text().await.unwrap().contains("SUMMARY:First"));
+}
+
+#[test]
+async fn due_dates_sync_both_ways(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let route = "/caldav/tasks/due.ics";
+    let due = |due: &str| {
+        vtodo("due-uid", "Due soon", "NEEDS-ACTION")
+            .replace("STATUS:", &format!("{due}\r\nSTATUS:"))
+    };
+    let due_at = || {
+        sqlx::query_scalar!("select due_at from task where caldav_name = 'due.ics'")
+            .fetch_one(&app.pool)
+    };
+
+    let response = dav(
+        &app,
+        "PUT",
+        route,
+        credentials(&user),
+        &[],
+        &due("DUE:20301102T140000Z"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(
+        due_at().await.unwrap(),
+        Some(datetime!(2030-11-02 14:00 UTC))
+    );
+    let response = dav(&app, "GET", route, credentials(&user), &[], "").await;
+    let data = response.text().await.unwrap();
+    assert!(data.contains("DUE:20301102T140000Z\r\n"));
+    //CalDAV resources only hold the todo
+    assert!(!data.contains("VEVENT"));
+
+    //all day due dates are due at the start of the day
+    dav(
+        &app,
+        "PUT",
+        route,
+        credentials(&user),
+        &[],
+        &due("DUE;VALUE=DATE:20301103"),
+    )
+    .await;
+    assert_eq!(
+        due_at().await.unwrap(),
+        Some(datetime!(2030-11-03 00:00 UTC))
+    );
+    dav(
+        &app,
+        "PUT",
+        route,
+        credentials(&user),
+        &[],
+        &due("X-NO-DUE:1"),
+    )
+    .await;
+    assert_eq!(due_at().await.unwrap(), None);
+}
+
+#[test]
+async fn collections_only_hold_the_users_own_tasks(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let mut other = app.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+    let mine = app.create_task(&Uuid::new_v4().to_string()).await;
+    let theirs = other.create_task(&Uuid::new_v4().to_string()).await;
+
+    let query = calendar_query("VTODO");
+    let response = dav(
+        &app,
+        "REPORT",
+        "/caldav/tasks/",
+        credentials(&user),
+        &[],
+        &query,
+    )
+    .await;
+    let report = response.text().await.unwrap();
+    assert!(report.contains(&format!("{mine}.ics")));
+    assert!(!report.contains(&format!("{theirs}.ics")));
+
+    let query = calendar_query("VEVENT");
+    let response = dav(
+        &app,
+        "REPORT",
+        "/caldav/tasks/",
+        credentials(&user),
+        &[],
+        &query,
+    )
+    .await;
+    assert!(!response.text().await.unwrap().contains(".ics"));
+
+    let route = format!("/caldav/tasks/{theirs}.ics");
+    let response = dav(&app, "GET", &route, credentials(&user), &[], "").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = dav(&app, "DELETE", &route, credentials(&user), &[], "").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = dav(
+        &app,
+        "PUT",
+        "/caldav/tasks/bad name",
+        credentials(&user),
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+async fn app_passwords_can_be_used_until_revoked(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+
+    let page = app
+        .client
+        .post(app.route_url("/calendar/app-passwords"))
+        .form(&[("name", "Phone")])
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap();
+    let start = page
+        .find(r#"<div class="new-app-password">"#)
+        .expect("the new password should be shown");
+    let start = start
+        + page[start..].find(r#"<p class="feed-link">"#).unwrap()
+        + r#"<p class="feed-link">"#.len();
+    let end = start + page[start..].find('<').unwrap();
+    let password = page[start..end].to_string();
+
+    let propfind = |password: String| {
+        let app = &app;
+        let login = user.email.clone();
+        async move {
+            dav(
+                app,
+                "PROPFIND",
+                "/caldav/",
+                (&login, &password),
+                &[("Depth", "0")],
+                "",
+            )
+            .await
+        }
+    };
+    let response = propfind(password.clone()).await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("<c:calendar-home-set><d:href>/caldav/</d:href>"));
+    let stored = sqlx::query!("select lookup_id, last_used_at from app_password")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert!(password.starts_with(&format!("{}.", stored.lookup_id)));
+    assert!(stored.last_used_at.is_some());
+
+    let page = app
+        .client
+        .get(app.route_url("/calendar"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("Phone"));
+    assert!(!page.contains(&password));
+
+    let app_password_id =
+        sqlx::query_scalar!("select app_password_id from app_password where name = 'Phone'")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+    app.client
+        .post(app.route_url(&format!("/calendar/app-passwords/{app_password_id}/revoke")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(propfind(password).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+async fn repeated_failed_logins_are_throttled(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+
+    let propfind = |password: String| {
+        let app = &app;
+        let login = user.username.clone();
+        async move {
+            dav(
+                app,
+                "PROPFIND",
+                "/caldav/",
+                (&login, &password),
+                &[("Depth", "0")],
+                "",
+            )
+            .await
+            .status()
+        }
+    };
+    //a failure is forgotten after logging in
+    assert_eq!(propfind("wrong".into()).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        propfind(user.password.clone()).await,
+        StatusCode::MULTI_STATUS
+    );
+    for _ in 0..4 {
+        assert_eq!(propfind("wrong".into()).await, StatusCode::UNAUTHORIZED);
+    }
+    assert_eq!(
+        propfind(user.password.clone()).await,
+        StatusCode::MULTI_STATUS
+    );
+
+    for _ in 0..5 {
+        assert_eq!(
+            propfind("wrong.guess".into()).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    assert_eq!(
+        propfind(user.password.clone()).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    sqlx::query!("update users set basic_auth_failed_at = now() - interval '2 minutes'")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        propfind(user.password.clone()).await,
+        StatusCode::MULTI_STATUS
+    );
+}
@@ -0,0 +1,186 @@
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn get(app: &TestApp, route: &str) -> reqwest::Response {
+    app.client
+        .get(app.route_url(route))
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+/// The feed address shown on the calendar page.
+async fn feed_path(app: &TestApp) -> String {
+    let page = get(app, "/calendar").await.text().await.unwrap();
+    let start = page
+        .find(r#"href="/calendar/feed/"#)
+        .expect("the page should link to the feed")
+        + r#"href=""#.len();
+    let end = start + page[start..].find('"').unwrap();
+    page[start..end].to_string()
+}
+
+#[test]
+async fn the_feed_lists_the_users_tasks_as_vtodos(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let mut other = app.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+    let task_id = app.create_task("Milk, eggs; bread").await;
+    let long_title = "a".repeat(100);
+    let done_id = app.create_task(&long_title).await;
+    app.client
+        .post(app.route_url(&format!("/todo/{done_id}/toggle")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    let others_task = other.create_task(&Uuid::new_v4().to_string()).await;
+
+    //calendar apps poll the feed without a session
+    let calendar_app = app.new_client();
+    let response = get(&calendar_app, &feed_path(&app).await).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[CONTENT_TYPE],
+        "text/calendar; charset=utf-8"
+    );
+    let feed = response.text().await.unwrap();
+    assert!(feed.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(feed.ends_with("END:VCALENDAR\r\n"));
+    assert!(feed.contains(&format!("UID:{task_id}\r\n")));
+    assert!(feed.contains(r"SUMMARY:Milk\, eggs\; bread"));
+    assert!(feed.contains(&format!("UID:{done_id}\r\n")));
+    assert!(feed.contains("STATUS:COMPLETED\r\n"));
+    assert!(!feed.contains(&others_task.to_string()));
+    //long lines are folded at 75 octets
+    assert!(feed.lines().all(|line| line.len() <= 75));
+    assert!(feed.contains(&format!(
+        "SUMMARY:{}\r\n {}\r\n",
+        &long_title[..67],
+        &long_title[67..]
+    )));
+}
+
+#[test]
+async fn due_dates_are_in_the_feed_as_events_too(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let task_id = app.create_task("Pay rent").await;
+    let response = app
+        .client
+        .patch(app.route_url(&format!("/todo/{task_id}")))
+        .form(&[("due_at", "2030-02-01T09:00")])
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let undated_id = app.create_task(&Uuid::new_v4().to_string()).await;
+
+    let feed = get(&app.new_client(), &feed_path(&app).await)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(feed.contains("DUE:20300201T090000Z\r\n"));
+    assert_eq!(feed.matches("BEGIN:VTODO").count(), 2);
+    assert_eq!(feed.matches("BEGIN:VEVENT").count(), 1);
+    let event = &feed[feed.find("BEGIN:VEVENT").unwrap()..];
+    let event = &event[..event.find("END:VEVENT").unwrap()];
+    assert!(event.contains(&format!("UID:{task_id}-due\r\n")));
+    assert!(event.contains("DTSTART:20300201T090000Z\r\n"));
+    assert!(event.contains("SUMMARY:Due: Pay rent\r\n"));
+    assert!(event.contains("TRANSP:TRANSPARENT\r\n"));
+    assert!(!feed.contains(&format!("UID:{undated_id}-due")));
+}
+
+#[test]
+async fn regenerating_the_secret_revokes_old_feed_addresses(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let calendar_app = app.new_client();
+    let old_path = feed_path(&app).await;
+    assert_eq!(get(&calendar_app, &old_path).await.status(), StatusCode::OK);
+
+    let signature_end = old_path.rfind('/').unwrap();
+    let flipped = if &old_path[signature_end - 1..signature_end] == "0" {
+        "1"
+    } else {
+        "0"
+    };
+    let tampered = format!(
+        "{}{flipped}{}",
+        &old_path[..signature_end - 1],
+        &old_path[signature_end..]
+    );
+    assert_eq!(
+        get(&calendar_app, &tampered).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let response = app
+        .client
+        .post(app.route_url("/calendar/regenerate"))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let new_path = feed_path(&app).await;
+    assert_ne!(new_path, old_path);
+    assert_eq!(
+        get(&calendar_app, &old_path).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(get(&calendar_app, &new_path).await.status(), StatusCode::OK);
+}
+
+#[test]
+async fn assigned_tasks_leave_the_feed_with_the_access_to_them(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let mut assignee = owner.new_client();
+    let assignee_user = assignee.register_test_user().await;
+    assignee.login_test_user(&assignee_user).await;
+    let assignee_id = owner.user_id(&assignee_user.email).await;
+
+    let title = Uuid::new_v4().to_string();
+    let task_id = owner.create_task(&title).await;
+    owner
+        .client
+        .post(owner.route_url(&format!("/todo/{task_id}/shares")))
+        .form(&[("user", assignee_user.email.as_str()), ("role", "viewer")])
+        .send()
+        .await
+        .expect("couldn't send request");
+    owner
+        .client
+        .post(owner.route_url(&format!("/todo/{task_id}")))
+        .form(&[
+            ("title", title.as_str()),
+            ("description", ""),
+            ("assignee_id", &assignee_id.to_string()),
+        ])
+        .send()
+        .await
+        .expect("couldn't send request");
+    let feed_path = feed_path(&assignee).await;
+    let feed = get(&assignee, &feed_path).await.text().await.unwrap();
+    assert!(feed.contains(&format!("UID:{task_id}\r\n")));
+
+    owner
+        .client
+        .post(owner.route_url(&format!("/todo/{task_id}/shares/{assignee_id}/delete")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    let feed = get(&assignee, &feed_path).await.text().await.unwrap();
+    assert!(!feed.contains(&task_id.to_string()));
+}
@@ -0,0 +1,85 @@
+use clap::Parser;
+use sqlx::{test, PgPool};
+use todo_web_app::{get_config, Cli, Settings};
+use uuid::Uuid;
+
+/// The configuration pointed at the database of the test.
+async fn test_config(pool: &PgPool) -> Settings {
+    let mut config = get_config();
+    config.postgres.db_name = sqlx::query_scalar!(r#"select current_database() as "name!""#)
+        .fetch_one(pool)
+        .await
+        .expect("should be able to get the test database name");
+    config
+}
+
+async fn run(pool: &PgPool, args: &[&str]) -> anyhow::Result<()> {
+    let cli =
+        Cli::try_parse_from(["todo_web_app"].iter().chain(args)).expect("arguments should parse");
+    cli.run_with_config(test_config(pool).await).await
+}
+
+#[test]
+async fn set_admin_grants_and_revokes_the_admin_role(pool: PgPool) {
+    let email = format!("{}@example.com", Uuid::new_v4());
+    let username = Uuid::new_v4().to_string();
+    run(
+        &pool,
+        &[
+            "create-user",
+            "--email",
+            &email,
+            "--username",
+            &username,
+            "--password",
+            "a password",
+        ],
+    )
+    .await
+    .expect("create-user should succeed");
+
+    let is_admin = || {
+        sqlx::query_scalar!("select is_admin from users where email = $1", email).fetch_one(&pool)
+    };
+    assert!(!is_admin().await.unwrap());
+
+    run(&pool, &["set-admin", "--email", &email])
+        .await
+        .expect("set-admin should succeed");
+    assert!(is_admin().await.unwrap());
+
+    run(&pool, &["set-admin", "--email", &email, "--revoke"])
+        .await
+        .expect("set-admin --revoke should succeed");
+    assert!(!is_admin().await.unwrap());
+}
+
+#[test]
+async fn set_admin_fails_for_an_unknown_email(pool: PgPool) {
+    let result = run(&pool, &["set-admin", "--email", "nobody@example.com"]).await;
+
+    assert!(result.is_err());
+}
+
+#[test(migrations = false)]
+async fn migrate_brings_an_empty_database_up_to_date(pool: PgPool) {
+    let applied = || {
+        sqlx::query_scalar::<_, i64>("select count(*) from _sqlx_migrations where success")
+            .fetch_one(&pool)
+    };
+
+    run(&pool, &["migrate"])
+        .await
+        .expect("migrate should succeed on an empty database");
+    let migrations = sqlx::migrate!().iter().count() as i64;
+    assert_eq!(applied().await.unwrap(), migrations);
+    sqlx::query("select task_id from task limit 1")
+        .fetch_optional(&pool)
+        .await
+        .expect("the migrated database should have the task table");
+
+    run(&pool, &["migrate"])
+        .await
+        .expect("migrate should succeed on an up to date database");
+    assert_eq!(applied().await.unwrap(), migrations);
+}
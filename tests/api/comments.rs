@@ -0,0 +1,149 @@
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn comment(
+    app: &TestApp,
+    task_id: Uuid,
+    body: &str,
+    parent_id: Option<Uuid>,
+) -> reqwest::Response {
+    let parent_id = parent_id.map(|id| id.to_string()).unwrap_or_default();
+    app.client
+        .post(app.route_url(&format!("/todo/{task_id}/comments")))
+        .form(&[("body", body), ("parent_id", parent_id.as_str())])
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+async fn task_page(app: &TestApp, task_id: Uuid) -> reqwest::Response {
+    app.client
+        .get(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+async fn comment_id(app: &TestApp, body: &str) -> Uuid {
+    sqlx::query_scalar!("select comment_id from task_comment where body = $1", body)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+}
+
+#[test]
+async fn users_with_access_discuss_a_task_in_threads(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let mut viewer = owner.new_client();
+    let viewer_user = viewer.register_test_user().await;
+    viewer.login_test_user(&viewer_user).await;
+    let mut outsider = owner.new_client();
+    let outsider_user = outsider.register_test_user().await;
+    outsider.login_test_user(&outsider_user).await;
+
+    let title = Uuid::new_v4().to_string();
+    let task_id = owner.create_task(&title).await;
+    owner
+        .client
+        .post(owner.route_url(&format!("/todo/{task_id}/shares")))
+        .form(&[("user", viewer_user.username.as_str()), ("role", "viewer")])
+        .send()
+        .await
+        .expect("couldn't send request");
+
+    let question = Uuid::new_v4().to_string();
+    let response = comment(&owner, task_id, &question, None).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let answer = Uuid::new_v4().to_string();
+    let question_id = comment_id(&owner, &question).await;
+    let response = comment(&viewer, task_id, &answer, Some(question_id)).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let response = task_page(&viewer, task_id).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let page = response.text().await.unwrap();
+    assert!(page.contains(&format!("<h1>{title}</h1>")));
+    let question_at = page.find(&question).unwrap();
+    let answer_at = page.find(&answer).unwrap();
+    assert!(question_at < answer_at);
+    assert!(page[..answer_at].contains("comment depth-1"));
+    //viewers can't edit the task itself
+    assert!(!page.contains("Edit this todo"));
+
+    assert_eq!(
+        task_page(&outsider, task_id).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        comment(&outsider, task_id, "let me in", None)
+            .await
+            .status(),
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[test]
+async fn only_authors_change_their_comments(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let mut admin = owner.new_client();
+    let admin_user = admin.register_test_user().await;
+    sqlx::query!(
+        "update users set is_admin = true where email = $1",
+        admin_user.email
+    )
+    .execute(&admin.pool)
+    .await
+    .unwrap();
+    admin.login_test_user(&admin_user).await;
+
+    let task_id = owner.create_task(&Uuid::new_v4().to_string()).await;
+    let body = Uuid::new_v4().to_string();
+    comment(&owner, task_id, &body, None).await;
+    let id = comment_id(&owner, &body).await;
+
+    //even admins can't edit comments of others
+    let response = admin
+        .client
+        .post(admin.route_url(&format!("/todo/{task_id}/comments/{id}")))
+        .form(&[("body", "rewritten")])
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let edited = Uuid::new_v4().to_string();
+    let response = owner
+        .client
+        .post(owner.route_url(&format!("/todo/{task_id}/comments/{id}")))
+        .form(&[("body", edited.as_str())])
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let page = task_page(&owner, task_id).await.text().await.unwrap();
+    assert!(page.contains(&edited));
+    assert!(page.contains("(edited)"));
+
+    let response = owner
+        .client
+        .post(owner.route_url(&format!("/todo/{task_id}/comments/{id}/delete")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let page = task_page(&owner, task_id).await.text().await.unwrap();
+    assert!(!page.contains(&edited));
+    assert!(page.contains("This comment was deleted."));
+
+    //empty comments are rejected with a flash message
+    comment(&owner, task_id, "   ", None).await;
+    let page = task_page(&owner, task_id).await.text().await.unwrap();
+    assert!(page.contains("body is required"));
+}
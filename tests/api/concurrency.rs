@@ -0,0 +1,136 @@
+use reqwest::{
+    header::{ETAG, IF_MATCH},
+    StatusCode,
+};
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn update(app: &TestApp, task_id: Uuid, form: &[(&str, &str)]) -> reqwest::Response {
+    app.client
+        .post(app.route_url(&format!("/todo/{task_id}")))
+        .form(form)
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+async fn title(app: &TestApp, task_id: Uuid) -> String {
+    sqlx::query_scalar!("select title from task where task_id = $1", task_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+}
+
+#[test]
+async fn edits_made_to_a_stale_version_are_rejected_with_the_current_task(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+
+    let edit_page = app
+        .client
+        .get(app.route_url(&format!("/todo/{task_id}/edit")))
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap();
+    assert!(edit_page.contains(r#"name="version" value="1""#));
+
+    //the first tab saves
+    let response = update(
+        &app,
+        task_id,
+        &[
+            ("title", "first tab"),
+            ("description", ""),
+            ("version", "1"),
+        ],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    //the second tab still has the first version
+    let response = update(
+        &app,
+        task_id,
+        &[
+            ("title", "second tab"),
+            ("description", ""),
+            ("version", "1"),
+        ],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("Someone else changed this todo"));
+    assert!(page.contains("<dd>first tab</dd>"));
+    assert!(page.contains(r#"value="second tab""#));
+    assert!(page.contains(r#"name="version" value="2""#));
+    assert_eq!(title(&app, task_id).await, "first tab");
+
+    //saving again from the conflict page knowingly replaces the other change
+    let response = update(
+        &app,
+        task_id,
+        &[
+            ("title", "second tab"),
+            ("description", ""),
+            ("version", "2"),
+        ],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(title(&app, task_id).await, "second tab");
+}
+
+#[test]
+async fn api_clients_can_update_conditionally_with_etags(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+
+    let response = app
+        .client
+        .get(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    let etag = response.headers()[ETAG].to_str().unwrap().to_string();
+    assert_eq!(etag, r#""1""#);
+
+    let conditional_update = |if_match: &str, title: &str| {
+        app.client
+            .post(app.route_url(&format!("/todo/{task_id}")))
+            .header(IF_MATCH, if_match)
+            .form(&[("title", title), ("description", "")])
+            .send()
+    };
+
+    let response = conditional_update(&etag, "updated").await.unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let response = conditional_update(&etag, "stale").await.unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(title(&app, task_id).await, "updated");
+
+    //weak tags and anything that isn't a tag never match
+    for if_match in [r#"W/"2""#, "2"] {
+        let response = conditional_update(if_match, "not matched").await.unwrap();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+    }
+    assert_eq!(title(&app, task_id).await, "updated");
+
+    let response = conditional_update(r#""1", "2""#, "listed").await.unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(title(&app, task_id).await, "listed");
+
+    let response = conditional_update("*", "anything").await.unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(title(&app, task_id).await, "anything");
+}
@@ -0,0 +1,123 @@
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+use time::macros::datetime;
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn patch(app: &TestApp, task_id: Uuid, form: &[(&str, &str)]) {
+    let response = app
+        .client
+        .patch(app.route_url(&format!("/todo/{task_id}")))
+        .form(form)
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+async fn task_page(app: &TestApp, task_id: Uuid) -> String {
+    app.client
+        .get(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap()
+}
+
+#[test]
+async fn open_tasks_past_their_due_date_are_overdue(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let title = Uuid::new_v4().to_string();
+    let response = app
+        .client
+        .post(app.route_url("/todo"))
+        .form(&[
+            ("title", title.as_str()),
+            ("description", ""),
+            ("due_at", "2020-01-02T09:30"),
+        ])
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let task_id = sqlx::query_scalar!("select task_id from task where title = $1", title)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    let later_id = app.create_task(&Uuid::new_v4().to_string()).await;
+    patch(&app, later_id, &[("due_at", "2999-12-31T23:59")]).await;
+
+    let page = app.todos_page().await;
+    assert!(page.contains(r#"<span class="todo-due overdue">Due 2020-01-02 09:30 UTC</span>"#));
+    assert!(page.contains(r#"<span class="todo-due">Due 2999-12-31 23:59 UTC</span>"#));
+    assert!(task_page(&app, task_id)
+        .await
+        .contains(r#"<span class="overdue">Due 2020-01-02 09:30 UTC</span>"#));
+    let edit_page = app
+        .client
+        .get(app.route_url(&format!("/todo/{task_id}/edit")))
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap();
+    assert!(edit_page.contains(r#"value="2020-01-02T09:30""#));
+
+    let stats = app
+        .client
+        .get(app.route_url("/stats"))
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap();
+    assert!(stats.contains("<dt>Overdue</dt>\n    <dd>1</dd>"));
+
+    //completed tasks are no longer overdue
+    patch(&app, task_id, &[("completed", "true")]).await;
+    assert!(!task_page(&app, task_id).await.contains("class=\"overdue\""));
+    let stats = app
+        .client
+        .get(app.route_url("/stats"))
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap();
+    assert!(stats.contains("<dt>Overdue</dt>\n    <dd>0</dd>"));
+}
+
+#[test]
+async fn due_dates_can_be_changed_and_taken_away(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+
+    patch(&app, task_id, &[("due_at", "2030-05-01T08:00")]).await;
+    patch(&app, task_id, &[("due_at", "2030-05-02T08:00")]).await;
+    patch(&app, task_id, &[("title", "Kept due date")]).await;
+    let due_at = sqlx::query_scalar!("select due_at from task where task_id = $1", task_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(due_at, Some(datetime!(2030-05-02 08:00 UTC)));
+    patch(&app, task_id, &[("due_at", "")]).await;
+
+    let page = task_page(&app, task_id).await;
+    assert!(!page.contains("Due "));
+    let timeline = &page[page.find("id=\"history\"").unwrap()..];
+    assert!(timeline.contains("set the due date to &quot;2030-05-01 08:00 UTC&quot;"));
+    assert!(timeline.contains(
+        "changed the due date from &quot;2030-05-01 08:00 UTC&quot; to &quot;2030-05-02 08:00 UTC&quot;"
+    ));
+    assert!(timeline.contains("cleared the due date, it was &quot;2030-05-02 08:00 UTC&quot;"));
+}
@@ -0,0 +1,99 @@
+use crate::helpers::TestApp;
+use reqwest::StatusCode;
+use serde_json::Value;
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+#[test]
+async fn browsers_get_an_html_error_page_with_the_request_id(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let test_user = app.register_test_user().await;
+    app.login_test_user(&test_user).await;
+
+    let response = app
+        .client
+        .get(app.route_url(&format!("/todo/{}/edit", Uuid::new_v4())))
+        .header("accept", "text/html,application/xhtml+xml,*/*;q=0.8")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(response
+        .headers()
+        .get("content-type")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    let request_id = response.headers()["todo-request-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = response.text().await.unwrap();
+    assert!(body.contains("404 Not Found"));
+    assert!(body.contains(&request_id));
+}
+
+#[test]
+async fn api_clients_get_problem_json_with_the_request_id(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let test_user = app.register_test_user().await;
+    app.login_test_user(&test_user).await;
+
+    let path = format!("/todo/{}/edit", Uuid::new_v4());
+    let response = app
+        .client
+        .get(app.route_url(&path))
+        .header("accept", "application/json")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json"
+    );
+    let request_id = response.headers()["todo-request-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let problem: Value = response.json().await.unwrap();
+    assert_eq!(problem["status"], 404);
+    assert_eq!(problem["title"], "Not Found");
+    assert_eq!(problem["instance"], path);
+    assert_eq!(problem["request_id"], request_id);
+}
+
+#[test]
+async fn api_clients_get_a_401_instead_of_a_login_redirect(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let response = app
+        .client
+        .get(app.route_url("/todo"))
+        .header("accept", "application/json")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let problem: Value = response.json().await.unwrap();
+    assert_eq!(problem["status"], 401);
+}
+
+#[test]
+async fn unknown_routes_get_the_not_found_page(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let response = app
+        .client
+        .get(app.route_url("/does-not-exist"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(response.text().await.unwrap().contains("404 Not Found"));
+}
@@ -4,25 +4,33 @@ use serde::Serialize;
 use serde_json::json;
 use sqlx::PgPool;
 use std::net::SocketAddr;
-use todo_web_app::{get_config, serve_app};
+use todo_web_app::{get_config, serve_app, Settings, Storage};
 use tokio::net::TcpListener;
 use uuid::Uuid;
 
 pub struct TestApp {
     pub client: reqwest::Client,
     pub address: SocketAddr,
+    pub pool: PgPool,
 }
 
 pub struct TestUser {
-    email: String,
-    password: String,
-    username: String,
+    pub email: String,
+    pub password: String,
+    pub username: String,
 }
 
 impl TestApp {
+    /// Attachments are stored in a fresh directory below the temp dir.
     pub async fn new(pool: PgPool) -> Self {
-        let config = get_config();
+        let mut config = get_config();
+        config.attachments.storage = Storage::Local {
+            path: std::env::temp_dir().join(format!("todo-attachments-{}", Uuid::new_v4())),
+        };
+        Self::with_config(pool, config).await
+    }
 
+    pub async fn with_config(pool: PgPool, config: Settings) -> Self {
         let listener = TcpListener::bind("localhost:0")
             .await
             .expect("should be able to bind to a free port on localhost");
@@ -30,15 +38,29 @@ impl TestApp {
             .local_addr()
             .expect("should be able to get the local address");
 
-        let _ = tokio::spawn(serve_app(config, pool, listener));
+        tokio::spawn(serve_app(config, pool.clone(), listener));
 
         Self {
-            client: reqwest::ClientBuilder::new()
-                .redirect(reqwest::redirect::Policy::none())
-                .cookie_store(true)
-                .build()
-                .expect("should be able to build client"),
+            client: Self::build_client(),
             address: addr,
+            pool,
+        }
+    }
+
+    fn build_client() -> reqwest::Client {
+        reqwest::ClientBuilder::new()
+            .redirect(reqwest::redirect::Policy::none())
+            .cookie_store(true)
+            .build()
+            .expect("should be able to build client")
+    }
+
+    /// Another browser talking to the same app, with its own cookies.
+    pub fn new_client(&self) -> Self {
+        Self {
+            client: Self::build_client(),
+            address: self.address,
+            pool: self.pool.clone(),
         }
     }
 
@@ -71,9 +93,82 @@ impl TestApp {
             .expect("could not send get request to /todo")
     }
 
+    pub async fn todos_page(&self) -> String {
+        self.get_todo().await.text().await.unwrap()
+    }
+
+    pub async fn filtered_todos_page(&self, filter: &str) -> String {
+        self.client
+            .get(self.route_url(&format!("/todo?filter={filter}")))
+            .send()
+            .await
+            .expect("could not send get request to /todo")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// Creates a task for the logged in user and returns its id.
+    pub async fn create_task(&self, title: &str) -> Uuid {
+        let response = self
+            .client
+            .post(self.route_url("/todo"))
+            .form(&[("title", title), ("description", "")])
+            .send()
+            .await
+            .expect("could not post new task form");
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+        sqlx::query_scalar!("select task_id from task where title = $1", title)
+            .fetch_one(&self.pool)
+            .await
+            .expect("created task should be in the database")
+    }
+
+    /// Creates a workspace, which also switches the creator to it.
+    pub async fn create_workspace(&self, name: &str) -> Uuid {
+        let response = self
+            .client
+            .post(self.route_url("/workspaces"))
+            .form(&[("name", name)])
+            .send()
+            .await
+            .expect("could not post new workspace form");
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+        let location = response
+            .headers()
+            .get("location")
+            .unwrap()
+            .to_str()
+            .unwrap();
+        location
+            .strip_prefix("/workspaces/")
+            .expect("should redirect to the workspace page")
+            .parse()
+            .unwrap()
+    }
+
+    /// Adds the user to the workspace, or changes their role if they're a member.
+    pub async fn invite(&self, workspace_id: Uuid, email: &str, role: &str) -> Response {
+        self.client
+            .post(self.route_url(&format!("/workspaces/{workspace_id}/members")))
+            .form(&[("email", email), ("role", role)])
+            .send()
+            .await
+            .expect("could not post member form")
+    }
+
+    pub async fn user_id(&self, email: &str) -> Uuid {
+        sqlx::query_scalar!("select user_id from users where email = $1", email)
+            .fetch_one(&self.pool)
+            .await
+            .expect("user should be in the database")
+    }
+
     pub async fn register_test_user(&mut self) -> TestUser {
         let test_user = TestUser {
-            email: Uuid::new_v4().to_string(),
+            email: format!("{}@example.com", Uuid::new_v4()),
             password: Uuid::new_v4().to_string(),
             username: Uuid::new_v4().to_string(),
         };
@@ -81,6 +176,7 @@ impl TestApp {
         let register_form = json!({
             "email": &test_user.email,
             "password": &test_user.password,
+            "confirm_password": &test_user.password,
             "username": &test_user.username
         });
 
@@ -130,3 +226,13 @@ impl TestApp {
         );
     }
 }
+
+/// The token of the first undo button on the page.
+pub fn undo_token(page: &str) -> String {
+    let start = page
+        .find(r#"name="token" value=""#)
+        .expect("the page should have an undo button")
+        + r#"name="token" value=""#.len();
+    let end = start + page[start..].find('"').unwrap();
+    page[start..end].to_string()
+}
@@ -0,0 +1,113 @@
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn update(app: &TestApp, task_id: Uuid, form: &[(&str, &str)]) {
+    let response = app
+        .client
+        .post(app.route_url(&format!("/todo/{task_id}")))
+        .form(form)
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+}
+
+#[test]
+async fn task_changes_are_shown_as_a_timeline(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let title = Uuid::new_v4().to_string();
+    let task_id = app.create_task(&title).await;
+
+    update(
+        &app,
+        task_id,
+        &[
+            ("title", "Renamed"),
+            ("description", "Some details"),
+            ("completed", "true"),
+        ],
+    )
+    .await;
+    update(
+        &app,
+        task_id,
+        &[("title", "Renamed"), ("description", "Some details")],
+    )
+    .await;
+    //saving without changes adds nothing
+    update(
+        &app,
+        task_id,
+        &[("title", "Renamed"), ("description", "Some details")],
+    )
+    .await;
+
+    let page = app
+        .client
+        .get(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap();
+    let timeline = &page[page.find("id=\"history\"").unwrap()..];
+    let entries = [
+        "created the task".to_string(),
+        format!("changed the title from &quot;{title}&quot; to &quot;Renamed&quot;"),
+        "set the description to &quot;Some details&quot;".to_string(),
+        "completed the task".to_string(),
+        "reopened the task".to_string(),
+    ];
+    let mut at = 0;
+    for entry in &entries {
+        let found = timeline[at..].find(entry.as_str()).unwrap_or_else(|| {
+            panic!("{entry} should be in the timeline after the previous entries")
+        });
+        at += found;
+    }
+    assert_eq!(timeline.matches("<li>").count(), entries.len());
+    assert!(timeline.contains(&format!("<strong>{}</strong>", user.username)));
+}
+
+#[test]
+async fn history_outlives_deleted_tasks_and_cant_be_rewritten(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+
+    let response = app
+        .client
+        .delete(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let kinds: Vec<String> = sqlx::query_scalar!(
+        "select kind from task_event where task_id = $1 order by created_at",
+        task_id
+    )
+    .fetch_all(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(kinds, ["created", "deleted"]);
+
+    let rewrite = sqlx::query!(
+        "update task_event set kind = 'created' where task_id = $1",
+        task_id
+    )
+    .execute(&app.pool)
+    .await;
+    assert!(rewrite.is_err());
+    let erase = sqlx::query!("delete from task_event where task_id = $1", task_id)
+        .execute(&app.pool)
+        .await;
+    assert!(erase.is_err());
+}
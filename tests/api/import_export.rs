@@ -0,0 +1,378 @@
+use reqwest::{
+    header::{ACCEPT, CONTENT_DISPOSITION, LOCATION},
+    multipart::{Form, Part},
+    StatusCode,
+};
+use serde_json::Value;
+use sqlx::{test, PgPool};
+use time::macros::datetime;
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn export(app: &TestApp, format: &str) -> reqwest::Response {
+    app.client
+        .get(app.route_url(&format!("/data/export/{format}")))
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+fn upload(file_name: &str, content: &str, dry_run: bool) -> Form {
+    let form = Form::new().part(
+        "file",
+        Part::text(content.to_string()).file_name(file_name.to_string()),
+    );
+    match dry_run {
+        true => form.text("dry_run", "true"),
+        false => form,
+    }
+}
+
+async fn import_json(app: &TestApp, form: Form) -> (StatusCode, Value) {
+    let response = app
+        .client
+        .post(app.route_url("/data/import"))
+        .header(ACCEPT, "application/json")
+        .multipart(form)
+        .send()
+        .await
+        .expect("couldn't send request");
+    (response.status(), response.json().await.unwrap())
+}
+
+async fn task_count(app: &TestApp, title: &str) -> i64 {
+    sqlx::query_scalar!(
+        r#"select count(*) as "count!" from task where title = $1"#,
+        title
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap()
+}
+
+fn statuses(report: &Value) -> Vec<(u64, &str)> {
+    report["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| {
+            (
+                row["row"].as_u64().unwrap(),
+                row["status"].as_str().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+async fn tasks_are_exported_in_every_format(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let mut other = app.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+    app.create_task(r#"Milk, "the good one""#).await;
+    let done = app.create_task("Call the bank").await;
+    app.client
+        .post(app.route_url(&format!("/todo/{done}/toggle")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    let theirs = Uuid::new_v4().to_string();
+    other.create_task(&theirs).await;
+
+    let response = export(&app, "json").await;
+    assert_eq!(
+        response.headers()[CONTENT_DISPOSITION],
+        r#"attachment; filename="todos.json""#
+    );
+    let tasks: Value = response.json().await.unwrap();
+    let tasks = tasks.as_array().unwrap();
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0]["title"], r#"Milk, "the good one""#);
+    assert_eq!(tasks[1]["completed"], true);
+    assert!(tasks[1]["completed_at"].is_string());
+
+    let csv = export(&app, "csv").await.text().await.unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("title,description,completed,created_at,completed_at,due_at")
+    );
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with(r#""Milk, ""the good one""",,false,"#));
+    assert!(!csv.contains(&theirs));
+
+    let todo_txt = export(&app, "todotxt").await.text().await.unwrap();
+    let lines: Vec<&str> = todo_txt.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with(r#" Milk, "the good one""#));
+    assert!(lines[1].starts_with("x "));
+    assert!(lines[1].ends_with(" Call the bank"));
+
+    assert_eq!(export(&app, "xml").await.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+async fn imports_are_previewed_and_made_all_at_once(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    app.create_task("Already here").await;
+    let new_title = Uuid::new_v4().to_string();
+
+    let invalid = format!(
+        "title,completed\r\n{new_title},false\r\nalready here,no\r\n\"{new_title}\",\r\n,true\r\nwrong,maybe\r\n"
+    );
+    let (status, report) = import_json(&app, upload("todos.csv", &invalid, true)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["dry_run"], true);
+    assert_eq!(
+        statuses(&report),
+        [
+            (2, "new"),
+            (3, "duplicate"),
+            (4, "duplicate"),
+            (5, "invalid"),
+            (6, "invalid")
+        ]
+    );
+    assert_eq!(report["rows"][3]["errors"][0], "title is required");
+
+    //invalid rows stop the whole import
+    let (status, report) = import_json(&app, upload("todos.csv", &invalid, false)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(report["imported"], false);
+    assert_eq!(task_count(&app, &new_title).await, 0);
+
+    let valid = format!("title,description\r\n{new_title},\"two\nlines\"\r\nAlready here,\r\n");
+    let (status, report) = import_json(&app, upload("todos.csv", &valid, false)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["imported"], true);
+    assert_eq!(report["new"], 1);
+    assert_eq!(report["duplicates"], 1);
+    assert_eq!(task_count(&app, &new_title).await, 1);
+
+    let (status, _) = import_json(&app, upload("todos.csv", "\"never closed", false)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[test]
+async fn todo_txt_and_json_files_can_be_imported(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    app.create_task("Round trip").await;
+    let exported = export(&app, "json").await.text().await.unwrap();
+
+    let mut other = app.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+    let (_, report) = import_json(&other, upload("todos.json", &exported, false)).await;
+    assert_eq!(report["new"], 1);
+    let (_, report) = import_json(&other, upload("todos.json", &exported, false)).await;
+    assert_eq!(report["new"], 0);
+    assert_eq!(report["duplicates"], 1);
+
+    let todo_txt = "x 2026-10-02 2026-10-01 Pay rent +home\n\n(A) 2026-10-01 Book flights @phone\n";
+    let preview = other
+        .client
+        .post(other.route_url("/data/import"))
+        .multipart(upload("todo.txt", todo_txt, true))
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap();
+    assert!(preview.contains("<td>Pay rent +home</td>"));
+    assert!(preview.contains("<td>Book flights @phone</td>"));
+    assert!(preview.contains("Import 2 todos"));
+    assert_eq!(task_count(&other, "Book flights @phone").await, 0);
+
+    //confirming the preview sends the file back
+    let response = other
+        .client
+        .post(other.route_url("/data/import"))
+        .multipart(
+            Form::new()
+                .text("format", "todotxt")
+                .text("content", todo_txt),
+        )
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(response.headers()[LOCATION], "/todo");
+    assert!(other
+        .get_todo()
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains("Imported 2 tasks"));
+    let completed =
+        sqlx::query_scalar!("select completed from task where title = 'Pay rent +home'")
+            .fetch_one(&other.pool)
+            .await
+            .unwrap();
+    assert!(completed);
+}
+
+#[test]
+async fn reimporting_a_todo_txt_export_finds_every_task(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    app.create_task("Plain").await;
+    let described = app.create_task("Described").await;
+    sqlx::query!(
+        "update task set description = 'todo.txt has no room for this' where task_id = $1",
+        described
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    let todo_txt = export(&app, "todotxt").await.text().await.unwrap();
+    let (status, report) = import_json(&app, upload("todo.txt", &todo_txt, false)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["new"], 0);
+    assert_eq!(report["duplicates"], 2);
+    assert_eq!(task_count(&app, "Described").await, 1);
+}
+
+#[test]
+async fn csv_exports_dont_run_as_spreadsheet_formulas(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    app.create_task("=HYPERLINK(\"http://evil.test\")").await;
+    app.create_task("-1 degrees").await;
+    app.create_task("'quoted").await;
+
+    let csv = export(&app, "csv").await.text().await.unwrap();
+    assert!(csv.contains("\"'=HYPERLINK(\"\"http://evil.test\"\")\""));
+    assert!(csv.contains("\r\n'-1 degrees,"));
+    assert!(csv.contains("\r\n'quoted,"));
+
+    let (status, report) = import_json(&app, upload("todos.csv", &csv, false)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["new"], 0);
+    assert_eq!(report["duplicates"], 3);
+}
+
+#[test]
+async fn todo_txt_titles_starting_with_a_date_keep_it(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    app.create_task("2026-12-24 dinner").await;
+    let completed = app.create_task("2026-12-31 party").await;
+    sqlx::query!(
+        "update task set completed = true where task_id = $1",
+        completed
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    let todo_txt = export(&app, "todotxt").await.text().await.unwrap();
+    let mut other = app.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+    let (status, report) = import_json(&other, upload("todo.txt", &todo_txt, false)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["new"], 2);
+    assert_eq!(task_count(&other, "2026-12-24 dinner").await, 2);
+    assert_eq!(task_count(&other, "2026-12-31 party").await, 2);
+}
+
+#[test]
+async fn workspace_tasks_are_left_out_of_exports_and_imports(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    app.create_task("Personal").await;
+    let task_id = app.create_task("Team standup").await;
+    sqlx::query!(
+        r#"
+        with workspace as (insert into workspace(name) values ('Team') returning workspace_id)
+        update task set workspace_id = (select workspace_id from workspace)
+        where task_id = $1
+        "#,
+        task_id
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    //imports go to the personal tasks, exports come from them
+    let exported = export(&app, "json").await.text().await.unwrap();
+    assert!(!exported.contains("Team standup"));
+    let (status, report) = import_json(&app, upload("todos.json", &exported, false)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["new"], 0);
+    assert_eq!(report["duplicates"], 1);
+    assert_eq!(task_count(&app, "Team standup").await, 1);
+
+    let (_, report) = import_json(&app, upload("todo.txt", "Team standup\n", false)).await;
+    assert_eq!(report["new"], 1);
+    assert_eq!(task_count(&app, "Team standup").await, 2);
+}
+
+#[test]
+async fn imports_keep_the_dates_in_the_file(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+
+    let json = r#"[
+        {"title": "Old", "completed": true, "created_at": "2020-01-01T09:00:00Z", "completed_at": "2020-01-02T10:00:00Z", "due_at": "2020-01-03T12:00:00Z"},
+        {"title": "Undated", "completed": true}
+    ]"#;
+    import_json(&app, upload("todos.json", json, false)).await;
+    let todo_txt = "x 2021-03-04 2021-03-01 Older still\n2021-05-06 Open\n";
+    import_json(&app, upload("todo.txt", todo_txt, false)).await;
+    let csv = "title,due_at\nDue soon,2030-01-01T08:00:00Z\n";
+    import_json(&app, upload("todos.csv", csv, false)).await;
+    let csv = "title,completed,completed_at\nBad date,true,yesterday\n";
+    let (status, report) = import_json(&app, upload("todos.csv", csv, false)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(report["rows"][0]["errors"][0]
+        .as_str()
+        .unwrap()
+        .starts_with("completed_at should be a date and time"));
+
+    let dates = |title: &'static str| {
+        sqlx::query!(
+            "select created_at, completed_at, due_at from task where title = $1",
+            title
+        )
+        .fetch_one(&app.pool)
+    };
+    let old = dates("Old").await.unwrap();
+    assert_eq!(old.created_at, datetime!(2020-01-01 09:00 UTC));
+    assert_eq!(old.completed_at, Some(datetime!(2020-01-02 10:00 UTC)));
+    assert_eq!(old.due_at, Some(datetime!(2020-01-03 12:00 UTC)));
+    let older = dates("Older still").await.unwrap();
+    assert_eq!(older.created_at, datetime!(2021-03-01 00:00 UTC));
+    assert_eq!(older.completed_at, Some(datetime!(2021-03-04 00:00 UTC)));
+    let open = dates("Open").await.unwrap();
+    assert_eq!(open.created_at, datetime!(2021-05-06 00:00 UTC));
+    assert_eq!(open.completed_at, None);
+    assert_eq!(open.due_at, None);
+    let due_soon = dates("Due soon").await.unwrap();
+    assert_eq!(due_soon.due_at, Some(datetime!(2030-01-01 08:00 UTC)));
+    let undated = sqlx::query_scalar!(
+        r#"select completed_at > now() - interval '1 minute' as "recent!" from task where title = 'Undated'"#
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert!(undated);
+}
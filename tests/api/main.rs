@@ -1,4 +1,30 @@
+#![allow(clippy::bool_assert_comparison)]
+
+mod assets;
+mod assignments;
+mod attachments;
+mod authorization;
+mod bulk;
+mod caldav;
+mod calendar;
+mod cli;
+mod comments;
+mod concurrency;
+mod due_dates;
+mod errors;
+mod history;
+mod import_export;
+mod migrations;
+mod ordering;
+mod partial_updates;
+mod security_headers;
+mod sharing;
+mod stats;
 mod tasks;
+mod tls;
+mod trash;
+mod undo;
 mod users;
+mod workspaces;
 
 mod helpers;
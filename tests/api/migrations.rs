@@ -0,0 +1,22 @@
+use sqlx::{test, PgPool};
+use todo_web_app::run_migrations;
+
+#[test(migrations = false)]
+async fn concurrent_migration_runs_all_succeed(pool: PgPool) {
+    let runs = (0..4).map(|_| {
+        let pool = pool.clone();
+        tokio::spawn(async move { run_migrations(&pool).await })
+    });
+
+    for run in runs.collect::<Vec<_>>() {
+        run.await
+            .expect("migration task should not panic")
+            .expect("migrations should apply cleanly");
+    }
+
+    let user_count: i64 = sqlx::query_scalar("select count(*) from users")
+        .fetch_one(&pool)
+        .await
+        .expect("users table should exist after migrating");
+    assert_eq!(user_count, 0);
+}
@@ -0,0 +1,104 @@
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn reorder(app: &TestApp, task_id: Uuid, form: &[(&str, String)]) -> reqwest::Response {
+    app.client
+        .post(app.route_url(&format!("/todo/{task_id}/reorder")))
+        .form(form)
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+/// The tasks in the order they're listed on the todos page.
+async fn listed_order(app: &TestApp, task_ids: &[Uuid]) -> Vec<Uuid> {
+    let page = app.get_todo().await.text().await.unwrap();
+    let mut listed: Vec<(usize, Uuid)> = task_ids
+        .iter()
+        .map(|&task_id| {
+            let at = page
+                .find(&format!("data-task-id=\"{task_id}\""))
+                .expect("the task should be listed");
+            (at, task_id)
+        })
+        .collect();
+    listed.sort();
+    listed.into_iter().map(|(_, task_id)| task_id).collect()
+}
+
+#[test]
+async fn tasks_are_listed_in_the_order_they_were_arranged_in(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let mut tasks = Vec::new();
+    for _ in 0..3 {
+        tasks.push(app.create_task(&Uuid::new_v4().to_string()).await);
+    }
+    let [a, b, c] = tasks[..] else { unreachable!() };
+    assert_eq!(listed_order(&app, &tasks).await, [a, b, c]);
+
+    let response = reorder(&app, c, &[("before", a.to_string())]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(listed_order(&app, &tasks).await, [c, a, b]);
+
+    reorder(&app, c, &[("after", b.to_string())]).await;
+    assert_eq!(listed_order(&app, &tasks).await, [a, b, c]);
+
+    //new tasks go to the end, even after a task was moved there
+    let d = app.create_task(&Uuid::new_v4().to_string()).await;
+    tasks.push(d);
+    assert_eq!(listed_order(&app, &tasks).await, [a, b, c, d]);
+
+    let response = reorder(&app, a, &[]).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[test]
+async fn packed_positions_are_spread_out_to_make_room(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let mut tasks = Vec::new();
+    for _ in 0..3 {
+        tasks.push(app.create_task(&Uuid::new_v4().to_string()).await);
+    }
+    let [a, b, c] = tasks[..] else { unreachable!() };
+    let mut other = app.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+    let others_task = other.create_task(&Uuid::new_v4().to_string()).await;
+    for (position, task_id) in [(10, a), (11, b), (100, c), (5, others_task)] {
+        sqlx::query!(
+            "update task set position = $2 where task_id = $1",
+            task_id,
+            position
+        )
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    }
+
+    reorder(&app, c, &[("after", a.to_string())]).await;
+    assert_eq!(listed_order(&app, &tasks).await, [a, c, b]);
+    let b_position = sqlx::query_scalar!("select position from task where task_id = $1", b)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(b_position, 11, "only the tasks below the gap should move");
+    let others_position =
+        sqlx::query_scalar!("select position from task where task_id = $1", others_task)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        others_position, 5,
+        "other users' lists should be left alone"
+    );
+
+    let response = reorder(&other, a, &[("before", b.to_string())]).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,129 @@
+use reqwest::{
+    header::{ACCEPT, ETAG, IF_MATCH},
+    StatusCode,
+};
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn patch(app: &TestApp, task_id: Uuid, form: &[(&str, &str)]) -> reqwest::Response {
+    app.client
+        .patch(app.route_url(&format!("/todo/{task_id}")))
+        .form(form)
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+struct Stored {
+    title: String,
+    description: Option<String>,
+    completed: bool,
+}
+
+async fn stored(app: &TestApp, task_id: Uuid) -> Stored {
+    sqlx::query_as!(
+        Stored,
+        "select title, description, completed from task where task_id = $1",
+        task_id
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap()
+}
+
+#[test]
+async fn patching_only_changes_the_fields_that_are_sent(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let title = Uuid::new_v4().to_string();
+    let task_id = app.create_task(&title).await;
+    patch(&app, task_id, &[("description", "keep me")]).await;
+
+    let response = patch(&app, task_id, &[("completed", "true")]).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers()[ETAG], r#""3""#);
+    let task = stored(&app, task_id).await;
+    assert!(task.completed);
+    assert_eq!(task.title, title);
+    assert_eq!(task.description.as_deref(), Some("keep me"));
+
+    let response = patch(&app, task_id, &[("title", "")]).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = app
+        .client
+        .patch(app.route_url(&format!("/todo/{task_id}")))
+        .header(IF_MATCH, r#""2""#)
+        .form(&[("title", "stale")])
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(stored(&app, task_id).await.title, title);
+}
+
+#[test]
+async fn tasks_can_be_toggled_in_one_click(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+    let toggle_url = format!("/todo/{task_id}/toggle");
+
+    let page = app.get_todo().await.text().await.unwrap();
+    assert!(page.contains(&format!(r#"action="{toggle_url}""#)));
+
+    let response = app
+        .client
+        .post(app.route_url(&toggle_url))
+        .header(ACCEPT, "application/json")
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_etag = response.headers()[ETAG].clone();
+    let toggled: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(toggled["completed"], true);
+    assert!(stored(&app, task_id).await.completed);
+    assert_eq!(response_etag, r#""2""#);
+
+    //API clients can make the toggle conditional
+    let conditional_toggle = |if_match: &'static str| {
+        app.client
+            .post(app.route_url(&toggle_url))
+            .header(ACCEPT, "application/json")
+            .header(IF_MATCH, if_match)
+            .send()
+    };
+    let response = conditional_toggle(r#""1""#).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+    assert!(stored(&app, task_id).await.completed);
+    let response = conditional_toggle(r#""2""#).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[ETAG], r#""3""#);
+    assert!(!stored(&app, task_id).await.completed);
+
+    //without scripts the form posts and goes back to the list
+    let response = app
+        .client
+        .post(app.route_url(&toggle_url))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(stored(&app, task_id).await.completed);
+
+    let mut other = app.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+    let response = other
+        .client
+        .post(other.route_url(&toggle_url))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
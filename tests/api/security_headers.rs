@@ -0,0 +1,45 @@
+use crate::helpers::TestApp;
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+
+#[test]
+async fn pages_carry_security_headers(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let response = app.get_login().await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(headers.get("x-content-type-options").unwrap(), "nosniff");
+    assert_eq!(headers.get("x-frame-options").unwrap(), "DENY");
+    assert!(headers.get("referrer-policy").is_some());
+    //hsts is only sent over https
+    assert!(headers.get("strict-transport-security").is_none());
+}
+
+#[test]
+async fn stylesheets_carry_the_csp_nonce(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let response = app.get_login().await;
+
+    let csp = response
+        .headers()
+        .get("content-security-policy")
+        .expect("content security policy should be set")
+        .to_str()
+        .unwrap()
+        .to_string();
+    let nonce = csp
+        .split(['\'', ' '])
+        .find_map(|part| part.strip_prefix("nonce-"))
+        .expect("policy should contain a nonce")
+        .to_string();
+
+    let body = response.text().await.unwrap();
+    assert!(body.contains(&format!("nonce=\"{nonce}\"")));
+
+    //every request gets a fresh nonce
+    let other_csp = app.get_login().await.headers()["content-security-policy"].clone();
+    assert_ne!(other_csp.to_str().unwrap(), csp);
+}
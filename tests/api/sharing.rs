@@ -0,0 +1,159 @@
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::{TestApp, TestUser};
+
+async fn owner_and_recipient(pool: PgPool) -> (TestApp, TestUser, TestApp, TestUser) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+
+    let mut recipient = owner.new_client();
+    let recipient_user = recipient.register_test_user().await;
+    recipient.login_test_user(&recipient_user).await;
+
+    (owner, owner_user, recipient, recipient_user)
+}
+
+async fn share(app: &TestApp, task_id: Uuid, user: &str, role: &str) -> reqwest::Response {
+    app.client
+        .post(app.route_url(&format!("/todo/{task_id}/shares")))
+        .form(&[("user", user), ("role", role)])
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+async fn update(app: &TestApp, task_id: Uuid, title: &str) -> reqwest::Response {
+    app.client
+        .post(app.route_url(&format!("/todo/{task_id}")))
+        .form(&[("title", title), ("description", "")])
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+#[test]
+async fn viewers_see_shared_tasks_but_cannot_change_them(pool: PgPool) {
+    let (owner, owner_user, recipient, recipient_user) = owner_and_recipient(pool).await;
+    let title = Uuid::new_v4().to_string();
+    let task_id = owner.create_task(&title).await;
+
+    let response = share(&owner, task_id, &recipient_user.username, "viewer").await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let todos = recipient.get_todo().await.text().await.unwrap();
+    assert!(todos.contains(&title));
+    assert!(todos.contains(&format!("Shared by {} (viewer)", owner_user.username)));
+    assert!(!todos.contains(&format!("/todo/{task_id}/edit")));
+
+    let response = update(&recipient, task_id, "changed").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = recipient
+        .client
+        .delete(recipient.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+async fn editors_can_update_until_the_share_is_removed(pool: PgPool) {
+    let (owner, _, recipient, recipient_user) = owner_and_recipient(pool).await;
+    let task_id = owner.create_task(&Uuid::new_v4().to_string()).await;
+
+    share(&owner, task_id, &recipient_user.email, "editor").await;
+    let edit_page = owner
+        .client
+        .get(owner.route_url(&format!("/todo/{task_id}/edit")))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(edit_page.contains(&format!(
+        "Shared with {} as editor",
+        recipient_user.username
+    )));
+
+    let new_title = Uuid::new_v4().to_string();
+    let response = update(&recipient, task_id, &new_title).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(owner
+        .get_todo()
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains(&new_title));
+
+    //only the owner can manage the shares
+    let response = share(&recipient, task_id, &recipient_user.email, "editor").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let recipient_id = owner.user_id(&recipient_user.email).await;
+    let response = owner
+        .client
+        .post(owner.route_url(&format!("/todo/{task_id}/shares/{recipient_id}/delete")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    assert!(!recipient
+        .get_todo()
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains(&new_title));
+    let response = update(&recipient, task_id, "changed").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+async fn sharing_with_an_unknown_user_shows_an_error(pool: PgPool) {
+    let (owner, _, _, _) = owner_and_recipient(pool).await;
+    let task_id = owner.create_task(&Uuid::new_v4().to_string()).await;
+
+    let response = share(&owner, task_id, "nobody-by-that-name", "viewer").await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let edit_page = owner
+        .client
+        .get(owner.route_url(&format!("/todo/{task_id}/edit")))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(edit_page.contains("There is no user with the username or email nobody-by-that-name"));
+    assert!(edit_page.contains("Not shared with anyone yet."));
+}
+
+#[test]
+async fn workspace_guests_with_an_editor_share_can_edit(pool: PgPool) {
+    let (owner, _, recipient, recipient_user) = owner_and_recipient(pool).await;
+    let workspace_id = owner.create_workspace(&Uuid::new_v4().to_string()).await;
+    owner
+        .invite(workspace_id, &recipient_user.email, "guest")
+        .await;
+    let task_id = owner.create_task(&Uuid::new_v4().to_string()).await;
+    share(&owner, task_id, &recipient_user.username, "editor").await;
+
+    let page = recipient
+        .client
+        .get(recipient.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains(&format!(r#"href="/todo/{task_id}/edit""#)));
+}
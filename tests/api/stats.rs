@@ -0,0 +1,94 @@
+use reqwest::{header::ACCEPT, StatusCode};
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn toggle(app: &TestApp, task_id: Uuid) {
+    let response = app
+        .client
+        .post(app.route_url(&format!("/todo/{task_id}/toggle")))
+        .header(ACCEPT, "application/json")
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+async fn completed_at_is_set(app: &TestApp, task_id: Uuid) -> bool {
+    sqlx::query_scalar!(
+        r#"select completed_at is not null as "set!" from task where task_id = $1"#,
+        task_id
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap()
+}
+
+#[test]
+async fn completing_a_task_records_when_it_was_done(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+    assert!(!completed_at_is_set(&app, task_id).await);
+
+    toggle(&app, task_id).await;
+    assert!(completed_at_is_set(&app, task_id).await);
+    let page = app
+        .client
+        .get(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request")
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("Completed on "));
+
+    toggle(&app, task_id).await;
+    assert!(!completed_at_is_set(&app, task_id).await);
+}
+
+#[test]
+async fn the_stats_page_sums_up_completed_tasks(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let mut tasks = Vec::new();
+    for _ in 0..3 {
+        tasks.push(app.create_task(&Uuid::new_v4().to_string()).await);
+    }
+    toggle(&app, tasks[0]).await;
+    toggle(&app, tasks[1]).await;
+    //one done today and one yesterday, each a day and two hours after it was created
+    for (task_id, days_ago) in [(tasks[0], 0), (tasks[1], 1)] {
+        sqlx::query!(
+            r#"
+            update task
+            set completed_at = now() - make_interval(days => $2),
+                created_at = now() - make_interval(days => $2 + 1, hours => 2)
+            where task_id = $1
+            "#,
+            task_id,
+            days_ago
+        )
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    }
+
+    let response = app
+        .client
+        .get(app.route_url("/stats"))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::OK);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("<dt>Completed</dt>\n    <dd>2</dd>"));
+    assert!(page.contains("<dt>Still open</dt>\n    <dd>1</dd>"));
+    assert!(page.contains("<dd>1 day 2 hours</dd>"));
+    assert!(page.contains("<dd>2 days</dd>"));
+    assert_eq!(page.matches("<meter").count(), 14 + 8);
+}
@@ -47,3 +47,72 @@ async fn creating_a_task_redirects_to_todo_with_the_task(pool: PgPool) {
     assert_eq!(text.contains(&title), true);
     assert_eq!(text.contains(&description), true);
 }
+
+#[test]
+async fn invalid_task_rerenders_the_form_with_errors(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let test_user = app.register_test_user().await;
+    app.login_test_user(&test_user).await;
+
+    let description = Uuid::new_v4().to_string();
+    let response = app
+        .client
+        .post(app.route_url("/todo"))
+        .form(&[("title", "   "), ("description", description.as_str())])
+        .send()
+        .await
+        .expect("couldn't send request");
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let text = response.text().await.unwrap();
+    assert!(text.contains("<form action=\"/todo\""));
+    assert!(text.contains("title is required"));
+    //the user's input is kept
+    assert!(text.contains(&description));
+
+    let todos = app.get_todo().await.text().await.unwrap();
+    assert!(!todos.contains(&description));
+}
+
+#[test]
+async fn overly_long_descriptions_are_rejected(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let test_user = app.register_test_user().await;
+    app.login_test_user(&test_user).await;
+
+    let description = "a".repeat(10_001);
+    let response = app
+        .client
+        .post(app.route_url("/todo"))
+        .form(&[("title", "title"), ("description", description.as_str())])
+        .send()
+        .await
+        .expect("couldn't send request");
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("description must be at most 10000 characters long"));
+}
+
+#[test]
+async fn flash_messages_outlast_forms_that_redirect(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let test_user = app.register_test_user().await;
+    app.login_test_user(&test_user).await;
+    let title = Uuid::new_v4().to_string();
+    let task_id = app.create_task(&title).await;
+
+    app.client
+        .delete(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    //only the page the new task redirects to shows the message
+    app.create_task(&Uuid::new_v4().to_string()).await;
+    let todos = app.todos_page().await;
+    assert!(todos.contains(&format!("Moved &quot;{title}&quot; to the trash")));
+    assert!(todos.contains("Undo"));
+}
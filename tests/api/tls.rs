@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use rcgen::{generate_simple_self_signed, CertifiedKey};
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+use todo_web_app::{get_config, serve_app, Tls};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+struct TlsTestApp {
+    client: reqwest::Client,
+    https_port: u16,
+    http_port: u16,
+}
+
+impl TlsTestApp {
+    async fn new(pool: PgPool) -> Self {
+        let CertifiedKey { cert, signing_key } =
+            generate_simple_self_signed(vec!["localhost".to_string()])
+                .expect("should be able to generate a self signed certificate");
+        let cert_path = write_temp_file(cert.pem());
+        let key_path = write_temp_file(signing_key.serialize_pem());
+
+        let http_port = std::net::TcpListener::bind("localhost:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("should be able to find a free port")
+            .port();
+
+        let mut config = get_config();
+        config.application.tls = Some(Tls {
+            cert_path,
+            key_path,
+            reload_interval: 1,
+            http_redirect_port: Some(http_port),
+        });
+
+        let listener = TcpListener::bind("localhost:0")
+            .await
+            .expect("should be able to bind to a free port on localhost");
+        let https_port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(serve_app(config, pool, listener));
+
+        let client = reqwest::ClientBuilder::new()
+            .redirect(reqwest::redirect::Policy::none())
+            .danger_accept_invalid_certs(true)
+            .build()
+            .expect("should be able to build client");
+
+        //the redirect listener is bound before the https server accepts its first connection
+        client
+            .get(format!("https://localhost:{https_port}/"))
+            .send()
+            .await
+            .expect("https server should come up");
+
+        Self {
+            client,
+            https_port,
+            http_port,
+        }
+    }
+}
+
+fn write_temp_file(contents: String) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}.pem", Uuid::new_v4()));
+    std::fs::write(&path, contents).expect("should be able to write to the temp dir");
+    path
+}
+
+#[test]
+async fn https_sets_secure_session_cookies_and_hsts(pool: PgPool) {
+    let app = TlsTestApp::new(pool).await;
+
+    let response = app
+        .client
+        .get(format!("https://localhost:{}/users/login", app.https_port))
+        .send()
+        .await
+        .expect("couldn't send request over https");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = response
+        .headers()
+        .get("set-cookie")
+        .expect("session cookie should be set")
+        .to_str()
+        .unwrap();
+    assert!(cookie.contains("Secure"));
+    assert!(response
+        .headers()
+        .get("strict-transport-security")
+        .is_some());
+}
+
+#[test]
+async fn plain_http_redirects_to_https(pool: PgPool) {
+    let app = TlsTestApp::new(pool).await;
+
+    let response = app
+        .client
+        .get(format!("http://localhost:{}/todo?page=2", app.http_port))
+        .send()
+        .await
+        .expect("couldn't send request over http");
+
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(
+        response
+            .headers()
+            .get("location")
+            .unwrap()
+            .to_str()
+            .unwrap(),
+        format!("https://localhost:{}/todo?page=2", app.https_port)
+    );
+}
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use reqwest::{
+    multipart::{Form, Part},
+    StatusCode,
+};
+use sqlx::{test, PgPool};
+use todo_web_app::{get_config, Storage};
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn get(app: &TestApp, route: &str) -> reqwest::Response {
+    app.client
+        .get(app.route_url(route))
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+async fn post(app: &TestApp, route: &str) -> reqwest::Response {
+    app.client
+        .post(app.route_url(route))
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+async fn delete(app: &TestApp, task_id: Uuid) -> reqwest::Response {
+    app.client
+        .delete(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+#[test]
+async fn deleted_tasks_can_be_restored_or_purged_from_the_trash(pool: PgPool) {
+    let mut owner = TestApp::new(pool).await;
+    let owner_user = owner.register_test_user().await;
+    owner.login_test_user(&owner_user).await;
+    let mut other = owner.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+
+    let title = Uuid::new_v4().to_string();
+    let task_id = owner.create_task(&title).await;
+    assert_eq!(delete(&owner, task_id).await.status(), StatusCode::OK);
+
+    let todos = owner.get_todo().await.text().await.unwrap();
+    assert!(!todos.contains(&format!("href=\"/todo/{task_id}\"")));
+    assert_eq!(
+        get(&owner, &format!("/todo/{task_id}")).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    //deleting again is a 404, the task is already gone
+    assert_eq!(
+        delete(&owner, task_id).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    let trash = get(&owner, "/todo/trash").await.text().await.unwrap();
+    assert!(trash.contains(&title));
+    assert!(trash.contains(&format!("deleted by {}", owner_user.username)));
+    assert!(!get(&other, "/todo/trash")
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains(&title));
+
+    assert_eq!(
+        post(&other, &format!("/todo/{task_id}/restore"))
+            .await
+            .status(),
+        StatusCode::FORBIDDEN
+    );
+    let response = post(&owner, &format!("/todo/{task_id}/restore")).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(owner
+        .get_todo()
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains(&title));
+    assert_eq!(
+        post(&owner, &format!("/todo/{task_id}/purge"))
+            .await
+            .status(),
+        StatusCode::NOT_FOUND
+    );
+
+    delete(&owner, task_id).await;
+    let response = post(&owner, &format!("/todo/{task_id}/purge")).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(!get(&owner, "/todo/trash")
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains(&format!("/todo/{task_id}/restore")));
+    let remaining = sqlx::query_scalar!("select count(*) from task where task_id = $1", task_id)
+        .fetch_one(&owner.pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, Some(0));
+
+    let kinds: Vec<String> = sqlx::query_scalar!(
+        "select kind from task_event where task_id = $1 order by created_at",
+        task_id
+    )
+    .fetch_all(&owner.pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        kinds,
+        ["created", "deleted", "restored", "deleted", "purged"]
+    );
+}
+
+#[test]
+async fn tasks_are_purged_after_the_retention_period(pool: PgPool) {
+    let mut config = get_config();
+    config.trash.retention_days = 7;
+    config.trash.purge_interval = 1;
+    let storage = std::env::temp_dir().join(format!("todo-attachments-{}", Uuid::new_v4()));
+    config.attachments.storage = Storage::Local {
+        path: storage.clone(),
+    };
+    let mut app = TestApp::with_config(pool, config).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+
+    let expired = app.create_task(&Uuid::new_v4().to_string()).await;
+    let recent = app.create_task(&Uuid::new_v4().to_string()).await;
+    let mut blobs = Vec::new();
+    for task_id in [expired, recent] {
+        let part = Part::bytes(b"notes".to_vec()).file_name("notes.txt");
+        app.client
+            .post(app.route_url(&format!("/todo/{task_id}/attachments")))
+            .multipart(Form::new().part("file", part))
+            .send()
+            .await
+            .expect("couldn't send request");
+        let storage_key = sqlx::query_scalar!(
+            "select storage_key from task_attachment where task_id = $1",
+            task_id
+        )
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+        blobs.push(storage.join(storage_key));
+    }
+    delete(&app, expired).await;
+    delete(&app, recent).await;
+    sqlx::query!(
+        "update task set deleted_at = now() - interval '8 days' where task_id = $1",
+        expired
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    let mut remaining = Vec::new();
+    for _ in 0..50 {
+        remaining = sqlx::query_scalar!("select task_id from task")
+            .fetch_all(&app.pool)
+            .await
+            .unwrap();
+        //the blobs are deleted after the tasks
+        if remaining.len() == 1 && !blobs[0].exists() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(remaining, [recent]);
+    //only the purged task's attachment goes from the store
+    assert!(!blobs[0].exists());
+    assert!(blobs[1].exists());
+
+    let expired_events = sqlx::query_scalar!(
+        "select count(*) from task_event where task_id = $1 and kind = 'expired' and actor_id is null",
+        expired
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(expired_events, Some(1));
+}
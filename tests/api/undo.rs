@@ -0,0 +1,173 @@
+use reqwest::{header::ACCEPT, StatusCode};
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::{undo_token, TestApp};
+
+async fn undo(app: &TestApp, token: &str) -> reqwest::Response {
+    app.client
+        .post(app.route_url("/todo/undo"))
+        .form(&[("token", token)])
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+async fn is_completed(app: &TestApp, task_id: Uuid) -> bool {
+    sqlx::query_scalar!("select completed from task where task_id = $1", task_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+}
+
+#[test]
+async fn deleting_and_completing_tasks_can_be_undone_once(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let title = Uuid::new_v4().to_string();
+    let task_id = app.create_task(&title).await;
+
+    app.client
+        .delete(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    let page = app.todos_page().await;
+    assert!(page.contains(&format!("Moved &quot;{title}&quot; to the trash")));
+    let token = undo_token(&page);
+
+    let response = undo(&app, &token).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let page = app.todos_page().await;
+    assert!(page.contains("Undone"));
+    assert!(page.contains(&format!("href=\"/todo/{task_id}\"")));
+
+    undo(&app, &token).await;
+    assert!(app.todos_page().await.contains("That was already undone"));
+
+    let response = app
+        .client
+        .post(app.route_url(&format!("/todo/{task_id}")))
+        .form(&[
+            ("title", title.as_str()),
+            ("description", ""),
+            ("completed", "true"),
+        ])
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(is_completed(&app, task_id).await);
+    let page = app.todos_page().await;
+    assert!(page.contains(&format!("Completed &quot;{title}&quot;")));
+
+    undo(&app, &undo_token(&page)).await;
+    assert!(!is_completed(&app, task_id).await);
+}
+
+#[test]
+async fn undo_tokens_only_work_for_their_user_within_the_window(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let mut other = app.new_client();
+    let other_user = other.register_test_user().await;
+    other.login_test_user(&other_user).await;
+    let task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+
+    app.client
+        .delete(app.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    let token = undo_token(&app.todos_page().await);
+
+    let (undo_id, signature) = token.rsplit_once('.').unwrap();
+    let forged = format!("{}.{signature}", Uuid::new_v4());
+    assert_eq!(undo(&app, &forged).await.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        undo(&app, &format!("{undo_id}.00")).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(undo(&other, &token).await.status(), StatusCode::NOT_FOUND);
+
+    sqlx::query!("update task_undo set expires_at = now() - interval '1 second'")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    undo(&app, &token).await;
+    assert!(app
+        .todos_page()
+        .await
+        .contains("It&#x27;s too late to undo that"));
+
+    //still after the next action cleared the expired undo away
+    let other_task_id = app.create_task(&Uuid::new_v4().to_string()).await;
+    app.client
+        .delete(app.route_url(&format!("/todo/{other_task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    let response = undo(&app, &token).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(app
+        .todos_page()
+        .await
+        .contains("It&#x27;s too late to undo that"));
+    let deleted = sqlx::query_scalar!(
+        r#"select deleted_at is not null as "deleted!" from task where task_id = $1"#,
+        task_id
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert!(deleted);
+}
+
+async fn toggle_json(app: &TestApp, task_id: Uuid) -> serde_json::Value {
+    app.client
+        .post(app.route_url(&format!("/todo/{task_id}/toggle")))
+        .header(ACCEPT, "application/json")
+        .send()
+        .await
+        .expect("couldn't send request")
+        .json()
+        .await
+        .unwrap()
+}
+
+#[test]
+async fn completing_a_task_in_one_click_can_be_undone(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    let title = Uuid::new_v4().to_string();
+    let task_id = app.create_task(&title).await;
+
+    let response = app
+        .client
+        .post(app.route_url(&format!("/todo/{task_id}/toggle")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(is_completed(&app, task_id).await);
+    let page = app.todos_page().await;
+    assert!(page.contains(&format!("Completed &quot;{title}&quot;")));
+
+    undo(&app, &undo_token(&page)).await;
+    assert!(!is_completed(&app, task_id).await);
+
+    //scripts get the token along with the new state
+    let toggled = toggle_json(&app, task_id).await;
+    assert_eq!(toggled["completed"], true);
+    undo(&app, toggled["undo_token"].as_str().unwrap()).await;
+    assert!(!is_completed(&app, task_id).await);
+
+    //reopening has nothing to undo
+    toggle_json(&app, task_id).await;
+    let toggled = toggle_json(&app, task_id).await;
+    assert_eq!(toggled["completed"], false);
+    assert!(toggled["undo_token"].is_null());
+}
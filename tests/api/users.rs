@@ -56,3 +56,86 @@ async fn must_be_logged_in_to_access_todo_page(pool: PgPool) {
         "/users/login"
     );
 }
+
+#[test]
+async fn nav_reflects_login_state(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+
+    let home = app
+        .client
+        .get(app.route_url("/"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(home.contains("href=\"/users/login\""));
+    assert!(!home.contains("Logout"));
+
+    let test_user = app.register_test_user().await;
+    app.login_test_user(&test_user).await;
+
+    let home = app
+        .client
+        .get(app.route_url("/"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(home.contains(&test_user.username));
+    assert!(home.contains("Logout"));
+}
+
+#[test]
+async fn invalid_registration_rerenders_the_form_with_errors(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let username = Uuid::new_v4().to_string();
+    let response = app
+        .client
+        .post(app.route_url("/users/register"))
+        .form(&json!({
+            "email": "not-an-email",
+            "username": &username,
+            "password": "long enough password",
+            "confirm_password": "another password"
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let text = response.text().await.unwrap();
+    assert!(text.contains("email is not a valid email address"));
+    assert!(text.contains("passwords don&#x27;t match"));
+    assert!(text.contains(&username));
+}
+
+#[test]
+async fn taken_email_is_reported_on_the_email_field(pool: PgPool) {
+    let mut app = TestApp::new(pool).await;
+    let test_user = app.register_test_user().await;
+
+    let response = app
+        .client
+        .post(app.route_url("/users/register"))
+        .form(&json!({
+            "email": &test_user.email,
+            "username": Uuid::new_v4().to_string(),
+            "password": &test_user.password,
+            "confirm_password": &test_user.password
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("email is already taken"));
+}
@@ -0,0 +1,175 @@
+use reqwest::StatusCode;
+use sqlx::{test, PgPool};
+use uuid::Uuid;
+
+use crate::helpers::{TestApp, TestUser};
+
+async fn logged_in(app: &TestApp) -> (TestApp, TestUser) {
+    let mut app = app.new_client();
+    let user = app.register_test_user().await;
+    app.login_test_user(&user).await;
+    (app, user)
+}
+
+async fn switch(app: &TestApp, workspace_id: Option<Uuid>) -> reqwest::Response {
+    let form: Vec<(&str, String)> = workspace_id
+        .map(|id| ("workspace_id", id.to_string()))
+        .into_iter()
+        .collect();
+    app.client
+        .post(app.route_url("/workspaces/switch"))
+        .form(&form)
+        .send()
+        .await
+        .expect("couldn't send request")
+}
+
+#[test]
+async fn members_work_on_the_tasks_of_their_workspace(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let (owner, owner_user) = logged_in(&app).await;
+    let (member, member_user) = logged_in(&app).await;
+
+    let personal_title = Uuid::new_v4().to_string();
+    owner.create_task(&personal_title).await;
+
+    let workspace_name = Uuid::new_v4().to_string();
+    let workspace_id = owner.create_workspace(&workspace_name).await;
+    let title = Uuid::new_v4().to_string();
+    let task_id = owner.create_task(&title).await;
+
+    let todos = owner.get_todo().await.text().await.unwrap();
+    assert!(todos.contains(&format!("<h1>{workspace_name}</h1>")));
+    assert!(todos.contains(&title));
+    assert!(!todos.contains(&personal_title));
+
+    let response = owner
+        .invite(workspace_id, &member_user.email, "member")
+        .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let response = switch(&member, Some(workspace_id)).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let todos = member.get_todo().await.text().await.unwrap();
+    assert!(todos.contains(&title));
+    assert!(todos.contains(&format!("Created by {}", owner_user.username)));
+
+    let response = member
+        .client
+        .post(member.route_url(&format!("/todo/{task_id}")))
+        .form(&[("title", "renamed by a member"), ("description", "")])
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let response = member
+        .client
+        .delete(member.route_url(&format!("/todo/{task_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    //back in the personal space the workspace's tasks are gone
+    switch(&owner, None).await;
+    let todos = owner.get_todo().await.text().await.unwrap();
+    assert!(todos.contains(&personal_title));
+    assert!(!todos.contains("renamed by a member"));
+}
+
+#[test]
+async fn guests_and_outsiders_are_kept_out(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let (owner, _) = logged_in(&app).await;
+    let (guest, guest_user) = logged_in(&app).await;
+    let (outsider, _) = logged_in(&app).await;
+
+    let workspace_id = owner.create_workspace("team").await;
+    owner.invite(workspace_id, &guest_user.email, "guest").await;
+
+    switch(&guest, Some(workspace_id)).await;
+    let todos = guest.get_todo().await.text().await.unwrap();
+    assert!(!todos.contains("Create New Todo"));
+    let response = guest
+        .client
+        .post(guest.route_url("/todo"))
+        .form(&[("title", "sneaky"), ("description", "")])
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = guest.invite(workspace_id, &guest_user.email, "admin").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = outsider
+        .client
+        .get(outsider.route_url(&format!("/workspaces/{workspace_id}")))
+        .send()
+        .await
+        .expect("couldn't send request");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = switch(&outsider, Some(workspace_id)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+async fn workspaces_keep_at_least_one_owner(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let (owner, owner_user) = logged_in(&app).await;
+    let (admin, admin_user) = logged_in(&app).await;
+
+    let workspace_id = owner.create_workspace("team").await;
+    owner.invite(workspace_id, &admin_user.email, "admin").await;
+
+    //admins can't touch the owner role
+    let response = admin
+        .invite(workspace_id, &owner_user.email, "member")
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    //the only owner can't demote themselves
+    owner
+        .invite(workspace_id, &owner_user.email, "member")
+        .await;
+    let page = owner
+        .client
+        .get(owner.route_url(&format!("/workspaces/{workspace_id}")))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("A workspace needs at least one owner"));
+    assert!(page.contains("Your role in this workspace: owner"));
+}
+
+#[test]
+async fn owners_demoting_each_other_at_once_leave_one_owner(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let (first, first_user) = logged_in(&app).await;
+    let (second, second_user) = logged_in(&app).await;
+
+    let workspace_id = first.create_workspace("team").await;
+    first
+        .invite(workspace_id, &second_user.email, "owner")
+        .await;
+
+    tokio::join!(
+        first.invite(workspace_id, &second_user.email, "member"),
+        second.invite(workspace_id, &first_user.email, "member"),
+    );
+    let owners = sqlx::query_scalar!(
+        r#"
+        select count(*) as "count!" from workspace_member
+        where workspace_id = $1 and role = 'owner'
+        "#,
+        workspace_id
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(owners, 1);
+}